@@ -51,6 +51,11 @@
         # Runtime dependencies
         runtimeDeps = with pkgs; [
           ydotool
+          # Clipboard tools
+          wl-clipboard
+          xclip
+          # OCR for window_ocr
+          tesseract
         ];
 
         # Development tools
@@ -81,7 +86,7 @@
 
           LIBCLANG_PATH = "${pkgs.llvmPackages.libclang.lib}/lib";
 
-          # Wrap binary to include ydotool in PATH
+          # Wrap binary to include ydotool and clipboard tools in PATH
           postInstall = ''
             wrapProgram $out/bin/marionette \
               --prefix PATH : ${pkgs.lib.makeBinPath runtimeDeps}
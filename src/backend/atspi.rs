@@ -0,0 +1,260 @@
+//! Accessibility tree access over AT-SPI2
+//!
+//! Toolkits (GTK, Qt, Firefox, Chromium with accessibility enabled) publish
+//! their widgets on a separate accessibility bus, whose address the session
+//! bus hands out via `org.a11y.Bus`. A window is located by finding the
+//! application with the window's PID (falling back to any application) and
+//! then its top-level frame with a matching title. Element extents are read
+//! in screen coordinates, which are reliable on X11; on Wayland many toolkits
+//! report them relative to the window or not at all.
+
+use zbus::Connection;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+use crate::core::registry::Geometry;
+
+/// Path of the registry's root accessible, whose children are applications
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+
+/// Bus name of the AT-SPI registry daemon
+const REGISTRY_NAME: &str = "org.a11y.atspi.Registry";
+
+const ACCESSIBLE_INTERFACE: &str = "org.a11y.atspi.Accessible";
+const COMPONENT_INTERFACE: &str = "org.a11y.atspi.Component";
+
+/// AT-SPI coordinate type for screen-relative extents
+const COORD_TYPE_SCREEN: u32 = 0;
+
+/// `AtspiStateType` bits for elements that are actually on screen
+const STATE_SHOWING: u32 = 25;
+const STATE_VISIBLE: u32 = 30;
+
+/// Limits on how much of the tree is walked
+#[derive(Debug, Clone, Copy)]
+pub struct TreeLimits {
+    /// Deepest level below the window frame to visit
+    pub max_depth: usize,
+    /// Most elements to visit in total
+    pub max_nodes: usize,
+}
+
+/// One element of a window's accessibility tree
+#[derive(Debug, Clone)]
+pub struct AccessibleNode {
+    /// Role name, e.g. "push button", "text", "menu item"
+    pub role: String,
+    /// Accessible name, usually the visible label
+    pub name: String,
+    /// Extents in screen coordinates, if the element reports any
+    pub bounds: Option<Geometry>,
+    /// Whether the element is visible and showing on screen
+    pub showing: bool,
+    /// Depth below the window frame (the frame is 0)
+    pub depth: usize,
+    /// Indices of child nodes in [`AccessibleTree::nodes`]
+    pub children: Vec<usize>,
+}
+
+/// A window's accessibility tree, flattened in depth-first order
+#[derive(Debug, Clone)]
+pub struct AccessibleTree {
+    /// Nodes in depth-first order; the window frame is first
+    pub nodes: Vec<AccessibleNode>,
+    /// Whether a limit stopped the walk before the whole tree was visited
+    pub truncated: bool,
+}
+
+impl AccessibleTree {
+    /// Showing elements with extents whose role and name match, in tree order
+    ///
+    /// Both matches are case-insensitive substrings, so "button" matches
+    /// "push button" and "toggle button".
+    pub fn find(&self, role: Option<&str>, name: Option<&str>) -> Vec<&AccessibleNode> {
+        let role = role.map(str::to_lowercase);
+        let name = name.map(str::to_lowercase);
+
+        self.nodes
+            .iter()
+            .filter(|node| node.showing && node.bounds.is_some_and(|b| b.width > 0 && b.height > 0))
+            .filter(|node| role.as_ref().is_none_or(|role| node.role.to_lowercase().contains(role)))
+            .filter(|node| name.as_ref().is_none_or(|name| node.name.to_lowercase().contains(name)))
+            .collect()
+    }
+}
+
+/// Connection to the accessibility bus
+pub struct AtspiClient {
+    conn: Connection,
+}
+
+impl AtspiClient {
+    /// Connect to the accessibility bus advertised on the session bus
+    pub async fn connect() -> anyhow::Result<Self> {
+        let session = Connection::session().await?;
+        let reply = session
+            .call_method(Some("org.a11y.Bus"), "/org/a11y/bus", Some("org.a11y.Bus"), "GetAddress", &())
+            .await
+            .map_err(|e| anyhow::anyhow!("Accessibility bus is not available (is at-spi2-core running?): {}", e))?;
+        let address: String = reply.body().deserialize()?;
+
+        let conn = zbus::connection::Builder::address(address.as_str())?.build().await?;
+        Ok(Self { conn })
+    }
+
+    /// Walk the accessibility tree of the window with this PID and title
+    pub async fn window_tree(&self, pid: Option<u32>, title: &str, limits: TreeLimits) -> anyhow::Result<AccessibleTree> {
+        let (bus_name, path) = self.find_frame(pid, title).await?;
+
+        let mut tree = AccessibleTree { nodes: Vec::new(), truncated: false };
+        // Depth-first walk with an explicit stack of (node, depth, parent index)
+        let mut stack = vec![(path, 0usize, None::<usize>)];
+        while let Some((path, depth, parent)) = stack.pop() {
+            if tree.nodes.len() >= limits.max_nodes {
+                tree.truncated = true;
+                break;
+            }
+
+            let node = self.node(&bus_name, &path, depth).await;
+            let index = tree.nodes.len();
+            tree.nodes.push(node);
+            if let Some(parent) = parent {
+                tree.nodes[parent].children.push(index);
+            }
+
+            let children = self.children(&bus_name, &path).await.unwrap_or_default();
+            if depth >= limits.max_depth {
+                tree.truncated |= !children.is_empty();
+                continue;
+            }
+            // Push in reverse so children are visited in order
+            for (child_bus, child_path) in children.into_iter().rev() {
+                // Children normally live in the same application; skip anything that doesn't
+                if child_bus == bus_name {
+                    stack.push((child_path, depth + 1, Some(index)));
+                }
+            }
+        }
+
+        Ok(tree)
+    }
+
+    /// Find the top-level frame of a window: (application bus name, frame path)
+    async fn find_frame(&self, pid: Option<u32>, title: &str) -> anyhow::Result<(String, OwnedObjectPath)> {
+        let applications = self.children(REGISTRY_NAME, &OwnedObjectPath::try_from(ROOT_PATH)?).await?;
+
+        // Prefer applications owned by the window's process
+        let mut candidates = Vec::new();
+        for (bus_name, path) in applications {
+            let app_pid = self.connection_pid(&bus_name).await.ok();
+            let owned = pid.is_some() && app_pid == pid;
+            candidates.push((owned, bus_name, path));
+        }
+        candidates.sort_by_key(|(owned, _, _)| !owned);
+
+        let mut fallback = None;
+        for (owned, bus_name, app_path) in candidates {
+            // Past the owning application, a frame of its own beats a title match elsewhere
+            if !owned && fallback.is_some() {
+                break;
+            }
+            let frames = self.children(&bus_name, &app_path).await.unwrap_or_default();
+            for (frame_bus, frame_path) in frames {
+                let name = self.name(&frame_bus, &frame_path).await.unwrap_or_default();
+                if name == title {
+                    return Ok((frame_bus, frame_path));
+                }
+                // A single-window application owned by the process is a safe guess
+                if owned && fallback.is_none() {
+                    fallback = Some((frame_bus, frame_path));
+                }
+            }
+        }
+
+        fallback.ok_or_else(|| {
+            anyhow::anyhow!(
+                "No accessible window titled '{}' found; the application may not support accessibility or has it disabled",
+                title
+            )
+        })
+    }
+
+    /// Read one element's role, name, state, and extents
+    ///
+    /// Elements that fail a query (e.g. lack the Component interface) still
+    /// produce a node, with the missing parts left empty.
+    async fn node(&self, bus_name: &str, path: &OwnedObjectPath, depth: usize) -> AccessibleNode {
+        let role = self
+            .call::<String>(bus_name, path, ACCESSIBLE_INTERFACE, "GetRoleName", &())
+            .await
+            .unwrap_or_default();
+        let name = self.name(bus_name, path).await.unwrap_or_default();
+
+        let showing = self
+            .call::<Vec<u32>>(bus_name, path, ACCESSIBLE_INTERFACE, "GetState", &())
+            .await
+            .is_ok_and(|states| state_set(&states, STATE_SHOWING) && state_set(&states, STATE_VISIBLE));
+
+        let bounds = self
+            .call::<(i32, i32, i32, i32)>(bus_name, path, COMPONENT_INTERFACE, "GetExtents", &(COORD_TYPE_SCREEN,))
+            .await
+            .ok()
+            .filter(|&(_, _, width, height)| width >= 0 && height >= 0)
+            .map(|(x, y, width, height)| Geometry { x, y, width: width as u32, height: height as u32 });
+
+        AccessibleNode { role, name, bounds, showing, depth, children: Vec::new() }
+    }
+
+    /// Children of an element as (bus name, path) references
+    async fn children(&self, bus_name: &str, path: &OwnedObjectPath) -> anyhow::Result<Vec<(String, OwnedObjectPath)>> {
+        self.call(bus_name, path, ACCESSIBLE_INTERFACE, "GetChildren", &()).await
+    }
+
+    /// An element's accessible name
+    async fn name(&self, bus_name: &str, path: &OwnedObjectPath) -> anyhow::Result<String> {
+        let value: OwnedValue = self
+            .call(bus_name, path, "org.freedesktop.DBus.Properties", "Get", &(ACCESSIBLE_INTERFACE, "Name"))
+            .await?;
+        Ok(String::try_from(value)?)
+    }
+
+    /// PID of the process owning a connection on the accessibility bus
+    async fn connection_pid(&self, bus_name: &str) -> anyhow::Result<u32> {
+        let reply = self
+            .conn
+            .call_method(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                Some("org.freedesktop.DBus"),
+                "GetConnectionUnixProcessID",
+                &(bus_name,),
+            )
+            .await?;
+        Ok(reply.body().deserialize()?)
+    }
+
+    /// Call a method and deserialize its reply
+    async fn call<R>(
+        &self,
+        bus_name: &str,
+        path: &OwnedObjectPath,
+        interface: &str,
+        method: &str,
+        body: &(impl serde::Serialize + zbus::zvariant::DynamicType),
+    ) -> anyhow::Result<R>
+    where
+        R: for<'d> zbus::zvariant::DynamicDeserialize<'d>,
+    {
+        let reply = self
+            .conn
+            .call_method(Some(bus_name), path.as_str(), Some(interface), method, body)
+            .await?;
+        Ok(reply.body().deserialize()?)
+    }
+}
+
+/// Whether a state bit is set in an AT-SPI state set (two 32-bit words)
+fn state_set(states: &[u32], state: u32) -> bool {
+    states
+        .get((state / 32) as usize)
+        .is_some_and(|word| word & (1 << (state % 32)) != 0)
+}
@@ -0,0 +1,332 @@
+//! GNOME Shell backend for GNOME on Wayland
+//!
+//! Like the KWin backend, this wraps the X11 backend for window enumeration
+//! (via XWayland) but activates windows through GNOME Shell itself, since
+//! Mutter ignores focus requests from X11 clients on Wayland.
+//!
+//! Activation first tries `org.gnome.Shell.Eval`, which only works when
+//! unsafe mode is enabled on GNOME 41+. Otherwise it falls back to the
+//! "Window Calls" extension's D-Bus interface.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use zbus::Connection;
+
+use super::{CloseOutcome, DesktopInfo, FocusOutcome, WindowBackend, WindowInfo};
+use crate::core::registry::{Geometry, PlatformWindowId};
+
+/// Object path exported by the Window Calls extension
+const WINDOW_CALLS_PATH: &str = "/org/gnome/Shell/Extensions/Windows";
+
+/// Interface exported by the Window Calls extension
+const WINDOW_CALLS_INTERFACE: &str = "org.gnome.Shell.Extensions.Windows";
+
+/// GNOME Shell backend that uses D-Bus for focus operations
+pub struct GnomeBackend {
+    /// Wrapped X11 backend for listing/geometry operations
+    x11_backend: Arc<dyn WindowBackend>,
+    /// D-Bus connection
+    dbus: Connection,
+}
+
+impl GnomeBackend {
+    /// Create a new GNOME Shell backend
+    pub async fn new(x11_backend: Arc<dyn WindowBackend>) -> anyhow::Result<Self> {
+        let dbus = Connection::session().await?;
+        Ok(Self { x11_backend, dbus })
+    }
+
+    /// Focus a window by evaluating JavaScript inside GNOME Shell
+    ///
+    /// Matches on the X11 window ID rather than the title, so windows sharing a
+    /// title can't be confused.
+    async fn focus_via_eval(&self, xid: u32) -> anyhow::Result<()> {
+        let script = format!(
+            r#"
+            (function() {{
+                var actors = global.get_window_actors();
+                for (var i = 0; i < actors.length; i++) {{
+                    var w = actors[i].get_meta_window();
+                    if (w.get_xwindow && w.get_xwindow() === {}) {{
+                        w.activate(global.get_current_time());
+                        return true;
+                    }}
+                }}
+                return false;
+            }})()
+            "#,
+            xid
+        );
+
+        let reply = self.dbus
+            .call_method(
+                Some("org.gnome.Shell"),
+                "/org/gnome/Shell",
+                Some("org.gnome.Shell"),
+                "Eval",
+                &(script.as_str(),),
+            )
+            .await?;
+
+        let (success, result): (bool, String) = reply.body().deserialize()?;
+
+        // GNOME 41+ returns (false, "") unless unsafe mode is enabled
+        if !success {
+            anyhow::bail!("org.gnome.Shell.Eval is disabled");
+        }
+        if result != "true" {
+            anyhow::bail!("Window not found in GNOME Shell");
+        }
+
+        Ok(())
+    }
+
+    /// Focus a window through the Window Calls extension
+    ///
+    /// The extension doesn't report X11 window IDs, so the window is found by
+    /// title, and a title shared by several windows is refused rather than
+    /// activating whichever comes first.
+    async fn focus_via_extension(&self, window_title: &str) -> anyhow::Result<()> {
+        let reply = self.dbus
+            .call_method(
+                Some("org.gnome.Shell"),
+                WINDOW_CALLS_PATH,
+                Some(WINDOW_CALLS_INTERFACE),
+                "List",
+                &(),
+            )
+            .await?;
+
+        let list: String = reply.body().deserialize()?;
+        let windows: serde_json::Value = serde_json::from_str(&list)?;
+
+        let window_id = unique_title_match(&windows, window_title)?;
+
+        self.dbus
+            .call_method(
+                Some("org.gnome.Shell"),
+                WINDOW_CALLS_PATH,
+                Some(WINDOW_CALLS_INTERFACE),
+                "Activate",
+                &(window_id as u32,),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Focus a window, trying Eval first and then the companion extension
+    async fn focus_via_shell(&self, xid: u32, window_title: &str) -> anyhow::Result<()> {
+        let eval_error = match self.focus_via_eval(xid).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        tracing::debug!("GNOME Shell Eval focus failed, trying extension: {}", eval_error);
+
+        self.focus_via_extension(window_title).await.map_err(|e| {
+            anyhow::anyhow!(
+                "GNOME Shell focus failed ({}; {}). Install the \"Window Calls\" GNOME Shell \
+                 extension (https://extensions.gnome.org/extension/4724/window-calls/) \
+                 so Marionette can activate windows on Wayland",
+                eval_error,
+                e
+            )
+        })
+    }
+}
+
+/// Extension window ID of the only window with this exact title
+fn unique_title_match(windows: &serde_json::Value, title: &str) -> anyhow::Result<u32> {
+    let matches: Vec<&serde_json::Value> = windows
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|w| w["title"].as_str() == Some(title))
+        .collect();
+
+    match matches.as_slice() {
+        [] => anyhow::bail!("Window not found in GNOME Shell"),
+        [window] => window["id"]
+            .as_u64()
+            .map(|id| id as u32)
+            .ok_or_else(|| anyhow::anyhow!("Window Calls listed a window without an id")),
+        _ => anyhow::bail!(
+            "{} windows in GNOME Shell are titled {:?}, so the Window Calls extension can't tell which to focus",
+            matches.len(),
+            title
+        ),
+    }
+}
+
+#[async_trait]
+impl WindowBackend for GnomeBackend {
+    fn name(&self) -> &'static str {
+        "gnome"
+    }
+
+    async fn list_windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
+        // Delegate to X11 backend - this works via XWayland
+        self.x11_backend.list_windows().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<String> {
+        let x11 = self.x11_backend.health_check().await?;
+        if !super::dbus_name_has_owner(&self.dbus, "org.gnome.Shell").await? {
+            anyhow::bail!("{}, but org.gnome.Shell is not on the session bus", x11);
+        }
+
+        // Focus needs either Eval (unsafe mode) or the Window Calls extension
+        let eval = self.dbus
+            .call_method(Some("org.gnome.Shell"), "/org/gnome/Shell", Some("org.gnome.Shell"), "Eval", &("true",))
+            .await
+            .ok()
+            .and_then(|reply| reply.body().deserialize::<(bool, String)>().ok())
+            .is_some_and(|(success, _)| success);
+        if eval {
+            return Ok(format!("{}; org.gnome.Shell.Eval is enabled", x11));
+        }
+
+        let extension = self.dbus
+            .call_method(Some("org.gnome.Shell"), WINDOW_CALLS_PATH, Some(WINDOW_CALLS_INTERFACE), "List", &())
+            .await
+            .is_ok();
+        if extension {
+            return Ok(format!("{}; Window Calls extension is available", x11));
+        }
+
+        anyhow::bail!(
+            "{}, but neither org.gnome.Shell.Eval nor the Window Calls extension is available, so focus will fail",
+            x11
+        )
+    }
+
+    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<FocusOutcome> {
+        // First, get the window title from X11 so we can find it in GNOME Shell
+        let windows = self.x11_backend.list_windows().await?;
+        let window = windows
+            .iter()
+            .find(|w| &w.platform_id == id)
+            .ok_or_else(|| anyhow::anyhow!("Window not found"))?;
+
+        let PlatformWindowId::X11(xid) = window.platform_id else {
+            anyhow::bail!("GNOME backend only tracks X11 windows");
+        };
+
+        match self.focus_via_shell(xid, &window.title).await {
+            Ok(()) => {
+                tracing::debug!("Focused window via GNOME Shell: {}", window.title);
+                Ok(FocusOutcome::unverified())
+            }
+            Err(e) => {
+                tracing::warn!("{}", e);
+                // Fall back to X11 (may only request attention), but keep the actionable error
+                if let Err(x11_error) = self.x11_backend.focus_window(id).await {
+                    tracing::debug!("X11 focus fallback failed: {}", x11_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn move_window(&self, id: &PlatformWindowId, x: i32, y: i32) -> anyhow::Result<()> {
+        // Delegate to X11 backend - this usually works for XWayland windows
+        self.x11_backend.move_window(id, x, y).await
+    }
+
+    async fn resize_window(&self, id: &PlatformWindowId, width: u32, height: u32) -> anyhow::Result<()> {
+        // Delegate to X11 backend
+        self.x11_backend.resize_window(id, width, height).await
+    }
+
+    async fn refresh_window(&self, id: &PlatformWindowId) -> anyhow::Result<Geometry> {
+        self.x11_backend.refresh_window(id).await
+    }
+
+    async fn minimize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        self.x11_backend.minimize_window(id).await
+    }
+
+    async fn maximize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        self.x11_backend.maximize_window(id).await
+    }
+
+    async fn restore_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        self.x11_backend.restore_window(id).await
+    }
+
+    async fn close_window(&self, id: &PlatformWindowId, force: bool) -> anyhow::Result<CloseOutcome> {
+        self.x11_backend.close_window(id, force).await
+    }
+
+    async fn raise_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        self.x11_backend.raise_window(id).await
+    }
+
+    async fn lower_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        self.x11_backend.lower_window(id).await
+    }
+
+    async fn set_always_on_top(&self, id: &PlatformWindowId, enabled: bool) -> anyhow::Result<()> {
+        self.x11_backend.set_always_on_top(id, enabled).await
+    }
+
+    async fn set_fullscreen(&self, id: &PlatformWindowId, enabled: bool) -> anyhow::Result<()> {
+        self.x11_backend.set_fullscreen(id, enabled).await
+    }
+
+    async fn set_opacity(&self, id: &PlatformWindowId, opacity: f64) -> anyhow::Result<()> {
+        self.x11_backend.set_opacity(id, opacity).await
+    }
+
+    async fn cursor_position(&self) -> anyhow::Result<(i32, i32)> {
+        // XWayland only sees the pointer while it is over an X11 window
+        self.x11_backend.cursor_position().await
+    }
+
+    async fn window_icon(&self, id: &PlatformWindowId) -> anyhow::Result<Option<image::RgbaImage>> {
+        self.x11_backend.window_icon(id).await
+    }
+
+    async fn desktop_info(&self) -> anyhow::Result<DesktopInfo> {
+        self.x11_backend.desktop_info().await
+    }
+
+    async fn switch_desktop(&self, desktop: u32) -> anyhow::Result<()> {
+        self.x11_backend.switch_desktop(desktop).await
+    }
+
+    async fn move_window_to_desktop(&self, id: &PlatformWindowId, desktop: u32) -> anyhow::Result<()> {
+        self.x11_backend.move_window_to_desktop(id, desktop).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn unique_title_match_finds_the_single_window() {
+        let windows = json!([
+            { "id": 1, "title": "Terminal" },
+            { "id": 2, "title": "Editor" }
+        ]);
+        assert_eq!(unique_title_match(&windows, "Editor").unwrap(), 2);
+    }
+
+    #[test]
+    fn unique_title_match_refuses_shared_titles() {
+        let windows = json!([
+            { "id": 1, "title": "Terminal" },
+            { "id": 2, "title": "Terminal" }
+        ]);
+        let error = unique_title_match(&windows, "Terminal").unwrap_err().to_string();
+        assert!(error.contains("2 windows"), "{}", error);
+    }
+
+    #[test]
+    fn unique_title_match_reports_missing_window() {
+        let windows = json!([{ "id": 1, "title": "Terminal" }]);
+        assert!(unique_title_match(&windows, "Editor").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Emergency-stop hotkey on X11
+//!
+//! When `MARIONETTE_STOP_HOTKEY` names a key combination, it is grabbed on the
+//! root window with a dedicated connection, so the operator can press it from
+//! any application. Each press toggles a flag that input tools check before
+//! sending anything. Holding the key toggles only once, even while X11
+//! auto-repeat sends more presses. The grab is X11-only: Wayland compositors
+//! don't let clients grab global keys.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::xkb::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{ConnectionExt, GrabMode, Keycode, Keysym, ModMask};
+use x11rb::rust_connection::RustConnection;
+
+/// Grab the hotkey and toggle `stopped` on every press (not repeat), from a background thread
+///
+/// Setup errors (unknown key, hotkey already grabbed by another client) are
+/// returned; once the grab is in place the listener runs until the X
+/// connection closes.
+pub fn spawn_stop_hotkey(combo: &str, stopped: Arc<AtomicBool>) -> anyhow::Result<()> {
+    let (key, modifiers) = crate::input::parse_key_combo(combo, &[])?;
+    let keysym = keysym_value(&crate::input::map_key_to_keysym(&key))
+        .ok_or_else(|| anyhow::anyhow!("Unsupported key '{}' in stop hotkey '{}'", key, combo))?;
+    let mut mask = ModMask::from(0u16);
+    for modifier in &modifiers {
+        mask |= modifier_mask(modifier)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported modifier '{}' in stop hotkey '{}'", modifier, combo))?;
+    }
+
+    let (conn, screen_num) = RustConnection::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let keycode = keycode_for(&conn, keysym)?
+        .ok_or_else(|| anyhow::anyhow!("Key '{}' in stop hotkey '{}' isn't on the keyboard map", key, combo))?;
+
+    // Grab with every combination of Caps Lock and Num Lock so they don't block the hotkey
+    for locks in [ModMask::from(0u16), ModMask::LOCK, ModMask::M2, ModMask::LOCK | ModMask::M2] {
+        conn.grab_key(false, root, mask | locks, keycode, GrabMode::ASYNC, GrabMode::ASYNC)?
+            .check()
+            .map_err(|e| anyhow::anyhow!("Failed to grab stop hotkey '{}' (is another program using it?): {:?}", combo, e))?;
+    }
+    // Repeats are also ignored by their matching timestamps, so this is best effort
+    if let Err(e) = enable_detectable_auto_repeat(&conn) {
+        tracing::debug!("Detectable auto-repeat unavailable for the stop hotkey: {}", e);
+    }
+    conn.flush()?;
+
+    tracing::info!("Emergency-stop hotkey {} grabbed; press it to stop and resume input tools", combo);
+    let combo = combo.to_string();
+    std::thread::spawn(move || {
+        // Auto-repeat sends more presses while the key is held, each preceded by a
+        // release with the same timestamp unless detectable auto-repeat is on
+        let mut held = false;
+        let mut last_release = None;
+        loop {
+            match conn.wait_for_event() {
+                Ok(Event::KeyPress(event)) if event.detail == keycode => {
+                    if held || last_release == Some(event.time) {
+                        continue;
+                    }
+                    held = true;
+                    let was_stopped = stopped.fetch_xor(true, Ordering::SeqCst);
+                    if was_stopped {
+                        tracing::warn!("Stop hotkey {} pressed; input tools are enabled again", combo);
+                    } else {
+                        tracing::warn!("Stop hotkey {} pressed; input tools are disabled until it is pressed again", combo);
+                    }
+                }
+                Ok(Event::KeyRelease(event)) if event.detail == keycode => {
+                    held = false;
+                    last_release = Some(event.time);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("Stop hotkey listener lost its X connection: {}", e);
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Ask the server not to send a release before each auto-repeated press
+fn enable_detectable_auto_repeat(conn: &RustConnection) -> anyhow::Result<()> {
+    let reply = conn.xkb_use_extension(1, 0)?.reply()?;
+    anyhow::ensure!(reply.supported, "XKB is not supported by the X server");
+
+    let flag = xkb::PerClientFlag::DETECTABLE_AUTO_REPEAT;
+    let none = xkb::BoolCtrl::from(0u32);
+    let reply = conn
+        .xkb_per_client_flags(xkb::ID::USE_CORE_KBD.into(), flag, flag, none, none, none)?
+        .reply()?;
+    anyhow::ensure!(reply.value & flag == flag, "the X server refused detectable auto-repeat");
+    Ok(())
+}
+
+/// X modifier mask for a modifier name
+fn modifier_mask(modifier: &str) -> Option<ModMask> {
+    match modifier.to_lowercase().as_str() {
+        "ctrl" | "control" => Some(ModMask::CONTROL),
+        "alt" => Some(ModMask::M1),
+        "shift" => Some(ModMask::SHIFT),
+        "super" | "meta" | "win" => Some(ModMask::M4),
+        _ => None,
+    }
+}
+
+/// Keysym value for a keysym name from [`crate::input::map_key_to_keysym`]
+///
+/// Covers the keys that make sense as a hotkey rather than every keysym.
+fn keysym_value(name: &str) -> Option<Keysym> {
+    let value = match name {
+        "Return" => 0xff0d,
+        "Escape" => 0xff1b,
+        "Tab" => 0xff09,
+        "BackSpace" => 0xff08,
+        "Delete" => 0xffff,
+        "Insert" => 0xff63,
+        "Home" => 0xff50,
+        "End" => 0xff57,
+        "Page_Up" => 0xff55,
+        "Page_Down" => 0xff56,
+        "Left" => 0xff51,
+        "Up" => 0xff52,
+        "Right" => 0xff53,
+        "Down" => 0xff54,
+        "Pause" | "pause" => 0xff13,
+        "Scroll_Lock" => 0xff14,
+        "Print" | "print" => 0xff61,
+        "space" => 0x20,
+        "minus" => 0x2d,
+        "equal" => 0x3d,
+        "bracketleft" => 0x5b,
+        "bracketright" => 0x5d,
+        "semicolon" => 0x3b,
+        "apostrophe" => 0x27,
+        "grave" => 0x60,
+        "backslash" => 0x5c,
+        "comma" => 0x2c,
+        "period" => 0x2e,
+        "slash" => 0x2f,
+        "plus" => 0x2b,
+        // F1..F35 are consecutive
+        f if f.len() > 1 && f.starts_with('F') => {
+            let number: u32 = f[1..].parse().ok().filter(|n| (1..=35).contains(n))?;
+            0xffbe + number - 1
+        }
+        // Latin-1 letters and digits are their own keysyms; the keymap lists letters lowercase
+        c if c.len() == 1 && c.as_bytes()[0].is_ascii_alphanumeric() => c.to_ascii_lowercase().as_bytes()[0] as u32,
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// First keycode whose keymap entry produces `keysym`
+fn keycode_for(conn: &RustConnection, keysym: Keysym) -> anyhow::Result<Option<Keycode>> {
+    let setup = conn.setup();
+    let (min, max) = (setup.min_keycode, setup.max_keycode);
+    let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+
+    let per_keycode = mapping.keysyms_per_keycode.max(1) as usize;
+    Ok(mapping
+        .keysyms
+        .iter()
+        .position(|&sym| sym == keysym)
+        .map(|index| min + (index / per_keycode) as u8))
+}
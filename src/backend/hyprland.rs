@@ -0,0 +1,398 @@
+//! Hyprland backend using the hyprctl IPC socket
+//!
+//! Hyprland does not honor X11 focus requests for native Wayland windows, so
+//! this backend speaks the same socket protocol as `hyprctl`. Windows are
+//! identified by their Hyprland address (e.g., "0x55d0c8a0b2c0").
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+use super::{CloseMethod, CloseOutcome, MonitorInfo, FocusOutcome, WindowBackend, WindowInfo};
+use crate::core::registry::{Geometry, PlatformWindowId};
+
+/// Special workspace used to emulate minimizing
+const MINIMIZED_WORKSPACE: &str = "special:minimized";
+
+/// Hyprland `fullscreen` dispatcher modes
+const FULLSCREEN_MODE_FULL: u8 = 0;
+const FULLSCREEN_MODE_MAXIMIZE: u8 = 1;
+
+/// Hyprland backend that uses the IPC socket for all operations
+pub struct HyprlandBackend {
+    /// Path to the request socket (`.socket.sock`)
+    socket_path: PathBuf,
+}
+
+impl HyprlandBackend {
+    /// Create a new Hyprland backend from `$HYPRLAND_INSTANCE_SIGNATURE`
+    pub async fn new() -> anyhow::Result<Self> {
+        let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+            .map_err(|_| anyhow::anyhow!("HYPRLAND_INSTANCE_SIGNATURE is not set"))?;
+
+        // Hyprland >= 0.40 uses $XDG_RUNTIME_DIR/hypr; older versions used /tmp/hypr
+        let runtime_path = std::env::var("XDG_RUNTIME_DIR")
+            .map(|dir| PathBuf::from(dir).join("hypr").join(&signature).join(".socket.sock"));
+        let socket_path = match runtime_path {
+            Ok(path) if path.exists() => path,
+            _ => PathBuf::from("/tmp/hypr").join(&signature).join(".socket.sock"),
+        };
+
+        let backend = Self { socket_path };
+
+        // Verify the socket is reachable before committing to this backend
+        backend.request("j/version").await?;
+
+        Ok(backend)
+    }
+
+    /// Send a raw request and return the full reply
+    async fn request(&self, command: &str) -> anyhow::Result<String> {
+        let mut stream = UnixStream::connect(&self.socket_path).await?;
+        stream.write_all(command.as_bytes()).await?;
+
+        // Hyprland closes the connection after replying
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await?;
+        Ok(reply)
+    }
+
+    /// Run a JSON query such as `j/clients`
+    async fn query(&self, command: &str) -> anyhow::Result<Value> {
+        let reply = self.request(command).await?;
+        Ok(serde_json::from_str(&reply)?)
+    }
+
+    /// Run a dispatcher and fail unless Hyprland answers "ok"
+    async fn dispatch(&self, dispatcher: &str) -> anyhow::Result<()> {
+        tracing::debug!("Running hyprland dispatcher: {}", dispatcher);
+
+        let reply = self.request(&format!("dispatch {}", dispatcher)).await?;
+        if reply.trim() != "ok" {
+            anyhow::bail!("hyprland dispatch '{}' failed: {}", dispatcher, reply.trim());
+        }
+
+        Ok(())
+    }
+
+    /// Find a client by its platform ID
+    async fn client(&self, id: &PlatformWindowId) -> anyhow::Result<Value> {
+        let address = Self::address(id)?;
+        let clients = self.query("j/clients").await?;
+        clients
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|c| c["address"].as_str() == Some(address))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Window not found"))
+    }
+
+    /// Extract the Hyprland address from a platform ID
+    fn address(id: &PlatformWindowId) -> anyhow::Result<&str> {
+        let PlatformWindowId::Wayland(address) = id else {
+            anyhow::bail!("Hyprland backend cannot handle non-Hyprland window IDs");
+        };
+        Ok(address)
+    }
+
+    /// Toggle fullscreen/maximize on a window, focusing it first since the
+    /// `fullscreen` dispatcher only acts on the active window
+    async fn toggle_fullscreen(&self, address: &str, mode: u8) -> anyhow::Result<()> {
+        self.dispatch(&format!("focuswindow address:{}", address)).await?;
+        self.dispatch(&format!("fullscreen {}", mode)).await
+    }
+}
+
+/// Current fullscreen state of a client: 0 = none, 1 = maximized, 2 = fullscreen,
+/// 3 = fullscreen and maximized (Hyprland's state is a bitmask of the two)
+fn fullscreen_state(client: &Value) -> u64 {
+    match &client["fullscreen"] {
+        Value::Number(n) => n.as_u64().unwrap_or(0),
+        // Older Hyprland reports a bool plus a separate mode (0 = full, 1 = maximize)
+        Value::Bool(true) => match client["fullscreenMode"].as_u64() {
+            Some(1) => 1,
+            _ => 2,
+        },
+        _ => 0,
+    }
+}
+
+/// Convert a client entry into backend window info
+fn window_info(client: &Value, stacking_index: usize) -> WindowInfo {
+    let at = &client["at"];
+    let size = &client["size"];
+
+    let geometry = Geometry {
+        x: at[0].as_i64().unwrap_or(0) as i32,
+        y: at[1].as_i64().unwrap_or(0) as i32,
+        width: size[0].as_u64().unwrap_or(0) as u32,
+        height: size[1].as_u64().unwrap_or(0) as u32,
+    };
+
+    let on_special = client["workspace"]["name"]
+        .as_str()
+        .is_some_and(|name| name.starts_with("special"));
+    let fullscreen = fullscreen_state(client);
+
+    WindowInfo {
+        platform_id: PlatformWindowId::Wayland(client["address"].as_str().unwrap_or_default().to_string()),
+        title: client["title"].as_str().unwrap_or_default().to_string(),
+        class: client["class"].as_str().unwrap_or_default().to_string(),
+        // Hyprland doesn't report the WM_CLASS instance separately
+        instance: String::new(),
+        geometry,
+        // Hyprland draws borders outside the reported geometry and doesn't report their size
+        frame: None,
+        // focusHistoryID 0 is the most recently focused window
+        focused: client["focusHistoryID"].as_i64() == Some(0),
+        visible: client["mapped"].as_bool().unwrap_or(true)
+            && !client["hidden"].as_bool().unwrap_or(false)
+            && !on_special,
+        stacking_index,
+        pid: client["pid"].as_i64().filter(|&pid| pid > 0).map(|pid| pid as u32),
+        desktop: None,
+        window_type: None,
+        minimized: client["workspace"]["name"].as_str() == Some(MINIMIZED_WORKSPACE),
+        maximized_horz: fullscreen == 1,
+        maximized_vert: fullscreen == 1,
+        fullscreen: matches!(fullscreen, 2 | 3),
+    }
+}
+
+#[async_trait]
+impl WindowBackend for HyprlandBackend {
+    fn name(&self) -> &'static str {
+        "hyprland"
+    }
+
+    async fn list_windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
+        let clients = self.query("j/clients").await?;
+        let clients: Vec<&Value> = clients.as_array().into_iter().flatten().collect();
+
+        // Hyprland exposes no stacking list, so approximate it by focus recency:
+        // windows focused longer ago (higher focusHistoryID) sit lower
+        let focus_history = |c: &Value| c["focusHistoryID"].as_i64().unwrap_or(i64::MAX);
+        Ok(clients
+            .iter()
+            .map(|client| {
+                let stacking_index = clients
+                    .iter()
+                    .filter(|other| focus_history(other) > focus_history(client))
+                    .count();
+                window_info(client, stacking_index)
+            })
+            .collect())
+    }
+
+    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<FocusOutcome> {
+        let address = Self::address(id)?;
+        self.dispatch(&format!("focuswindow address:{}", address)).await?;
+        Ok(FocusOutcome::unverified())
+    }
+
+    async fn move_window(&self, id: &PlatformWindowId, x: i32, y: i32) -> anyhow::Result<()> {
+        let address = Self::address(id)?;
+        self.dispatch(&format!("movewindowpixel exact {} {},address:{}", x, y, address)).await
+    }
+
+    async fn resize_window(&self, id: &PlatformWindowId, width: u32, height: u32) -> anyhow::Result<()> {
+        let address = Self::address(id)?;
+        self.dispatch(&format!("resizewindowpixel exact {} {},address:{}", width, height, address)).await
+    }
+
+    async fn minimize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        // Hyprland has no iconified state; park the window on a special workspace
+        let address = Self::address(id)?;
+        self.dispatch(&format!("movetoworkspacesilent {},address:{}", MINIMIZED_WORKSPACE, address)).await
+    }
+
+    async fn maximize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let client = self.client(id).await?;
+        let address = Self::address(id)?;
+
+        // The dispatcher toggles, so only act when the window isn't already maximized
+        match fullscreen_state(&client) {
+            0 => self.toggle_fullscreen(address, FULLSCREEN_MODE_MAXIMIZE).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn restore_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let client = self.client(id).await?;
+        let address = Self::address(id)?;
+
+        if client["workspace"]["name"].as_str() == Some(MINIMIZED_WORKSPACE) {
+            // Bring it back to whichever workspace is currently active
+            let workspace = self.query("j/activeworkspace").await?;
+            let workspace_id = workspace["id"].as_i64().unwrap_or(1);
+            self.dispatch(&format!("movetoworkspace {},address:{}", workspace_id, address)).await?;
+        }
+
+        match fullscreen_state(&client) {
+            1 => self.toggle_fullscreen(address, FULLSCREEN_MODE_MAXIMIZE).await,
+            2 | 3 => self.toggle_fullscreen(address, FULLSCREEN_MODE_FULL).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn raise_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        // alterzorder only affects floating windows; tiled windows don't overlap
+        let address = Self::address(id)?;
+        self.dispatch(&format!("alterzorder top,address:{}", address)).await
+    }
+
+    async fn lower_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let address = Self::address(id)?;
+        self.dispatch(&format!("alterzorder bottom,address:{}", address)).await
+    }
+
+    async fn set_fullscreen(&self, id: &PlatformWindowId, enabled: bool) -> anyhow::Result<()> {
+        let client = self.client(id).await?;
+        let address = Self::address(id)?;
+
+        // The dispatcher toggles, so compare against the current state first
+        match (fullscreen_state(&client), enabled) {
+            (2 | 3, true) | (0 | 1, false) => Ok(()),
+            (1, true) => {
+                // Leave maximize before entering fullscreen
+                self.toggle_fullscreen(address, FULLSCREEN_MODE_MAXIMIZE).await?;
+                self.toggle_fullscreen(address, FULLSCREEN_MODE_FULL).await
+            }
+            _ => self.toggle_fullscreen(address, FULLSCREEN_MODE_FULL).await,
+        }
+    }
+
+    async fn close_window(&self, id: &PlatformWindowId, force: bool) -> anyhow::Result<CloseOutcome> {
+        let address = Self::address(id)?;
+
+        if force {
+            self.dispatch(&format!("killwindow address:{}", address)).await?;
+            return Ok(CloseOutcome {
+                method: CloseMethod::Force,
+                graceful_supported: true,
+            });
+        }
+
+        self.dispatch(&format!("closewindow address:{}", address)).await?;
+        Ok(CloseOutcome {
+            method: CloseMethod::Graceful,
+            graceful_supported: true,
+        })
+    }
+
+    async fn cursor_position(&self) -> anyhow::Result<(i32, i32)> {
+        let position = self.query("j/cursorpos").await?;
+        Ok((
+            position["x"].as_i64().unwrap_or(0) as i32,
+            position["y"].as_i64().unwrap_or(0) as i32,
+        ))
+    }
+
+    async fn list_monitors(&self) -> anyhow::Result<Vec<MonitorInfo>> {
+        let monitors = self.query("j/monitors").await?;
+
+        // Hyprland reports physical pixels; window coordinates are logical, so
+        // divide by the scale to keep both in the same space. It has no primary
+        // monitor, so the focused one is reported as primary.
+        Ok(monitors
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|m| {
+                let scale = m["scale"].as_f64().unwrap_or(1.0);
+                MonitorInfo {
+                    name: m["name"].as_str().unwrap_or_default().to_string(),
+                    x: m["x"].as_i64().unwrap_or(0) as i32,
+                    y: m["y"].as_i64().unwrap_or(0) as i32,
+                    width: (m["width"].as_f64().unwrap_or(0.0) / scale).round() as u32,
+                    height: (m["height"].as_f64().unwrap_or(0.0) / scale).round() as u32,
+                    scale_factor: scale,
+                    primary: m["focused"].as_bool().unwrap_or(false),
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fullscreen_state_reads_numeric_state() {
+        for state in 0..=3 {
+            assert_eq!(fullscreen_state(&json!({ "fullscreen": state })), state);
+        }
+        assert_eq!(fullscreen_state(&json!({})), 0);
+    }
+
+    #[test]
+    fn fullscreen_state_reads_legacy_bool_and_mode() {
+        assert_eq!(fullscreen_state(&json!({ "fullscreen": false, "fullscreenMode": 1 })), 0);
+        assert_eq!(fullscreen_state(&json!({ "fullscreen": true, "fullscreenMode": 0 })), 2);
+        assert_eq!(fullscreen_state(&json!({ "fullscreen": true, "fullscreenMode": 1 })), 1);
+        assert_eq!(fullscreen_state(&json!({ "fullscreen": true })), 2);
+    }
+
+    fn client(overrides: Value) -> Value {
+        let mut client = json!({
+            "address": "0x55d1c0a3e2f0",
+            "mapped": true,
+            "hidden": false,
+            "at": [10, 40],
+            "size": [800, 600],
+            "workspace": { "id": 1, "name": "1" },
+            "class": "foot",
+            "title": "Terminal",
+            "pid": 4242,
+            "fullscreen": 0,
+            "focusHistoryID": 0
+        });
+        for (key, value) in overrides.as_object().unwrap() {
+            client[key] = value.clone();
+        }
+        client
+    }
+
+    #[test]
+    fn window_info_reads_client_fields() {
+        let info = window_info(&client(json!({})), 3);
+        assert_eq!(info.platform_id, PlatformWindowId::Wayland("0x55d1c0a3e2f0".to_string()));
+        assert_eq!((info.title.as_str(), info.class.as_str()), ("Terminal", "foot"));
+        assert_eq!(info.geometry, Geometry { x: 10, y: 40, width: 800, height: 600 });
+        assert_eq!(info.pid, Some(4242));
+        assert_eq!(info.stacking_index, 3);
+        assert!(info.focused && info.visible && !info.minimized);
+        assert!(!info.fullscreen && !info.maximized_horz && !info.maximized_vert);
+    }
+
+    #[test]
+    fn window_info_reports_maximized_and_fullscreen() {
+        let maximized = window_info(&client(json!({ "fullscreen": 1 })), 0);
+        assert!(maximized.maximized_horz && maximized.maximized_vert && !maximized.fullscreen);
+
+        for state in [2, 3] {
+            assert!(window_info(&client(json!({ "fullscreen": state })), 0).fullscreen);
+        }
+    }
+
+    #[test]
+    fn window_info_hides_special_workspaces() {
+        let minimized = window_info(&client(json!({ "workspace": { "id": -99, "name": MINIMIZED_WORKSPACE } })), 0);
+        assert!(minimized.minimized && !minimized.visible);
+
+        let scratch = window_info(&client(json!({ "workspace": { "id": -98, "name": "special:scratch" } })), 0);
+        assert!(!scratch.minimized && !scratch.visible);
+    }
+
+    #[test]
+    fn window_info_drops_unknown_pid_and_unfocused() {
+        let info = window_info(&client(json!({ "pid": -1, "focusHistoryID": 2 })), 0);
+        assert_eq!(info.pid, None);
+        assert!(!info.focused);
+    }
+}
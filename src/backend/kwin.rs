@@ -1,16 +1,68 @@
 //! KWin backend for KDE Plasma on Wayland
 //!
-//! This backend wraps the X11 backend for window enumeration (via XWayland)
-//! but uses KWin's D-Bus scripting API for window focus, which properly
-//! raises windows on Wayland instead of just requesting attention.
+//! This backend wraps the X11 backend for XWayland windows, and uses KWin's
+//! D-Bus scripting API to list native Wayland windows, which XWayland can't
+//! see, and to focus, move, and resize windows, which properly raises them on
+//! Wayland instead of just requesting attention.
+//!
+//! Loading a script is slow, so a single bridge script is loaded once and kept
+//! running. It registers a global shortcut; invoking that shortcut over D-Bus
+//! makes the script fetch the pending command from an object Marionette serves
+//! on its own connection, run it, and report the result back.
 
 use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::{Mutex, oneshot};
 use zbus::Connection;
-use zbus::zvariant::ObjectPath;
 
-use super::{WindowBackend, WindowInfo};
-use crate::core::registry::PlatformWindowId;
+use super::{CloseOutcome, DesktopInfo, FocusOutcome, WindowBackend, WindowInfo};
+use crate::core::registry::{Geometry, PlatformWindowId};
+
+/// Object path the bridge script calls back into
+const BRIDGE_PATH: &str = "/org/marionette/KWinBridge";
+
+/// Interface the bridge script calls back into
+const BRIDGE_INTERFACE: &str = "org.marionette.KWinBridge";
+
+/// Plugin name the bridge script is loaded under
+const BRIDGE_PLUGIN: &str = "marionette-bridge";
+
+/// Global shortcut the bridge script registers to be woken up
+const BRIDGE_SHORTCUT: &str = "Marionette Bridge";
+
+/// How long to wait for the bridge script to report a result
+const BRIDGE_TIMEOUT_MS: u64 = 1000;
+
+/// Command waiting for the bridge script, and where its result goes
+#[derive(Default)]
+struct BridgeState {
+    command: Option<String>,
+    reply: Option<oneshot::Sender<String>>,
+}
+
+/// D-Bus object the bridge script calls to fetch commands and report results
+struct ScriptBridge {
+    state: Arc<std::sync::Mutex<BridgeState>>,
+}
+
+#[zbus::interface(name = "org.marionette.KWinBridge")]
+impl ScriptBridge {
+    /// Take the pending command as JSON, or an empty string if there is none
+    fn command(&self) -> String {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.command.take().unwrap_or_default()
+    }
+
+    /// Receive the JSON result of the last command
+    fn complete(&self, result: String) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(reply) = state.reply.take() {
+            let _ = reply.send(result);
+        }
+    }
+}
 
 /// KWin backend that uses D-Bus for focus operations
 pub struct KWinBackend {
@@ -18,106 +70,503 @@ pub struct KWinBackend {
     x11_backend: Arc<dyn WindowBackend>,
     /// D-Bus connection
     dbus: Connection,
+    /// State shared with the served bridge object
+    bridge: Arc<std::sync::Mutex<BridgeState>>,
+    /// ID of the loaded bridge script, or None until it is (re)loaded
+    ///
+    /// Held for the duration of each command, so commands run one at a time.
+    script_id: Mutex<Option<i32>>,
 }
 
 impl KWinBackend {
     /// Create a new KWin backend
     pub async fn new(x11_backend: Arc<dyn WindowBackend>) -> anyhow::Result<Self> {
         let dbus = Connection::session().await?;
-        Ok(Self { x11_backend, dbus })
+
+        let bridge = Arc::new(std::sync::Mutex::new(BridgeState::default()));
+        dbus.object_server()
+            .at(BRIDGE_PATH, ScriptBridge { state: bridge.clone() })
+            .await?;
+
+        Ok(Self {
+            x11_backend,
+            dbus,
+            bridge,
+            script_id: Mutex::new(None),
+        })
+    }
+
+    /// Call a method on KWin's Scripting object
+    async fn call_scripting<B>(&self, method: &str, body: &B) -> anyhow::Result<zbus::Message>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        Ok(self.dbus
+            .call_method(
+                Some("org.kde.KWin"),
+                "/Scripting",
+                Some("org.kde.kwin.Scripting"),
+                method,
+                body,
+            )
+            .await?)
+    }
+
+    /// Whether KWin currently has the bridge script loaded
+    async fn bridge_loaded(&self) -> anyhow::Result<bool> {
+        let reply = self.call_scripting("isScriptLoaded", &(BRIDGE_PLUGIN,)).await?;
+        Ok(reply.body().deserialize()?)
+    }
+
+    /// Load and start the bridge script, returning its script ID
+    async fn load_bridge(&self) -> anyhow::Result<i32> {
+        // A script left by an earlier Marionette process would call back to its
+        // dead connection, so replace it
+        if self.bridge_loaded().await? {
+            self.call_scripting("unloadScript", &(BRIDGE_PLUGIN,)).await?;
+        }
+
+        let service = self.dbus
+            .unique_name()
+            .ok_or_else(|| anyhow::anyhow!("D-Bus connection has no unique name"))?
+            .to_string();
+
+        // KWin requires a file path; the file is only needed until the script starts
+        let script_path = std::env::temp_dir().join(format!("marionette_bridge_{}.js", std::process::id()));
+        tokio::fs::write(&script_path, bridge_script(&service)).await?;
+
+        let loaded = self.call_scripting("loadScript", &(script_path.to_string_lossy().as_ref(), BRIDGE_PLUGIN)).await;
+        let started = match loaded {
+            Ok(reply) => match reply.body().deserialize::<i32>() {
+                // start() runs every loaded script that isn't running yet
+                Ok(script_id) if script_id >= 0 => self.call_scripting("start", &()).await.map(|_| script_id),
+                Ok(_) => Err(anyhow::anyhow!("KWin refused to load the bridge script")),
+                Err(e) => Err(e.into()),
+            },
+            Err(e) => Err(e),
+        };
+
+        let _ = tokio::fs::remove_file(&script_path).await;
+
+        let script_id = started?;
+        tracing::debug!("Loaded KWin bridge script with ID {}", script_id);
+        Ok(script_id)
+    }
+
+    /// Run a command in the bridge script and return its JSON result
+    ///
+    /// The script is loaded on first use, and reloaded if KWin has restarted
+    /// (or otherwise dropped it) since.
+    async fn run_script_command(&self, command: Value) -> anyhow::Result<Value> {
+        let mut script_id = self.script_id.lock().await;
+
+        if script_id.is_none() || !self.bridge_loaded().await? {
+            *script_id = Some(self.load_bridge().await?);
+        }
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        {
+            let mut state = self.bridge.lock().unwrap_or_else(|e| e.into_inner());
+            state.command = Some(command.to_string());
+            state.reply = Some(reply_tx);
+        }
+
+        self.dbus
+            .call_method(
+                Some("org.kde.kglobalaccel"),
+                "/component/kwin",
+                Some("org.kde.kglobalaccel.Component"),
+                "invokeShortcut",
+                &(BRIDGE_SHORTCUT,),
+            )
+            .await?;
+
+        let timeout = std::time::Duration::from_millis(BRIDGE_TIMEOUT_MS);
+        let result = match tokio::time::timeout(timeout, reply_rx).await {
+            Ok(Ok(result)) => result,
+            _ => {
+                // Drop the stale command and reload the script next time
+                *self.bridge.lock().unwrap_or_else(|e| e.into_inner()) = BridgeState::default();
+                *script_id = None;
+                anyhow::bail!("KWin bridge script did not respond within {}ms", BRIDGE_TIMEOUT_MS);
+            }
+        };
+
+        let result: Value = serde_json::from_str(&result)?;
+        if result["ok"].as_bool() != Some(true) {
+            anyhow::bail!("KWin script failed: {}", result["error"].as_str().unwrap_or("unknown error"));
+        }
+        Ok(result)
     }
 
     /// Focus a window using KWin's scripting API
-    async fn focus_via_kwin(&self, window_title: &str) -> anyhow::Result<()> {
-        // KWin scripting API: load a script that finds and activates the window
-        // The script uses workspace.windowList() (KDE 6) or workspace.clientList() (KDE 5)
-        let script = format!(
-            r#"
-            (function() {{
+    ///
+    /// Windows are matched by X11 window ID, which KWin exposes as `windowId`
+    /// for XWayland clients, so duplicate or since-changed titles can't pick
+    /// the wrong window. The title is only used if no window reports that ID,
+    /// and only when exactly one window has it.
+    async fn focus_via_kwin(&self, window_id: u32, window_title: &str) -> anyhow::Result<()> {
+        self.run_script_command(json!({
+            "action": "focus",
+            "windowId": window_id,
+            "title": window_title
+        }))
+        .await?;
+        Ok(())
+    }
+
+    /// Run a bridge command on one window, matched by X11 `windowId` or KWin `internalId`
+    async fn run_window_command(&self, id: &PlatformWindowId, command: Value) -> anyhow::Result<Value> {
+        let mut command = command;
+        match id {
+            PlatformWindowId::X11(window_id) => command["windowId"] = json!(window_id),
+            PlatformWindowId::Wayland(internal_id) => command["internalId"] = json!(internal_id),
+        }
+        self.run_script_command(command).await
+    }
+
+    /// Move or resize a window from a KWin script
+    ///
+    /// Works for native Wayland windows as well as XWayland ones. Positions and
+    /// sizes are of the client area, like every other tool; the script converts
+    /// them to the frame geometry KWin sets. KWin may clamp the request to the
+    /// window's size limits, so the client geometry it actually applied is returned.
+    async fn set_geometry_via_kwin(&self, id: &PlatformWindowId, command: Value) -> anyhow::Result<Geometry> {
+        let result = self.run_window_command(id, command).await?;
+        Ok(geometry_from_json(&result["geometry"]))
+    }
+
+    /// Minimize, maximize, or restore a window from a KWin script
+    ///
+    /// XWayland windows fall back to the X11 backend's EWMH requests if the
+    /// script fails; native Wayland windows can only be reached through KWin.
+    async fn set_state_via_kwin(&self, id: &PlatformWindowId, action: &str) -> anyhow::Result<()> {
+        let result = self.run_window_command(id, json!({ "action": action })).await;
+        match (result, id) {
+            (Ok(_), _) => Ok(()),
+            (Err(e), PlatformWindowId::X11(_)) => {
+                tracing::warn!("KWin {} failed, falling back to X11: {}", action, e);
+                match action {
+                    "minimize" => self.x11_backend.minimize_window(id).await,
+                    "maximize" => self.x11_backend.maximize_window(id).await,
+                    _ => self.x11_backend.restore_window(id).await,
+                }
+            }
+            (Err(e), PlatformWindowId::Wayland(_)) => Err(e),
+        }
+    }
+
+    /// Native Wayland windows and the stacking order of every window, from the bridge script
+    async fn list_via_kwin(&self) -> anyhow::Result<Vec<Value>> {
+        let result = self.run_script_command(json!({ "action": "list" })).await?;
+        Ok(result["windows"].as_array().cloned().unwrap_or_default())
+    }
+}
+
+/// Geometry from a `{x, y, width, height}` object reported by the bridge script
+fn geometry_from_json(geometry: &Value) -> Geometry {
+    Geometry {
+        x: geometry["x"].as_f64().unwrap_or_default() as i32,
+        y: geometry["y"].as_f64().unwrap_or_default() as i32,
+        width: geometry["width"].as_f64().unwrap_or_default() as u32,
+        height: geometry["height"].as_f64().unwrap_or_default() as u32,
+    }
+}
+
+/// Window info for a native Wayland window from the bridge script's "list" result
+///
+/// Returns `None` for XWayland windows (which have a nonzero `windowId`) and
+/// entries without an `internalId`.
+fn native_window_info(window: &Value) -> Option<WindowInfo> {
+    if window["windowId"].as_u64().is_some_and(|id| id != 0) {
+        return None;
+    }
+    let internal_id = window["internalId"].as_str().filter(|id| !id.is_empty())?;
+
+    Some(WindowInfo {
+        platform_id: PlatformWindowId::Wayland(internal_id.to_string()),
+        title: window["title"].as_str().unwrap_or_default().to_string(),
+        class: window["class"].as_str().unwrap_or_default().to_string(),
+        instance: window["instance"].as_str().unwrap_or_default().to_string(),
+        geometry: geometry_from_json(&window["client"]),
+        frame: Some(geometry_from_json(&window["frame"])),
+        focused: window["active"].as_bool().unwrap_or(false),
+        visible: window["visible"].as_bool().unwrap_or(true),
+        stacking_index: 0,
+        pid: window["pid"].as_u64().filter(|&pid| pid > 0).map(|pid| pid as u32),
+        desktop: window["desktop"].as_i64(),
+        window_type: window["type"].as_str().map(str::to_string),
+        minimized: window["minimized"].as_bool().unwrap_or(false),
+        // KWin scripting doesn't expose the maximize mode
+        maximized_horz: false,
+        maximized_vert: false,
+        fullscreen: window["fullscreen"].as_bool().unwrap_or(false),
+    })
+}
+
+/// Add the native windows from a bridge "list" result, and restack every window in KWin's order
+///
+/// `listed` is bottom-to-top and covers XWayland windows too, so it replaces
+/// the X11 stacking order. X11 windows KWin didn't list keep their relative
+/// order below the rest. List order is left alone, since refs follow it.
+fn merge_native_windows(windows: &mut Vec<WindowInfo>, listed: &[Value]) {
+    let mut order: HashMap<PlatformWindowId, usize> = HashMap::new();
+    for (index, window) in listed.iter().enumerate() {
+        if let Some(window_id) = window["windowId"].as_u64().filter(|&id| id != 0) {
+            order.insert(PlatformWindowId::X11(window_id as u32), index);
+        } else if let Some(info) = native_window_info(window) {
+            order.insert(info.platform_id.clone(), index);
+            windows.push(info);
+        }
+    }
+
+    let mut ranked: Vec<usize> = (0..windows.len()).collect();
+    ranked.sort_by_key(|&i| {
+        let window = &windows[i];
+        (order.get(&window.platform_id).map_or(0, |&index| index + 1), window.stacking_index)
+    });
+    for (stacking_index, i) in ranked.into_iter().enumerate() {
+        windows[i].stacking_index = stacking_index;
+    }
+}
+
+/// Error for operations only the X11 backend implements, on a native Wayland window
+fn x11_only(id: &PlatformWindowId, operation: &str) -> anyhow::Result<()> {
+    match id {
+        PlatformWindowId::X11(_) => Ok(()),
+        PlatformWindowId::Wayland(_) => {
+            anyhow::bail!("{} is only supported for XWayland windows under KWin, not native Wayland ones", operation)
+        }
+    }
+}
+
+/// Build the bridge script, which calls back to `service` for its commands
+fn bridge_script(service: &str) -> String {
+    format!(
+        r#"
+        (function() {{
+            var service = {};
+            var path = {};
+            var iface = {};
+
+            function allWindows() {{
                 // Try KDE 6 API first, fall back to KDE 5
-                var windows = typeof workspace.windowList === 'function'
+                return typeof workspace.windowList === 'function'
                     ? workspace.windowList()
                     : workspace.clientList();
+            }}
+
+            function findWindow(command) {{
+                var windows = allWindows();
+                var titleMatches = [];
                 for (var i = 0; i < windows.length; i++) {{
                     var w = windows[i];
-                    var title = w.caption || w.title || '';
-                    if (title === '{}') {{
-                        workspace.activeWindow = w;  // KDE 6
-                        workspace.activeClient = w;  // KDE 5 fallback
-                        break;
+                    // Native Wayland windows have no X11 ID, only KWin's internal one
+                    if (command.internalId !== undefined) {{
+                        if (String(w.internalId) === command.internalId) {{
+                            return w;
+                        }}
+                        continue;
+                    }}
+                    if (w.windowId === command.windowId) {{
+                        return w;
+                    }}
+                    if ((w.caption || w.title || '') === command.title) {{
+                        titleMatches.push(w);
                     }}
                 }}
-            }})();
-            "#,
-            window_title.replace('\\', "\\\\").replace('\'', "\\'").replace('"', "\\\"")
-        );
+                return titleMatches.length === 1 ? titleMatches[0] : null;
+            }}
 
-        // Write script to temp file (KWin requires a file path)
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join(format!("marionette_focus_{}.js", std::process::id()));
-        tokio::fs::write(&script_path, &script).await?;
+            function rect(g) {{
+                return {{ x: g.x, y: g.y, width: g.width, height: g.height }};
+            }}
 
-        // Load the script via D-Bus using the Scripting interface
-        let script_path_str = script_path.to_str().unwrap_or("");
+            // KDE 5 before 5.27 has no clientGeometry
+            function clientGeometry(w) {{
+                return w.clientGeometry || w.frameGeometry;
+            }}
 
-        let reply = self.dbus
-            .call_method(
-                Some("org.kde.KWin"),
-                "/Scripting",
-                Some("org.kde.kwin.Scripting"),
-                "loadScript",
-                &(script_path_str,),
-            )
-            .await?;
+            function windowType(w) {{
+                var types = [
+                    ['normalWindow', 'normal'], ['dialog', 'dialog'], ['desktopWindow', 'desktop'],
+                    ['dock', 'dock'], ['toolbar', 'toolbar'], ['menu', 'menu'], ['utility', 'utility'],
+                    ['splash', 'splash'], ['dropdownMenu', 'dropdown_menu'], ['popupMenu', 'popup_menu'],
+                    ['tooltip', 'tooltip'], ['notification', 'notification'], ['comboBox', 'combo'],
+                    ['dndIcon', 'dnd']
+                ];
+                for (var i = 0; i < types.length; i++) {{
+                    if (w[types[i][0]]) {{
+                        return types[i][1];
+                    }}
+                }}
+                return 'unknown';
+            }}
 
-        let script_id: i32 = reply.body().deserialize()?;
-
-        if script_id >= 0 {
-            // Run the script via its object path
-            let script_obj_path = format!("/{}", script_id);
-            let script_obj_path = ObjectPath::try_from(script_obj_path.as_str())?;
-
-            self.dbus
-                .call_method(
-                    Some("org.kde.KWin"),
-                    script_obj_path,
-                    Some("org.kde.kwin.Script"),
-                    "run",
-                    &(),
-                )
-                .await?;
-
-            // Small delay to let the script execute
-            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-
-            // Stop/unload the script (ignore errors)
-            let _ = self.dbus
-                .call_method(
-                    Some("org.kde.KWin"),
-                    ObjectPath::try_from(format!("/{}", script_id).as_str())?,
-                    Some("org.kde.kwin.Script"),
-                    "stop",
-                    &(),
-                )
-                .await;
-        }
-
-        // Clean up temp file
-        let _ = tokio::fs::remove_file(&script_path).await;
+            function desktopOf(w) {{
+                if (w.onAllDesktops) {{
+                    return -1;
+                }}
+                // KDE 6 lists desktop objects; KDE 5 has a 1-based number
+                if (w.desktops !== undefined && workspace.desktops !== undefined) {{
+                    return w.desktops.length > 0 ? workspace.desktops.indexOf(w.desktops[0]) : null;
+                }}
+                return typeof w.desktop === 'number' ? w.desktop - 1 : null;
+            }}
 
-        Ok(())
-    }
+            function describe(w) {{
+                return {{
+                    windowId: w.windowId || 0,
+                    internalId: String(w.internalId || ''),
+                    title: w.caption || '',
+                    'class': String(w.resourceClass || ''),
+                    instance: String(w.resourceName || ''),
+                    pid: w.pid || 0,
+                    client: rect(clientGeometry(w)),
+                    frame: rect(w.frameGeometry),
+                    active: !!w.active,
+                    visible: !w.minimized && !w.hidden,
+                    minimized: !!w.minimized,
+                    fullscreen: !!w.fullScreen,
+                    desktop: desktopOf(w),
+                    type: windowType(w)
+                }};
+            }}
+
+            function list() {{
+                // Bottom-to-top, so the caller can take stacking from the order
+                var windows = allWindows().slice();
+                windows.sort(function(a, b) {{
+                    return (a.stackingOrder || 0) - (b.stackingOrder || 0);
+                }});
+                var described = [];
+                for (var i = 0; i < windows.length; i++) {{
+                    // Closed windows linger briefly for their close animation
+                    if (windows[i].deleted) {{
+                        continue;
+                    }}
+                    described.push(describe(windows[i]));
+                }}
+                return {{ ok: true, windows: described }};
+            }}
+
+            function run(command) {{
+                if (command.action === 'list') {{
+                    return list();
+                }}
+                var w = findWindow(command);
+                if (w === null) {{
+                    return {{ ok: false, error: 'Window not found' }};
+                }}
+                switch (command.action) {{
+                    case 'focus':
+                        workspace.activeWindow = w;  // KDE 6
+                        workspace.activeClient = w;  // KDE 5 fallback
+                        return {{ ok: true }};
+                    case 'geometry':
+                        return {{ ok: true, geometry: rect(clientGeometry(w)) }};
+                    case 'minimize':
+                        w.minimized = true;
+                        return {{ ok: true }};
+                    case 'maximize':
+                        w.minimized = false;
+                        w.setMaximize(true, true);
+                        return {{ ok: true }};
+                    case 'restore':
+                        w.minimized = false;
+                        w.setMaximize(false, false);
+                        return {{ ok: true }};
+                    case 'move':
+                    case 'resize':
+                        // Commands are in client coordinates, but only the frame can be set,
+                        // so carry the decoration sizes over
+                        var frame = w.frameGeometry;
+                        var client = clientGeometry(w);
+                        var left = client.x - frame.x;
+                        var top = client.y - frame.y;
+                        w.frameGeometry = command.action === 'move'
+                            ? {{ x: command.x - left, y: command.y - top, width: frame.width, height: frame.height }}
+                            : {{
+                                x: frame.x,
+                                y: frame.y,
+                                width: command.width + frame.width - client.width,
+                                height: command.height + frame.height - client.height
+                            }};
+                        // KWin clamps to size hints, so report what it applied
+                        return {{ ok: true, geometry: rect(clientGeometry(w)) }};
+                    default:
+                        return {{ ok: false, error: 'Unknown action: ' + command.action }};
+                }}
+            }}
+
+            registerShortcut({}, 'Marionette bridge', '', function() {{
+                callDBus(service, path, iface, 'Command', function(json) {{
+                    if (!json) {{
+                        return;
+                    }}
+                    var result;
+                    try {{
+                        result = run(JSON.parse(json));
+                    }} catch (e) {{
+                        result = {{ ok: false, error: String(e) }};
+                    }}
+                    callDBus(service, path, iface, 'Complete', JSON.stringify(result));
+                }});
+            }});
+        }})();
+        "#,
+        js_string_literal(service),
+        js_string_literal(BRIDGE_PATH),
+        js_string_literal(BRIDGE_INTERFACE),
+        js_string_literal(BRIDGE_SHORTCUT)
+    )
+}
+
+/// Encode a string as a JavaScript string literal
+///
+/// JSON string syntax is valid JavaScript, so any value (quotes, backslashes,
+/// newlines, `${...}`) stays inside the literal. U+2028 and U+2029 are escaped
+/// too, since JavaScript engines before ES2019 treat them as line breaks.
+fn js_string_literal(value: &str) -> String {
+    serde_json::to_string(value)
+        .unwrap_or_else(|_| "\"\"".to_string())
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
 }
 
 #[async_trait]
 impl WindowBackend for KWinBackend {
+    fn name(&self) -> &'static str {
+        "kwin"
+    }
+
     async fn list_windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
-        // Delegate to X11 backend - this works via XWayland
-        self.x11_backend.list_windows().await
+        let mut windows = self.x11_backend.list_windows().await?;
+
+        // XWayland can't see native Wayland windows, so KWin lists those
+        match self.list_via_kwin().await {
+            Ok(listed) => merge_native_windows(&mut windows, &listed),
+            Err(e) => tracing::debug!("Listing native Wayland windows through KWin failed: {}", e),
+        }
+        Ok(windows)
     }
 
-    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
-        // First, get the window title from X11 so we can find it in KWin
+    async fn health_check(&self) -> anyhow::Result<String> {
+        let x11 = self.x11_backend.health_check().await?;
+        if !super::dbus_name_has_owner(&self.dbus, "org.kde.KWin").await? {
+            anyhow::bail!("{}, but org.kde.KWin is not on the session bus, so focus can't use KWin scripting", x11);
+        }
+        Ok(format!("{}; org.kde.KWin is on the session bus", x11))
+    }
+
+    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<FocusOutcome> {
+        let PlatformWindowId::X11(window_id) = id else {
+            // Native Wayland windows can only be focused through KWin
+            self.run_window_command(id, json!({ "action": "focus" })).await?;
+            return Ok(FocusOutcome::unverified());
+        };
+
+        // The title is only a fallback for KWin versions without windowId
         let windows = self.x11_backend.list_windows().await?;
         let window = windows
             .iter()
@@ -125,10 +574,10 @@ impl WindowBackend for KWinBackend {
             .ok_or_else(|| anyhow::anyhow!("Window not found"))?;
 
         // Try KWin D-Bus focus first
-        match self.focus_via_kwin(&window.title).await {
+        match self.focus_via_kwin(*window_id, &window.title).await {
             Ok(()) => {
                 tracing::debug!("Focused window via KWin D-Bus: {}", window.title);
-                Ok(())
+                Ok(FocusOutcome::unverified())
             }
             Err(e) => {
                 tracing::warn!("KWin D-Bus focus failed, falling back to X11: {}", e);
@@ -139,12 +588,244 @@ impl WindowBackend for KWinBackend {
     }
 
     async fn move_window(&self, id: &PlatformWindowId, x: i32, y: i32) -> anyhow::Result<()> {
-        // Delegate to X11 backend - this usually works for XWayland windows
-        self.x11_backend.move_window(id, x, y).await
+        match self.set_geometry_via_kwin(id, json!({ "action": "move", "x": x, "y": y })).await {
+            Ok(geometry) => {
+                tracing::debug!("Moved window via KWin script to {:?}", geometry);
+                Ok(())
+            }
+            Err(e) if matches!(id, PlatformWindowId::X11(_)) => {
+                tracing::warn!("KWin move failed, falling back to X11: {}", e);
+                self.x11_backend.move_window(id, x, y).await
+            }
+            Err(e) => Err(e),
+        }
     }
 
     async fn resize_window(&self, id: &PlatformWindowId, width: u32, height: u32) -> anyhow::Result<()> {
-        // Delegate to X11 backend
-        self.x11_backend.resize_window(id, width, height).await
+        match self.set_geometry_via_kwin(id, json!({ "action": "resize", "width": width, "height": height })).await {
+            Ok(geometry) => {
+                if geometry.width != width || geometry.height != height {
+                    tracing::debug!("KWin clamped resize of {:?} to {}x{}", id, geometry.width, geometry.height);
+                }
+                Ok(())
+            }
+            Err(e) if matches!(id, PlatformWindowId::X11(_)) => {
+                tracing::warn!("KWin resize failed, falling back to X11: {}", e);
+                self.x11_backend.resize_window(id, width, height).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn refresh_window(&self, id: &PlatformWindowId) -> anyhow::Result<Geometry> {
+        match id {
+            PlatformWindowId::X11(_) => self.x11_backend.refresh_window(id).await,
+            PlatformWindowId::Wayland(_) => {
+                let result = self.run_window_command(id, json!({ "action": "geometry" })).await?;
+                Ok(geometry_from_json(&result["geometry"]))
+            }
+        }
+    }
+
+    async fn minimize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        self.set_state_via_kwin(id, "minimize").await
+    }
+
+    async fn maximize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        self.set_state_via_kwin(id, "maximize").await
+    }
+
+    async fn restore_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        self.set_state_via_kwin(id, "restore").await
+    }
+
+    async fn close_window(&self, id: &PlatformWindowId, force: bool) -> anyhow::Result<CloseOutcome> {
+        x11_only(id, "Closing")?;
+        self.x11_backend.close_window(id, force).await
+    }
+
+    async fn raise_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        x11_only(id, "Raising")?;
+        self.x11_backend.raise_window(id).await
+    }
+
+    async fn lower_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        x11_only(id, "Lowering")?;
+        self.x11_backend.lower_window(id).await
+    }
+
+    async fn set_always_on_top(&self, id: &PlatformWindowId, enabled: bool) -> anyhow::Result<()> {
+        x11_only(id, "Keeping a window on top")?;
+        self.x11_backend.set_always_on_top(id, enabled).await
+    }
+
+    async fn set_fullscreen(&self, id: &PlatformWindowId, enabled: bool) -> anyhow::Result<()> {
+        x11_only(id, "Fullscreen")?;
+        self.x11_backend.set_fullscreen(id, enabled).await
+    }
+
+    async fn set_opacity(&self, id: &PlatformWindowId, opacity: f64) -> anyhow::Result<()> {
+        x11_only(id, "Setting opacity")?;
+        self.x11_backend.set_opacity(id, opacity).await
+    }
+
+    async fn cursor_position(&self) -> anyhow::Result<(i32, i32)> {
+        // XWayland only sees the pointer while it is over an X11 window
+        self.x11_backend.cursor_position().await
+    }
+
+    async fn window_icon(&self, id: &PlatformWindowId) -> anyhow::Result<Option<image::RgbaImage>> {
+        // KWin scripting doesn't expose icon pixels
+        if matches!(id, PlatformWindowId::Wayland(_)) {
+            return Ok(None);
+        }
+        self.x11_backend.window_icon(id).await
+    }
+
+    async fn desktop_info(&self) -> anyhow::Result<DesktopInfo> {
+        self.x11_backend.desktop_info().await
+    }
+
+    async fn switch_desktop(&self, desktop: u32) -> anyhow::Result<()> {
+        self.x11_backend.switch_desktop(desktop).await
+    }
+
+    async fn move_window_to_desktop(&self, id: &PlatformWindowId, desktop: u32) -> anyhow::Result<()> {
+        x11_only(id, "Moving a window to a desktop")?;
+        self.x11_backend.move_window_to_desktop(id, desktop).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Literal contents between the quotes, which must not contain a raw terminator
+    fn literal_body(value: &str) -> String {
+        let literal = js_string_literal(value);
+        assert!(literal.starts_with('"') && literal.ends_with('"'), "{}", literal);
+        literal[1..literal.len() - 1].to_string()
+    }
+
+    /// Whether a string literal body has a `"` that isn't escaped
+    fn has_unescaped_quote(body: &str) -> bool {
+        let mut escaped = false;
+        for c in body.chars() {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => return true,
+                _ => escaped = false,
+            }
+        }
+        false
+    }
+
+    fn x11_window(window_id: u32, stacking_index: usize) -> WindowInfo {
+        WindowInfo {
+            platform_id: PlatformWindowId::X11(window_id),
+            title: format!("X11 {}", window_id),
+            class: String::new(),
+            instance: String::new(),
+            geometry: Geometry::default(),
+            frame: None,
+            focused: false,
+            visible: true,
+            stacking_index,
+            pid: None,
+            desktop: None,
+            window_type: Some("normal".to_string()),
+            minimized: false,
+            maximized_horz: false,
+            maximized_vert: false,
+            fullscreen: false,
+        }
+    }
+
+    fn native(internal_id: &str) -> Value {
+        json!({
+            "windowId": 0,
+            "internalId": internal_id,
+            "title": "Native",
+            "class": "org.kde.dolphin",
+            "instance": "dolphin",
+            "pid": 4242,
+            "client": { "x": 100, "y": 130, "width": 800, "height": 600 },
+            "frame": { "x": 100, "y": 100, "width": 800, "height": 630 },
+            "active": true,
+            "visible": true,
+            "minimized": false,
+            "fullscreen": false,
+            "desktop": 0,
+            "type": "normal"
+        })
+    }
+
+    #[test]
+    fn native_window_uses_client_geometry_and_internal_id() {
+        let info = native_window_info(&native("{1234}")).unwrap();
+        assert_eq!(info.platform_id, PlatformWindowId::Wayland("{1234}".to_string()));
+        assert_eq!(info.geometry, Geometry { x: 100, y: 130, width: 800, height: 600 });
+        assert_eq!(info.frame, Some(Geometry { x: 100, y: 100, width: 800, height: 630 }));
+        assert_eq!(info.pid, Some(4242));
+        assert_eq!(info.window_type.as_deref(), Some("normal"));
+        assert!(info.focused);
+
+        // XWayland windows come from the X11 backend instead
+        assert!(native_window_info(&json!({ "windowId": 7, "internalId": "{5678}" })).is_none());
+        assert!(native_window_info(&json!({ "windowId": 0, "internalId": "" })).is_none());
+    }
+
+    #[test]
+    fn merge_restacks_in_kwin_order() {
+        let mut windows = vec![x11_window(1, 0), x11_window(2, 1), x11_window(3, 2)];
+        // KWin's bottom-to-top order puts the native window between 2 and 1; 3 isn't listed
+        let listed = [json!({ "windowId": 2 }), native("{n}"), json!({ "windowId": 1 })];
+        merge_native_windows(&mut windows, &listed);
+
+        let ids: Vec<_> = windows.iter().map(|w| w.platform_id.clone()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                PlatformWindowId::X11(1),
+                PlatformWindowId::X11(2),
+                PlatformWindowId::X11(3),
+                PlatformWindowId::Wayland("{n}".to_string()),
+            ],
+            "list order is kept for ref assignment"
+        );
+        let stacking: Vec<_> = windows.iter().map(|w| w.stacking_index).collect();
+        assert_eq!(stacking, vec![3, 1, 0, 2]);
+    }
+
+    #[test]
+    fn escapes_line_breaks() {
+        assert_eq!(js_string_literal("a\nb\rc"), r#""a\nb\rc""#);
+        assert_eq!(js_string_literal("a\u{2028}b\u{2029}c"), r#""a\u2028b\u2029c""#);
+    }
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        assert_eq!(js_string_literal(r#"it's "quoted""#), r#""it's \"quoted\"""#);
+        assert_eq!(js_string_literal(r"C:\path\"), r#""C:\\path\\""#);
+        // A trailing backslash can't escape the closing quote
+        assert!(!has_unescaped_quote(&literal_body(r#"\"); workspace.activeWindow = null; (""#)));
+    }
+
+    #[test]
+    fn template_and_script_syntax_stays_inert() {
+        // Double-quoted literals don't interpolate `${...}`
+        assert_eq!(js_string_literal("${workspace.activeWindow}"), r#""${workspace.activeWindow}""#);
+        assert_eq!(js_string_literal("`${x}`"), r#""`${x}`""#);
+        assert_eq!(js_string_literal("</script><script>"), r#""</script><script>""#);
+    }
+
+    #[test]
+    fn no_raw_control_characters() {
+        let nasty = "title\n\"); callDBus(\"x\"); //\u{2028}\u{2029}\t\0\\";
+        let body = literal_body(nasty);
+        assert!(!has_unescaped_quote(&body));
+        assert!(!body.chars().any(|c| c.is_control() || c == '\u{2028}' || c == '\u{2029}'), "{}", body);
+        // Round-trips through a JSON parser, which shares JavaScript's string syntax
+        assert_eq!(serde_json::from_str::<String>(&js_string_literal(nasty)).unwrap(), nasty);
     }
 }
@@ -3,7 +3,12 @@
 //! This module provides a platform-agnostic interface for window operations,
 //! with implementations for X11 and Wayland.
 
+pub mod atspi;
+pub mod hotkey;
+mod gnome;
+mod hyprland;
 mod kwin;
+mod sway;
 mod x11;
 
 use async_trait::async_trait;
@@ -17,25 +22,236 @@ pub struct WindowInfo {
     pub platform_id: PlatformWindowId,
     pub title: String,
     pub class: String,
+    /// Instance name, the first `WM_CLASS` field (empty when unknown)
+    pub instance: String,
+    /// Client area, excluding decorations; click coordinates are relative to this
     pub geometry: Geometry,
+    /// Outer bounds including decorations, when the backend knows them
+    pub frame: Option<Geometry>,
     pub focused: bool,
     pub visible: bool,
+    /// Position in the stacking order, bottom-to-top (higher is closer to the top)
+    pub stacking_index: usize,
+    /// Process that owns the window, if it advertises one
+    pub pid: Option<u32>,
+    /// Virtual desktop the window is on, or -1 when it is shown on all desktops
+    pub desktop: Option<i64>,
+    /// EWMH window type (one of [`WINDOW_TYPES`], or [`UNKNOWN_WINDOW_TYPE`]), if the backend reports one
+    pub window_type: Option<String>,
+    /// Minimized (iconified, or parked on a scratchpad/special workspace)
+    pub minimized: bool,
+    /// Maximized horizontally (both maximized flags are set for a full maximize)
+    pub maximized_horz: bool,
+    /// Maximized vertically
+    pub maximized_vert: bool,
+    /// Fullscreen, covering panels without decorations
+    pub fullscreen: bool,
+}
+
+/// Window types from `_NET_WM_WINDOW_TYPE`, lowercased without the prefix
+pub const WINDOW_TYPES: &[&str] = &[
+    "normal",
+    "dialog",
+    "desktop",
+    "dock",
+    "toolbar",
+    "menu",
+    "utility",
+    "splash",
+    "dropdown_menu",
+    "popup_menu",
+    "tooltip",
+    "notification",
+    "combo",
+    "dnd",
+];
+
+/// Window type reported when `_NET_WM_WINDOW_TYPE` only lists types not in [`WINDOW_TYPES`]
+///
+/// Toolkit- and desktop-specific types (e.g. `_KDE_NET_WM_WINDOW_TYPE_OVERRIDE`)
+/// get this rather than no type, so the default type filter leaves them out.
+pub const UNKNOWN_WINDOW_TYPE: &str = "unknown";
+
+/// Virtual desktop layout
+#[derive(Debug, Clone, Copy)]
+pub struct DesktopInfo {
+    /// Index of the active desktop
+    pub current: u32,
+    /// Number of desktops
+    pub count: u32,
+}
+
+/// Information about a monitor/output
+#[derive(Debug, Clone)]
+pub struct MonitorInfo {
+    pub name: String,
+    /// Origin in the global screen coordinate space used by window geometry
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+impl MonitorInfo {
+    /// The monitor's bounds in screen coordinates
+    pub fn geometry(&self) -> Geometry {
+        Geometry {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Outcome of a focus request
+#[derive(Debug, Clone, Copy)]
+pub struct FocusOutcome {
+    /// Number of activation requests sent
+    pub attempts: u32,
+    /// Whether the window was confirmed active afterwards
+    pub verified: bool,
+}
+
+impl FocusOutcome {
+    /// A single request whose effect the backend can't observe
+    pub fn unverified() -> Self {
+        Self { attempts: 1, verified: false }
+    }
+}
+
+/// Mechanism used to close a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseMethod {
+    /// Politely asked the client to close (e.g., ICCCM `WM_DELETE_WINDOW`)
+    Graceful,
+    /// Forcibly disconnected the owning client (e.g., `XKillClient`)
+    Force,
+}
+
+/// Outcome of a close request
+#[derive(Debug, Clone)]
+pub struct CloseOutcome {
+    /// Mechanism that was actually used
+    pub method: CloseMethod,
+    /// Whether the window advertises support for graceful close
+    pub graceful_supported: bool,
 }
 
 /// Trait for window backend implementations
 #[async_trait]
 pub trait WindowBackend: Send + Sync {
+    /// Short name of the backend (e.g., "x11", "kwin")
+    fn name(&self) -> &'static str;
+
     /// List all windows
     async fn list_windows(&self) -> anyhow::Result<Vec<WindowInfo>>;
 
+    /// Check that the backend's connection and focus mechanism are reachable
+    ///
+    /// Returns a short description of what was verified. Defaults to listing windows.
+    async fn health_check(&self) -> anyhow::Result<String> {
+        let count = self.list_windows().await?.len();
+        Ok(format!("Listed {} windows", count))
+    }
+
     /// Focus a window
-    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<()>;
+    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<FocusOutcome>;
 
     /// Move a window
     async fn move_window(&self, id: &PlatformWindowId, x: i32, y: i32) -> anyhow::Result<()>;
 
     /// Resize a window
     async fn resize_window(&self, id: &PlatformWindowId, width: u32, height: u32) -> anyhow::Result<()>;
+
+    /// Re-read a single window's current geometry
+    ///
+    /// Defaults to a full enumeration; backends that can query one window directly override this.
+    async fn refresh_window(&self, id: &PlatformWindowId) -> anyhow::Result<Geometry> {
+        self.list_windows()
+            .await?
+            .into_iter()
+            .find(|w| &w.platform_id == id)
+            .map(|w| w.geometry)
+            .ok_or_else(|| anyhow::anyhow!("Window not found"))
+    }
+
+    /// Minimize (iconify) a window
+    async fn minimize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()>;
+
+    /// Maximize a window (idempotent: an already maximized window stays maximized)
+    async fn maximize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()>;
+
+    /// Restore a window from the minimized or maximized state
+    async fn restore_window(&self, id: &PlatformWindowId) -> anyhow::Result<()>;
+
+    /// Close a window, either gracefully or by killing its client when `force` is set
+    async fn close_window(&self, id: &PlatformWindowId, force: bool) -> anyhow::Result<CloseOutcome>;
+
+    /// Raise a window to the top of the stacking order without focusing it
+    async fn raise_window(&self, _id: &PlatformWindowId) -> anyhow::Result<()> {
+        anyhow::bail!("Raising windows without focus is not supported by this backend")
+    }
+
+    /// Lower a window to the bottom of the stacking order without changing focus
+    async fn lower_window(&self, _id: &PlatformWindowId) -> anyhow::Result<()> {
+        anyhow::bail!("Lowering windows is not supported by this backend")
+    }
+
+    /// Keep a window above all others, or return it to normal stacking
+    ///
+    /// Sets the state explicitly rather than toggling, so repeated calls are idempotent.
+    async fn set_always_on_top(&self, _id: &PlatformWindowId, _enabled: bool) -> anyhow::Result<()> {
+        anyhow::bail!("Always-on-top is not supported by this backend")
+    }
+
+    /// Make a window fullscreen (no decorations, covering panels) or leave fullscreen
+    ///
+    /// Sets the state explicitly rather than toggling, so repeated calls are idempotent.
+    async fn set_fullscreen(&self, _id: &PlatformWindowId, _enabled: bool) -> anyhow::Result<()> {
+        anyhow::bail!("Fullscreen is not supported by this backend")
+    }
+
+    /// Set window opacity, from 0.0 (transparent) to 1.0 (opaque)
+    ///
+    /// Takes effect only under a compositing window manager.
+    async fn set_opacity(&self, _id: &PlatformWindowId, _opacity: f64) -> anyhow::Result<()> {
+        anyhow::bail!("Window opacity is not supported by this backend")
+    }
+
+    /// Get the pointer position in screen coordinates
+    async fn cursor_position(&self) -> anyhow::Result<(i32, i32)> {
+        anyhow::bail!("Cursor position queries are not supported by this backend")
+    }
+
+    /// Get the window's icon, or None if it doesn't set one
+    async fn window_icon(&self, _id: &PlatformWindowId) -> anyhow::Result<Option<image::RgbaImage>> {
+        Ok(None)
+    }
+
+    /// Get the active desktop and the number of desktops
+    async fn desktop_info(&self) -> anyhow::Result<DesktopInfo> {
+        anyhow::bail!("Virtual desktops are not supported by this backend")
+    }
+
+    /// Switch to another virtual desktop
+    async fn switch_desktop(&self, _desktop: u32) -> anyhow::Result<()> {
+        anyhow::bail!("Virtual desktops are not supported by this backend")
+    }
+
+    /// Move a window to another virtual desktop
+    async fn move_window_to_desktop(&self, _id: &PlatformWindowId, _desktop: u32) -> anyhow::Result<()> {
+        anyhow::bail!("Virtual desktops are not supported by this backend")
+    }
+
+    /// List monitors/outputs
+    ///
+    /// Defaults to xcap, which works on X11 and most Wayland compositors.
+    async fn list_monitors(&self) -> anyhow::Result<Vec<MonitorInfo>> {
+        crate::screenshot::monitors::list_monitors().await
+    }
 }
 
 /// Detect if running on KDE Plasma
@@ -48,19 +264,85 @@ fn is_kde_plasma() -> bool {
             .unwrap_or(false)
 }
 
+/// Detect if running on GNOME Shell
+fn is_gnome() -> bool {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|d| d.to_uppercase().split(':').any(|part| part == "GNOME"))
+        .unwrap_or(false)
+}
+
+/// Detect if running on Hyprland
+fn is_hyprland() -> bool {
+    std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok()
+}
+
+/// Detect if running on Sway (or i3 via its compatible IPC)
+fn is_sway() -> bool {
+    std::env::var("SWAYSOCK").is_ok() || std::env::var("I3SOCK").is_ok()
+}
+
 /// Detect if running on Wayland
-fn is_wayland() -> bool {
+pub(crate) fn is_wayland() -> bool {
     std::env::var("WAYLAND_DISPLAY").is_ok()
         || std::env::var("XDG_SESSION_TYPE")
             .map(|t| t.to_lowercase() == "wayland")
             .unwrap_or(false)
 }
 
+/// Whether a D-Bus name currently has an owner on the given bus
+pub(crate) async fn dbus_name_has_owner(dbus: &zbus::Connection, name: &str) -> anyhow::Result<bool> {
+    let reply = dbus
+        .call_method(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            Some("org.freedesktop.DBus"),
+            "NameHasOwner",
+            &(name,),
+        )
+        .await?;
+    Ok(reply.body().deserialize()?)
+}
+
+/// Session type as reported by backend_info: "wayland" or "x11"
+pub fn session_type() -> &'static str {
+    if is_wayland() { "wayland" } else { "x11" }
+}
+
 /// Create the appropriate backend for the current environment
 pub async fn create_backend() -> anyhow::Result<Arc<dyn WindowBackend>> {
     let display_env = std::env::var("DISPLAY").ok();
     let wayland = is_wayland();
     let kde = is_kde_plasma();
+    let gnome = is_gnome();
+
+    // On Hyprland, the IPC socket handles native Wayland windows directly
+    if is_hyprland() {
+        tracing::info!(
+            "Using Hyprland backend (HYPRLAND_INSTANCE_SIGNATURE={})",
+            std::env::var("HYPRLAND_INSTANCE_SIGNATURE").unwrap_or_default()
+        );
+        match hyprland::HyprlandBackend::new().await {
+            Ok(hyprland_backend) => return Ok(Arc::new(hyprland_backend)),
+            Err(e) => {
+                tracing::warn!("Failed to initialize Hyprland backend, falling back to X11: {}", e);
+            }
+        }
+    }
+
+    // On Sway, the IPC socket handles both native Wayland and XWayland windows
+    if is_sway() {
+        tracing::info!(
+            "Using Sway backend (SWAYSOCK={}, I3SOCK={})",
+            std::env::var("SWAYSOCK").unwrap_or_default(),
+            std::env::var("I3SOCK").unwrap_or_default()
+        );
+        match sway::SwayBackend::new().await {
+            Ok(sway_backend) => return Ok(Arc::new(sway_backend)),
+            Err(e) => {
+                tracing::warn!("Failed to initialize Sway backend, falling back to X11: {}", e);
+            }
+        }
+    }
 
     // Always need X11/XWayland for window enumeration
     if display_env.is_none() {
@@ -83,6 +365,20 @@ pub async fn create_backend() -> anyhow::Result<Arc<dyn WindowBackend>> {
         }
     }
 
+    // On GNOME Wayland, use GNOME Shell for proper window focus
+    if wayland && gnome {
+        tracing::info!(
+            "Using GNOME Shell backend (WAYLAND_DISPLAY={}, GNOME detected)",
+            std::env::var("WAYLAND_DISPLAY").unwrap_or_default()
+        );
+        match gnome::GnomeBackend::new(x11_backend.clone()).await {
+            Ok(gnome_backend) => return Ok(Arc::new(gnome_backend)),
+            Err(e) => {
+                tracing::warn!("Failed to initialize GNOME Shell backend, falling back to X11: {}", e);
+            }
+        }
+    }
+
     // Default to pure X11 backend
     tracing::info!(
         "Using X11 backend (DISPLAY={})",
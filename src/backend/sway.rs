@@ -0,0 +1,426 @@
+//! Sway/i3 backend using the i3 IPC protocol
+//!
+//! Sway's `_NET_ACTIVE_WINDOW` support only covers XWayland windows, so this
+//! backend talks to the compositor directly over its IPC socket. Windows are
+//! enumerated from `get_tree`, and XWayland windows keep their X11 IDs so refs
+//! correlate with the X11 backend (and xcap screenshots keep working).
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+use super::{CloseMethod, CloseOutcome, FocusOutcome, MonitorInfo, WindowBackend, WindowInfo};
+use crate::core::registry::{Geometry, PlatformWindowId};
+
+/// i3 IPC magic string that prefixes every message
+const IPC_MAGIC: &[u8; 6] = b"i3-ipc";
+
+/// i3 IPC message types
+const IPC_RUN_COMMAND: u32 = 0;
+const IPC_GET_OUTPUTS: u32 = 3;
+const IPC_GET_TREE: u32 = 4;
+
+/// Name of the hidden workspace holding scratchpad windows
+const SCRATCHPAD_WORKSPACE: &str = "__i3_scratch";
+
+/// Sway/i3 backend that uses the IPC socket for all operations
+pub struct SwayBackend {
+    /// Path to the IPC socket
+    socket_path: PathBuf,
+}
+
+impl SwayBackend {
+    /// Create a new Sway backend from `$SWAYSOCK` or `$I3SOCK`
+    pub async fn new() -> anyhow::Result<Self> {
+        let socket_path = std::env::var("SWAYSOCK")
+            .or_else(|_| std::env::var("I3SOCK"))
+            .map(PathBuf::from)
+            .map_err(|_| anyhow::anyhow!("Neither SWAYSOCK nor I3SOCK is set"))?;
+
+        let backend = Self { socket_path };
+
+        // Verify the socket is reachable before committing to this backend
+        backend.request(IPC_GET_TREE, "").await?;
+
+        Ok(backend)
+    }
+
+    /// Send a single IPC message and return the parsed JSON reply
+    async fn request(&self, message_type: u32, payload: &str) -> anyhow::Result<Value> {
+        let mut stream = UnixStream::connect(&self.socket_path).await?;
+
+        let mut message = Vec::with_capacity(14 + payload.len());
+        message.extend_from_slice(IPC_MAGIC);
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(payload.as_bytes());
+        stream.write_all(&message).await?;
+
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header).await?;
+        if &header[0..6] != IPC_MAGIC {
+            anyhow::bail!("Invalid IPC reply from compositor");
+        }
+        let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+
+        let mut body = vec![0u8; length];
+        stream.read_exact(&mut body).await?;
+
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Run a command and fail if the compositor reports an error
+    async fn run_command(&self, command: &str) -> anyhow::Result<()> {
+        tracing::debug!("Running sway command: {}", command);
+
+        let reply = self.request(IPC_RUN_COMMAND, command).await?;
+
+        // RUN_COMMAND replies with one result object per command
+        for result in reply.as_array().into_iter().flatten() {
+            if !result["success"].as_bool().unwrap_or(false) {
+                let error = result["error"].as_str().unwrap_or("unknown error");
+                anyhow::bail!("sway command '{}' failed: {}", command, error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build a criteria expression selecting a window
+    fn criteria(id: &PlatformWindowId) -> String {
+        match id {
+            // XWayland windows are matched by their X11 window ID
+            PlatformWindowId::X11(window_id) => format!("[id={}]", window_id),
+            PlatformWindowId::Wayland(con_id) => format!("[con_id={}]", con_id),
+        }
+    }
+
+    /// Fetch the tree and flatten it into its leaf window nodes
+    async fn window_nodes(&self) -> anyhow::Result<Vec<(Value, bool)>> {
+        let tree = self.request(IPC_GET_TREE, "").await?;
+        let mut nodes = Vec::new();
+        collect_windows(&tree, false, &mut nodes);
+        Ok(nodes)
+    }
+
+    /// Whether a window currently lives on the scratchpad
+    async fn is_in_scratchpad(&self, id: &PlatformWindowId) -> anyhow::Result<bool> {
+        let nodes = self.window_nodes().await?;
+        Ok(nodes
+            .iter()
+            .any(|(node, in_scratchpad)| *in_scratchpad && &platform_id(node) == id))
+    }
+}
+
+/// Recursively collect window leaves from the tree
+///
+/// Containers (splits, tabs, workspaces, outputs) have children and are skipped;
+/// only leaves that hold an actual view are reported.
+fn collect_windows(node: &Value, in_scratchpad: bool, out: &mut Vec<(Value, bool)>) {
+    let in_scratchpad = in_scratchpad
+        || (node["type"] == "workspace" && node["name"] == SCRATCHPAD_WORKSPACE);
+
+    let children: Vec<&Value> = node["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .chain(node["floating_nodes"].as_array().into_iter().flatten())
+        .collect();
+
+    let is_view = !node["app_id"].is_null() || !node["window"].is_null();
+    let is_container_type = node["type"] == "con" || node["type"] == "floating_con";
+
+    if children.is_empty() && is_container_type && is_view {
+        out.push((node.clone(), in_scratchpad));
+        return;
+    }
+
+    for child in children {
+        collect_windows(child, in_scratchpad, out);
+    }
+}
+
+/// Derive the platform ID for a window node
+fn platform_id(node: &Value) -> PlatformWindowId {
+    match node["window"].as_u64() {
+        Some(window_id) => PlatformWindowId::X11(window_id as u32),
+        None => PlatformWindowId::Wayland(node["id"].as_u64().unwrap_or(0).to_string()),
+    }
+}
+
+/// Convert a window node into backend window info
+fn window_info(node: &Value, in_scratchpad: bool, stacking_index: usize) -> WindowInfo {
+    let title = node["name"].as_str().unwrap_or_default().to_string();
+
+    // Native Wayland windows have an app_id; XWayland windows expose WM_CLASS
+    let class = node["app_id"]
+        .as_str()
+        .or_else(|| node["window_properties"]["class"].as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    // window_rect is the content area relative to the container rect
+    let rect = &node["rect"];
+    let window_rect = &node["window_rect"];
+    let geometry = Geometry {
+        x: (rect["x"].as_i64().unwrap_or(0) + window_rect["x"].as_i64().unwrap_or(0)) as i32,
+        y: (rect["y"].as_i64().unwrap_or(0) + window_rect["y"].as_i64().unwrap_or(0)) as i32,
+        width: window_rect["width"].as_u64().unwrap_or(0) as u32,
+        height: window_rect["height"].as_u64().unwrap_or(0) as u32,
+    };
+
+    // XWayland windows report the WM_CLASS instance; native ones only have an app_id
+    let instance = node["window_properties"]["instance"]
+        .as_str()
+        .or_else(|| node["app_id"].as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    // The container rect includes borders and the title bar
+    let frame = Geometry {
+        x: rect["x"].as_i64().unwrap_or(0) as i32,
+        y: rect["y"].as_i64().unwrap_or(0) as i32,
+        width: rect["width"].as_u64().unwrap_or(0) as u32,
+        height: rect["height"].as_u64().unwrap_or(0) as u32,
+    };
+
+    WindowInfo {
+        platform_id: platform_id(node),
+        title,
+        class,
+        instance,
+        geometry,
+        frame: Some(frame),
+        focused: node["focused"].as_bool().unwrap_or(false),
+        visible: !in_scratchpad && node["visible"].as_bool().unwrap_or(true),
+        stacking_index,
+        pid: node["pid"].as_u64().filter(|&pid| pid != 0).map(|pid| pid as u32),
+        desktop: None,
+        window_type: None,
+        minimized: in_scratchpad,
+        // Tiling layouts have no maximized state
+        maximized_horz: false,
+        maximized_vert: false,
+        // fullscreen_mode is 1 for output fullscreen and 2 for global fullscreen
+        fullscreen: node["fullscreen_mode"].as_u64().unwrap_or(0) != 0,
+    }
+}
+
+#[async_trait]
+impl WindowBackend for SwayBackend {
+    fn name(&self) -> &'static str {
+        "sway"
+    }
+
+    async fn list_windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
+        let nodes = self.window_nodes().await?;
+
+        // Sway has no global stacking list; tree order puts floating windows after
+        // tiled ones on each workspace, which matches what is drawn on top
+        Ok(nodes
+            .iter()
+            .enumerate()
+            .map(|(index, (node, in_scratchpad))| window_info(node, *in_scratchpad, index))
+            .collect())
+    }
+
+    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<FocusOutcome> {
+        self.run_command(&format!("{} focus", Self::criteria(id))).await?;
+        Ok(FocusOutcome::unverified())
+    }
+
+    async fn move_window(&self, id: &PlatformWindowId, x: i32, y: i32) -> anyhow::Result<()> {
+        // Only floating windows can be positioned absolutely
+        self.run_command(&format!(
+            "{} move absolute position {} px {} px",
+            Self::criteria(id),
+            x,
+            y
+        ))
+        .await
+    }
+
+    async fn resize_window(&self, id: &PlatformWindowId, width: u32, height: u32) -> anyhow::Result<()> {
+        self.run_command(&format!(
+            "{} resize set width {} px height {} px",
+            Self::criteria(id),
+            width,
+            height
+        ))
+        .await
+    }
+
+    async fn minimize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        // Sway has no iconified state; the scratchpad is the closest equivalent
+        self.run_command(&format!("{} move scratchpad", Self::criteria(id))).await
+    }
+
+    async fn maximize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        // Tiling layouts have no maximized state, so use fullscreen (idempotent with enable)
+        self.run_command(&format!("{} fullscreen enable", Self::criteria(id))).await
+    }
+
+    async fn restore_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let criteria = Self::criteria(id);
+
+        if self.is_in_scratchpad(id).await? {
+            // Bring it back from the scratchpad and return it to the tiling layout
+            self.run_command(&format!("{} scratchpad show", criteria)).await?;
+            self.run_command(&format!("{} floating disable", criteria)).await?;
+        }
+
+        self.run_command(&format!("{} fullscreen disable", criteria)).await
+    }
+
+    async fn set_fullscreen(&self, id: &PlatformWindowId, enabled: bool) -> anyhow::Result<()> {
+        let action = if enabled { "enable" } else { "disable" };
+        self.run_command(&format!("{} fullscreen {}", Self::criteria(id), action)).await
+    }
+
+    async fn close_window(&self, id: &PlatformWindowId, force: bool) -> anyhow::Result<CloseOutcome> {
+        if !force {
+            // `kill` asks the client to close (xdg_toplevel.close / WM_DELETE_WINDOW)
+            self.run_command(&format!("{} kill", Self::criteria(id))).await?;
+            return Ok(CloseOutcome {
+                method: CloseMethod::Graceful,
+                graceful_supported: true,
+            });
+        }
+
+        // The IPC has no forceful close, so kill the owning process instead
+        let nodes = self.window_nodes().await?;
+        let pid = nodes
+            .iter()
+            .find(|(node, _)| &platform_id(node) == id)
+            .and_then(|(node, _)| node["pid"].as_u64())
+            .ok_or_else(|| anyhow::anyhow!("Could not determine PID of window"))?;
+
+        let status = tokio::process::Command::new("kill")
+            .args(["-KILL", &pid.to_string()])
+            .status()
+            .await?;
+
+        if !status.success() {
+            anyhow::bail!("Failed to kill process {}", pid);
+        }
+
+        Ok(CloseOutcome {
+            method: CloseMethod::Force,
+            graceful_supported: true,
+        })
+    }
+
+    async fn list_monitors(&self) -> anyhow::Result<Vec<MonitorInfo>> {
+        let outputs = self.request(IPC_GET_OUTPUTS, "").await?;
+
+        // Sway has no primary output; report the focused one as primary
+        Ok(outputs
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|o| o["active"].as_bool().unwrap_or(true))
+            .map(|o| MonitorInfo {
+                name: o["name"].as_str().unwrap_or_default().to_string(),
+                x: o["rect"]["x"].as_i64().unwrap_or(0) as i32,
+                y: o["rect"]["y"].as_i64().unwrap_or(0) as i32,
+                width: o["rect"]["width"].as_u64().unwrap_or(0) as u32,
+                height: o["rect"]["height"].as_u64().unwrap_or(0) as u32,
+                scale_factor: o["scale"].as_f64().unwrap_or(1.0),
+                primary: o["focused"].as_bool().unwrap_or(false),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Collect windows from a tree and return their node IDs and scratchpad flags
+    fn collected(tree: &Value) -> Vec<(u64, bool)> {
+        let mut out = Vec::new();
+        collect_windows(tree, false, &mut out);
+        out.iter().map(|(node, in_scratchpad)| (node["id"].as_u64().unwrap(), *in_scratchpad)).collect()
+    }
+
+    fn view(id: u64, app_id: &str) -> Value {
+        json!({ "id": id, "type": "con", "app_id": app_id, "nodes": [], "floating_nodes": [] })
+    }
+
+    #[test]
+    fn collects_leaves_of_nested_split_and_tabbed_containers() {
+        let tree = json!({
+            "id": 1, "type": "root", "nodes": [{
+                "id": 2, "type": "output", "nodes": [{
+                    "id": 3, "type": "workspace", "name": "1", "nodes": [
+                        view(10, "foot"),
+                        { "id": 4, "type": "con", "layout": "splitv", "nodes": [
+                            view(11, "firefox"),
+                            { "id": 5, "type": "con", "layout": "tabbed", "nodes": [
+                                view(12, "code"),
+                                view(13, "gimp")
+                            ]}
+                        ]}
+                    ]
+                }]
+            }]
+        });
+        assert_eq!(collected(&tree), vec![(10, false), (11, false), (12, false), (13, false)]);
+    }
+
+    #[test]
+    fn collects_floating_nodes_after_tiled_ones() {
+        let tree = json!({
+            "id": 3, "type": "workspace", "name": "1",
+            "nodes": [view(10, "foot")],
+            "floating_nodes": [{ "id": 20, "type": "floating_con", "app_id": "pavucontrol" }]
+        });
+        assert_eq!(collected(&tree), vec![(10, false), (20, false)]);
+    }
+
+    #[test]
+    fn xwayland_leaf_without_app_id_is_collected() {
+        let tree = json!({
+            "id": 3, "type": "workspace", "name": "1",
+            "nodes": [{ "id": 30, "type": "con", "app_id": null, "window": 4194307 }]
+        });
+        assert_eq!(collected(&tree), vec![(30, false)]);
+        let mut out = Vec::new();
+        collect_windows(&tree, false, &mut out);
+        assert_eq!(platform_id(&out[0].0), PlatformWindowId::X11(4194307));
+    }
+
+    #[test]
+    fn skips_empty_containers_and_workspaces() {
+        let tree = json!({
+            "id": 1, "type": "root", "nodes": [{
+                "id": 2, "type": "output", "nodes": [
+                    { "id": 3, "type": "workspace", "name": "1", "nodes": [], "floating_nodes": [] },
+                    { "id": 4, "type": "workspace", "name": "2", "nodes": [
+                        { "id": 5, "type": "con", "layout": "splith", "nodes": [] }
+                    ]}
+                ]
+            }]
+        });
+        assert!(collected(&tree).is_empty());
+    }
+
+    #[test]
+    fn marks_windows_on_the_scratchpad() {
+        let tree = json!({
+            "id": 1, "type": "root", "nodes": [
+                { "id": 2, "type": "output", "name": "__i3", "nodes": [{
+                    "id": 3, "type": "workspace", "name": SCRATCHPAD_WORKSPACE, "nodes": [],
+                    "floating_nodes": [{ "id": 6, "type": "floating_con", "nodes": [view(40, "keepassxc")] }]
+                }]},
+                { "id": 7, "type": "output", "name": "eDP-1", "nodes": [{
+                    "id": 8, "type": "workspace", "name": "1", "nodes": [view(41, "foot")]
+                }]}
+            ]
+        });
+        assert_eq!(collected(&tree), vec![(40, true), (41, false)]);
+    }
+}
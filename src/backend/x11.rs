@@ -1,16 +1,22 @@
 //! X11 window backend using x11rb
 
 use async_trait::async_trait;
+use std::sync::{Mutex, MutexGuard};
 use x11rb::connection::Connection;
 use x11rb::protocol::xproto::{self, Atom, AtomEnum, ConnectionExt, Window};
 use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
 
-use super::{WindowBackend, WindowInfo};
+use super::{
+    CloseMethod, CloseOutcome, DesktopInfo, FocusOutcome, WindowBackend, WindowInfo, UNKNOWN_WINDOW_TYPE, WINDOW_TYPES,
+};
 use crate::core::registry::{Geometry, PlatformWindowId};
 
 /// X11 window backend
 pub struct X11Backend {
-    conn: RustConnection,
+    /// Locked per request so concurrent tool calls send and await their
+    /// requests one at a time; use [`X11Backend::conn`]
+    conn: Mutex<RustConnection>,
     root: Window,
     atoms: X11Atoms,
 }
@@ -18,15 +24,63 @@ pub struct X11Backend {
 /// Cached X11 atoms for efficiency
 struct X11Atoms {
     net_client_list: Atom,
+    net_client_list_stacking: Atom,
     net_wm_name: Atom,
+    net_wm_pid: Atom,
+    net_wm_desktop: Atom,
+    net_current_desktop: Atom,
+    net_number_of_desktops: Atom,
     net_active_window: Atom,
     wm_class: Atom,
     wm_name: Atom,
     utf8_string: Atom,
     net_wm_state: Atom,
     net_wm_state_hidden: Atom,
+    net_wm_state_maximized_horz: Atom,
+    net_wm_state_maximized_vert: Atom,
+    net_wm_state_above: Atom,
+    net_wm_state_fullscreen: Atom,
+    net_wm_window_opacity: Atom,
+    net_wm_icon: Atom,
+    net_frame_extents: Atom,
+    wm_change_state: Atom,
+    wm_protocols: Atom,
+    wm_delete_window: Atom,
+    wm_state: Atom,
+    net_wm_window_type: Atom,
+    /// `_NET_WM_WINDOW_TYPE_*` atoms paired with their [`WINDOW_TYPES`] names
+    window_types: Vec<(Atom, &'static str)>,
 }
 
+/// `_NET_WM_STATE` client message actions
+const NET_WM_STATE_REMOVE: u32 = 0;
+const NET_WM_STATE_ADD: u32 = 1;
+
+/// ICCCM `WM_STATE` value for an iconified (minimized) window
+const ICONIC_STATE: u32 = 3;
+
+/// Window state read from `_NET_WM_STATE` and `WM_STATE`
+#[derive(Debug, Default, Clone, Copy)]
+struct WmState {
+    hidden: bool,
+    iconic: bool,
+    maximized_horz: bool,
+    maximized_vert: bool,
+    fullscreen: bool,
+}
+
+/// How deep below a top-level frame to look for the client window
+const CLIENT_SEARCH_DEPTH: u32 = 2;
+
+/// How many times `_NET_ACTIVE_WINDOW` is requested before giving up
+const FOCUS_ATTEMPTS: u32 = 3;
+
+/// How many times the active window is re-read after each request
+const FOCUS_VERIFY_POLLS: u32 = 5;
+
+/// Delay between reads of the active window
+const FOCUS_VERIFY_INTERVAL_MS: u64 = 20;
+
 impl X11Backend {
     /// Create a new X11 backend
     pub fn new() -> anyhow::Result<Self> {
@@ -37,33 +91,82 @@ impl X11Backend {
         // Intern atoms we need
         let atoms = Self::intern_atoms(&conn)?;
 
-        Ok(Self { conn, root, atoms })
+        Ok(Self { conn: Mutex::new(conn), root, atoms })
+    }
+
+    /// Lock the connection for one request and its reply
+    ///
+    /// The guard is a temporary, so it is released at the end of the statement
+    /// and never held across an await.
+    fn conn(&self) -> MutexGuard<'_, RustConnection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
     }
 
     fn intern_atoms(conn: &RustConnection) -> anyhow::Result<X11Atoms> {
         let net_client_list = conn.intern_atom(false, b"_NET_CLIENT_LIST")?.reply()?.atom;
+        let net_client_list_stacking = conn.intern_atom(false, b"_NET_CLIENT_LIST_STACKING")?.reply()?.atom;
         let net_wm_name = conn.intern_atom(false, b"_NET_WM_NAME")?.reply()?.atom;
+        let net_wm_pid = conn.intern_atom(false, b"_NET_WM_PID")?.reply()?.atom;
+        let net_wm_desktop = conn.intern_atom(false, b"_NET_WM_DESKTOP")?.reply()?.atom;
+        let net_current_desktop = conn.intern_atom(false, b"_NET_CURRENT_DESKTOP")?.reply()?.atom;
+        let net_number_of_desktops = conn.intern_atom(false, b"_NET_NUMBER_OF_DESKTOPS")?.reply()?.atom;
         let net_active_window = conn.intern_atom(false, b"_NET_ACTIVE_WINDOW")?.reply()?.atom;
         let wm_class = conn.intern_atom(false, b"WM_CLASS")?.reply()?.atom;
         let wm_name = conn.intern_atom(false, b"WM_NAME")?.reply()?.atom;
         let utf8_string = conn.intern_atom(false, b"UTF8_STRING")?.reply()?.atom;
         let net_wm_state = conn.intern_atom(false, b"_NET_WM_STATE")?.reply()?.atom;
         let net_wm_state_hidden = conn.intern_atom(false, b"_NET_WM_STATE_HIDDEN")?.reply()?.atom;
+        let net_wm_state_maximized_horz = conn.intern_atom(false, b"_NET_WM_STATE_MAXIMIZED_HORZ")?.reply()?.atom;
+        let net_wm_state_maximized_vert = conn.intern_atom(false, b"_NET_WM_STATE_MAXIMIZED_VERT")?.reply()?.atom;
+        let net_wm_state_above = conn.intern_atom(false, b"_NET_WM_STATE_ABOVE")?.reply()?.atom;
+        let net_wm_state_fullscreen = conn.intern_atom(false, b"_NET_WM_STATE_FULLSCREEN")?.reply()?.atom;
+        let net_wm_window_opacity = conn.intern_atom(false, b"_NET_WM_WINDOW_OPACITY")?.reply()?.atom;
+        let net_wm_icon = conn.intern_atom(false, b"_NET_WM_ICON")?.reply()?.atom;
+        let net_frame_extents = conn.intern_atom(false, b"_NET_FRAME_EXTENTS")?.reply()?.atom;
+        let wm_change_state = conn.intern_atom(false, b"WM_CHANGE_STATE")?.reply()?.atom;
+        let wm_protocols = conn.intern_atom(false, b"WM_PROTOCOLS")?.reply()?.atom;
+        let wm_delete_window = conn.intern_atom(false, b"WM_DELETE_WINDOW")?.reply()?.atom;
+        let wm_state = conn.intern_atom(false, b"WM_STATE")?.reply()?.atom;
+        let net_wm_window_type = conn.intern_atom(false, b"_NET_WM_WINDOW_TYPE")?.reply()?.atom;
+
+        let mut window_types = Vec::with_capacity(WINDOW_TYPES.len());
+        for &name in WINDOW_TYPES {
+            let atom_name = format!("_NET_WM_WINDOW_TYPE_{}", name.to_uppercase());
+            window_types.push((conn.intern_atom(false, atom_name.as_bytes())?.reply()?.atom, name));
+        }
 
         Ok(X11Atoms {
             net_client_list,
+            net_client_list_stacking,
             net_wm_name,
+            net_wm_pid,
+            net_wm_desktop,
+            net_current_desktop,
+            net_number_of_desktops,
             net_active_window,
             wm_class,
             wm_name,
             utf8_string,
             net_wm_state,
             net_wm_state_hidden,
+            net_wm_state_maximized_horz,
+            net_wm_state_maximized_vert,
+            net_wm_state_above,
+            net_wm_state_fullscreen,
+            net_wm_window_opacity,
+            net_wm_icon,
+            net_frame_extents,
+            wm_change_state,
+            wm_protocols,
+            wm_delete_window,
+            wm_state,
+            net_wm_window_type,
+            window_types,
         })
     }
 
     fn get_window_property(&self, window: Window, property: Atom, type_: Atom) -> anyhow::Result<Option<Vec<u8>>> {
-        let reply = self.conn.get_property(
+        let reply = self.conn().get_property(
             false,
             window,
             property,
@@ -79,48 +182,51 @@ impl X11Backend {
         }
     }
 
+    /// Read a format-32 property (WINDOW, ATOM, CARDINAL) as a list of values
+    ///
+    /// Uses the reply's format rather than reinterpreting raw bytes, so values
+    /// decode correctly whatever byte order the X server uses. Returns None if
+    /// the property is unset, empty, or not 32-bit.
+    fn get_u32_property(&self, window: Window, property: Atom, type_: Atom) -> anyhow::Result<Option<Vec<u32>>> {
+        let reply = self.conn().get_property(false, window, property, type_, 0, u32::MAX)?.reply()?;
+
+        Ok(reply
+            .value32()
+            .map(|values| values.collect::<Vec<u32>>())
+            .filter(|values| !values.is_empty()))
+    }
+
     fn get_window_title(&self, window: Window) -> String {
         // Try _NET_WM_NAME first (UTF-8)
-        if let Ok(Some(data)) = self.get_window_property(window, self.atoms.net_wm_name, self.atoms.utf8_string) {
-            if let Ok(s) = String::from_utf8(data) {
-                return s;
-            }
+        if let Ok(Some(data)) = self.get_window_property(window, self.atoms.net_wm_name, self.atoms.utf8_string)
+            && let Ok(s) = String::from_utf8(data)
+        {
+            return s;
         }
 
         // Fall back to WM_NAME
-        if let Ok(Some(data)) = self.get_window_property(window, self.atoms.wm_name, AtomEnum::STRING.into()) {
-            if let Ok(s) = String::from_utf8(data) {
-                return s;
-            }
+        if let Ok(Some(data)) = self.get_window_property(window, self.atoms.wm_name, AtomEnum::STRING.into())
+            && let Ok(s) = String::from_utf8(data)
+        {
+            return s;
         }
 
         String::new()
     }
 
-    fn get_window_class(&self, window: Window) -> String {
-        if let Ok(Some(data)) = self.get_window_property(window, self.atoms.wm_class, AtomEnum::STRING.into()) {
-            // WM_CLASS is two null-separated strings: instance name and class name
-            // We want the class name (second one)
-            let parts: Vec<&[u8]> = data.split(|&b| b == 0).collect();
-            if parts.len() >= 2 {
-                if let Ok(s) = std::str::from_utf8(parts[1]) {
-                    return s.to_string();
-                }
-            }
-            if let Some(part) = parts.first() {
-                if let Ok(s) = std::str::from_utf8(part) {
-                    return s.to_string();
-                }
-            }
+    /// Read `WM_CLASS` as (instance, class)
+    fn get_window_class(&self, window: Window) -> (String, String) {
+        match self.get_window_property(window, self.atoms.wm_class, AtomEnum::STRING.into()) {
+            Ok(Some(data)) => parse_wm_class(&data),
+            _ => (String::new(), String::new()),
         }
-        String::new()
     }
 
     fn get_window_geometry(&self, window: Window) -> anyhow::Result<Geometry> {
-        let geom = self.conn.get_geometry(window)?.reply()?;
+        let geom = self.conn().get_geometry(window)?.reply()?;
 
         // Translate to root window coordinates
-        let translated = self.conn.translate_coordinates(window, self.root, 0, 0)?.reply()?;
+        let translated = self.conn().translate_coordinates(window, self.root, 0, 0)?.reply()?;
 
         Ok(Geometry {
             x: translated.dst_x as i32,
@@ -130,104 +236,376 @@ impl X11Backend {
         })
     }
 
+    /// Outer bounds of the window including decorations, from `_NET_FRAME_EXTENTS`
+    ///
+    /// None when the window manager doesn't report extents (e.g. it isn't
+    /// reparenting or doesn't support the hint).
+    fn get_frame_geometry(&self, window: Window, client: &Geometry) -> Option<Geometry> {
+        let extents = self.get_u32_property(window, self.atoms.net_frame_extents, AtomEnum::CARDINAL.into()).ok()??;
+        let extents: [u32; 4] = extents.get(..4)?.try_into().ok()?;
+        frame_geometry(client, extents)
+    }
+
+    /// Read a single CARDINAL property, if set
+    fn get_cardinal(&self, window: Window, property: Atom) -> Option<u32> {
+        self.get_u32_property(window, property, AtomEnum::CARDINAL.into())
+            .ok()??
+            .first()
+            .copied()
+    }
+
+    fn get_window_pid(&self, window: Window) -> Option<u32> {
+        // _NET_WM_PID is optional; treat missing or zero as unknown
+        self.get_cardinal(window, self.atoms.net_wm_pid).filter(|&pid| pid != 0)
+    }
+
+    fn get_window_desktop(&self, window: Window) -> Option<i64> {
+        // 0xFFFFFFFF means the window is sticky (shown on all desktops)
+        self.get_cardinal(window, self.atoms.net_wm_desktop)
+            .map(|desktop| if desktop == u32::MAX { -1 } else { desktop as i64 })
+    }
+
+    /// Read `_NET_WM_WINDOW_TYPE`, taking the first type we recognize
+    ///
+    /// EWMH lists types in order of preference and says windows without the
+    /// property should be treated as normal. A property listing only types we
+    /// don't recognize gives [`UNKNOWN_WINDOW_TYPE`].
+    fn get_window_type(&self, window: Window) -> Option<String> {
+        let types = match self.get_u32_property(window, self.atoms.net_wm_window_type, AtomEnum::ATOM.into()) {
+            Ok(Some(types)) if !types.is_empty() => types,
+            _ => return Some("normal".to_string()),
+        };
+
+        let known = types.iter().find_map(|atom| {
+            self.atoms
+                .window_types
+                .iter()
+                .find(|(known, _)| known == atom)
+                .map(|(_, name)| name.to_string())
+        });
+        Some(known.unwrap_or_else(|| UNKNOWN_WINDOW_TYPE.to_string()))
+    }
+
     fn get_active_window(&self) -> Option<Window> {
-        if let Ok(Some(data)) = self.get_window_property(self.root, self.atoms.net_active_window, AtomEnum::WINDOW.into()) {
-            if data.len() >= 4 {
-                let window = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]);
-                if window != 0 {
-                    return Some(window);
-                }
-            }
+        self.get_u32_property(self.root, self.atoms.net_active_window, AtomEnum::WINDOW.into())
+            .ok()??
+            .first()
+            .copied()
+            .filter(|&window| window != 0)
+    }
+
+    fn get_wm_state(&self, window: Window) -> WmState {
+        let states = self
+            .get_u32_property(window, self.atoms.net_wm_state, AtomEnum::ATOM.into())
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        // WM_STATE is typed as itself; its first field is the ICCCM state
+        let iconic = self
+            .get_u32_property(window, self.atoms.wm_state, self.atoms.wm_state)
+            .ok()
+            .flatten()
+            .and_then(|values| values.first().copied())
+            == Some(ICONIC_STATE);
+
+        WmState {
+            hidden: states.contains(&self.atoms.net_wm_state_hidden),
+            iconic,
+            maximized_horz: states.contains(&self.atoms.net_wm_state_maximized_horz),
+            maximized_vert: states.contains(&self.atoms.net_wm_state_maximized_vert),
+            fullscreen: states.contains(&self.atoms.net_wm_state_fullscreen),
         }
-        None
-    }
-
-    fn is_window_visible(&self, window: Window) -> bool {
-        // Check _NET_WM_STATE for hidden state
-        if let Ok(Some(data)) = self.get_window_property(window, self.atoms.net_wm_state, AtomEnum::ATOM.into()) {
-            // Parse as array of atoms
-            for chunk in data.chunks(4) {
-                if chunk.len() == 4 {
-                    let atom = u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
-                    if atom == self.atoms.net_wm_state_hidden {
-                        return false;
-                    }
-                }
-            }
+    }
+
+    fn is_window_visible(&self, window: Window, state: &WmState) -> bool {
+        if state.hidden {
+            return false;
         }
 
         // Check if window is viewable
-        if let Ok(attrs) = self.conn.get_window_attributes(window) {
-            if let Ok(reply) = attrs.reply() {
-                return reply.map_state == xproto::MapState::VIEWABLE;
-            }
+        if let Ok(attrs) = self.conn().get_window_attributes(window)
+            && let Ok(reply) = attrs.reply()
+        {
+            return reply.map_state == xproto::MapState::VIEWABLE;
         }
 
         true
     }
+
+    /// Enumerate client windows by walking the tree, for window managers that
+    /// don't maintain `_NET_CLIENT_LIST`
+    ///
+    /// Returns windows bottom-to-top, since `query_tree` reports children in
+    /// stacking order.
+    fn tree_client_windows(&self) -> anyhow::Result<Vec<Window>> {
+        let children = self.conn().query_tree(self.root)?.reply()?.children;
+
+        let mut clients = Vec::new();
+        for child in children {
+            // Menus and tooltips set override-redirect; unmapped windows aren't shown
+            let Ok(attrs) = self.conn().get_window_attributes(child)?.reply() else {
+                continue;
+            };
+            if attrs.override_redirect || attrs.map_state != xproto::MapState::VIEWABLE {
+                continue;
+            }
+
+            // Reparenting WMs put the client inside a frame; non-reparenting ones
+            // that skip WM_STATE leave the top-level window as the only candidate
+            clients.push(self.find_client(child, CLIENT_SEARCH_DEPTH).unwrap_or(child));
+        }
+
+        Ok(clients)
+    }
+
+    /// Find the window carrying ICCCM `WM_STATE` at or below `window`
+    fn find_client(&self, window: Window, depth: u32) -> Option<Window> {
+        if let Ok(Some(_)) = self.get_window_property(window, self.atoms.wm_state, AtomEnum::ANY.into()) {
+            return Some(window);
+        }
+        if depth == 0 {
+            return None;
+        }
+
+        let children = self.conn().query_tree(window).ok()?.reply().ok()?.children;
+        children.into_iter().find_map(|child| self.find_client(child, depth - 1))
+    }
+
+    fn supports_delete_window(&self, window: Window) -> bool {
+        // WM_PROTOCOLS is an array of atoms the client participates in
+        self.get_u32_property(window, self.atoms.wm_protocols, AtomEnum::ATOM.into())
+            .ok()
+            .flatten()
+            .is_some_and(|protocols| protocols.contains(&self.atoms.wm_delete_window))
+    }
+
+    /// Send a client message to the root window, as EWMH requires for
+    /// requests that the window manager should act upon
+    fn send_root_client_message(&self, window: Window, message_type: Atom, data: [u32; 5]) -> anyhow::Result<()> {
+        let event = xproto::ClientMessageEvent::new(32, window, message_type, data);
+
+        self.conn().send_event(
+            false,
+            self.root,
+            xproto::EventMask::SUBSTRUCTURE_REDIRECT | xproto::EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )?;
+
+        self.conn().flush()?;
+        Ok(())
+    }
+
+    /// Add or remove up to two `_NET_WM_STATE` properties (pass 0 for the second to skip it)
+    fn set_wm_state(&self, window: Window, action: u32, first: Atom, second: Atom) -> anyhow::Result<()> {
+        self.send_root_client_message(
+            window,
+            self.atoms.net_wm_state,
+            [
+                action,
+                first,
+                second,
+                1, // Source indication: 1 = application
+                0,
+            ],
+        )
+    }
+
+    /// Add or remove both maximized states on a window
+    fn set_maximized(&self, window: Window, action: u32) -> anyhow::Result<()> {
+        self.set_wm_state(
+            window,
+            action,
+            self.atoms.net_wm_state_maximized_horz,
+            self.atoms.net_wm_state_maximized_vert,
+        )
+    }
+}
+
+/// Grow client geometry by `_NET_FRAME_EXTENTS` (left, right, top, bottom)
+///
+/// Extents come straight from the window manager, so nonsense values that
+/// would overflow yield None instead of a wrapped geometry.
+fn frame_geometry(client: &Geometry, [left, right, top, bottom]: [u32; 4]) -> Option<Geometry> {
+    Some(Geometry {
+        x: client.x.checked_sub(i32::try_from(left).ok()?)?,
+        y: client.y.checked_sub(i32::try_from(top).ok()?)?,
+        width: client.width.checked_add(left)?.checked_add(right)?,
+        height: client.height.checked_add(top)?.checked_add(bottom)?,
+    })
+}
+
+/// Split `WM_CLASS` into (instance, class)
+///
+/// The property is two NUL-terminated strings, but some clients set only one
+/// or drop the terminators. Fields are taken by position, so an empty instance
+/// stays empty; only a missing class falls back to the instance name.
+fn parse_wm_class(data: &[u8]) -> (String, String) {
+    let data = data.strip_suffix(&[0]).unwrap_or(data);
+    let mut fields = data.split(|&b| b == 0).map(decode_string);
+    let instance = fields.next().unwrap_or_default();
+    let class = fields.next().unwrap_or_else(|| instance.clone());
+    (instance, class)
+}
+
+/// Decode an X11 STRING property value
+///
+/// STRING is Latin-1 per ICCCM, but many clients write UTF-8 anyway, so UTF-8
+/// is tried first. Latin-1 maps every byte straight to its code point.
+fn decode_string(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// Pick the largest image out of `_NET_WM_ICON` data
+///
+/// The property holds one or more icons, each a width and height followed by
+/// width * height ARGB pixels. Truncated entries end the list.
+fn largest_icon(data: &[u32]) -> Option<image::RgbaImage> {
+    let mut largest: Option<(u32, u32, &[u32])> = None;
+
+    let mut rest = data;
+    while let [width, height, pixels @ ..] = rest {
+        let len = (*width as usize).checked_mul(*height as usize)?;
+        if len == 0 || pixels.len() < len {
+            break;
+        }
+        if largest.is_none_or(|(w, h, _)| width * height > w * h) {
+            largest = Some((*width, *height, &pixels[..len]));
+        }
+        rest = &pixels[len..];
+    }
+
+    let (width, height, pixels) = largest?;
+    let rgba = pixels
+        .iter()
+        .flat_map(|&argb| {
+            let [a, r, g, b] = argb.to_be_bytes();
+            [r, g, b, a]
+        })
+        .collect();
+
+    image::RgbaImage::from_raw(width, height, rgba)
 }
 
 #[async_trait]
 impl WindowBackend for X11Backend {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
     async fn list_windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
         let mut windows = Vec::new();
 
-        // Get _NET_CLIENT_LIST
-        let data = match self.get_window_property(self.root, self.atoms.net_client_list, AtomEnum::WINDOW.into())? {
-            Some(d) => d,
-            None => return Ok(windows),
-        };
+        // Prefer _NET_CLIENT_LIST; minimal window managers don't set it
+        let client_ids: Vec<Window> =
+            match self.get_u32_property(self.root, self.atoms.net_client_list, AtomEnum::WINDOW.into())? {
+                Some(windows) => {
+                    tracing::debug!("Enumerating windows from _NET_CLIENT_LIST");
+                    windows
+                }
+                None => {
+                    tracing::debug!("_NET_CLIENT_LIST is not set; enumerating windows with query_tree");
+                    self.tree_client_windows()?
+                }
+            };
 
         let active_window = self.get_active_window();
 
-        // Parse window IDs (each is 4 bytes)
-        for chunk in data.chunks(4) {
-            if chunk.len() == 4 {
-                let window_id = u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
-
-                // Get window info
-                let title = self.get_window_title(window_id);
-                let class = self.get_window_class(window_id);
-                let geometry = self.get_window_geometry(window_id).unwrap_or_default();
-                let focused = active_window == Some(window_id);
-                let visible = self.is_window_visible(window_id);
-
-                windows.push(WindowInfo {
-                    platform_id: PlatformWindowId::X11(window_id),
-                    title,
-                    class,
-                    geometry,
-                    focused,
-                    visible,
-                });
-            }
+        // _NET_CLIENT_LIST_STACKING lists the same windows bottom-to-top
+        let stacking: Vec<Window> = self
+            .get_u32_property(self.root, self.atoms.net_client_list_stacking, AtomEnum::WINDOW.into())
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        for (index, window_id) in client_ids.into_iter().enumerate() {
+            // Get window info
+            let title = self.get_window_title(window_id);
+            let (instance, class) = self.get_window_class(window_id);
+            // A window destroyed mid-enumeration fails here; leave it out rather
+            // than reporting a zero-size window at the screen origin
+            let geometry = match self.get_window_geometry(window_id) {
+                Ok(geometry) => geometry,
+                Err(e) => {
+                    tracing::debug!("Skipping window {:#x}, geometry query failed: {}", window_id, e);
+                    continue;
+                }
+            };
+            let frame = self.get_frame_geometry(window_id, &geometry);
+            let focused = active_window == Some(window_id);
+            let state = self.get_wm_state(window_id);
+            let visible = self.is_window_visible(window_id, &state);
+            // Without a stacking list, fall back to list order: mapping order for
+            // _NET_CLIENT_LIST, which WMs keep roughly bottom-to-top, and true
+            // stacking order for the query_tree fallback
+            let stacking_index = stacking
+                .iter()
+                .position(|&w| w == window_id)
+                .unwrap_or(index);
+
+            windows.push(WindowInfo {
+                platform_id: PlatformWindowId::X11(window_id),
+                title,
+                class,
+                instance,
+                geometry,
+                frame,
+                focused,
+                visible,
+                stacking_index,
+                pid: self.get_window_pid(window_id),
+                desktop: self.get_window_desktop(window_id),
+                window_type: self.get_window_type(window_id),
+                minimized: state.hidden || state.iconic,
+                maximized_horz: state.maximized_horz,
+                maximized_vert: state.maximized_vert,
+                fullscreen: state.fullscreen,
+            });
         }
 
         Ok(windows)
     }
 
-    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+    async fn focus_window(&self, id: &PlatformWindowId) -> anyhow::Result<FocusOutcome> {
         let PlatformWindowId::X11(window_id) = id else {
             anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
         };
 
-        // Use _NET_ACTIVE_WINDOW client message
-        let event = xproto::ClientMessageEvent::new(
-            32,
-            *window_id,
-            self.atoms.net_active_window,
-            [1, 0, 0, 0, 0], // Source indication: 1 = application
-        );
+        // The window manager may ignore the request (focus stealing prevention,
+        // or the window wasn't mapped yet), so confirm it and retry
+        let mut reported = None;
+        for attempt in 1..=FOCUS_ATTEMPTS {
+            // Retries claim to come from a pager, which window managers trust more
+            let source = if attempt == 1 { 1 } else { 2 };
+            self.send_root_client_message(
+                *window_id,
+                self.atoms.net_active_window,
+                [source, 0, 0, 0, 0],
+            )?;
+
+            for _ in 0..FOCUS_VERIFY_POLLS {
+                tokio::time::sleep(std::time::Duration::from_millis(FOCUS_VERIFY_INTERVAL_MS)).await;
+                reported = self.get_active_window();
+                if reported == Some(*window_id) {
+                    return Ok(FocusOutcome { attempts: attempt, verified: true });
+                }
+            }
 
-        self.conn.send_event(
-            false,
-            self.root,
-            xproto::EventMask::SUBSTRUCTURE_REDIRECT | xproto::EventMask::SUBSTRUCTURE_NOTIFY,
-            event,
-        )?;
+            tracing::debug!("Window {:#x} not active after focus attempt {}", window_id, attempt);
+        }
 
-        self.conn.flush()?;
-        Ok(())
+        // Window managers without _NET_ACTIVE_WINDOW can't be verified either way
+        match reported {
+            None => Ok(FocusOutcome { attempts: FOCUS_ATTEMPTS, verified: false }),
+            Some(active) => anyhow::bail!(
+                "Window manager kept window {:#x} active after {} focus attempts",
+                active,
+                FOCUS_ATTEMPTS
+            ),
+        }
     }
 
     async fn move_window(&self, id: &PlatformWindowId, x: i32, y: i32) -> anyhow::Result<()> {
@@ -240,8 +618,8 @@ impl WindowBackend for X11Backend {
             .x(x)
             .y(y);
 
-        self.conn.configure_window(*window_id, &values)?;
-        self.conn.flush()?;
+        self.conn().configure_window(*window_id, &values)?;
+        self.conn().flush()?;
         Ok(())
     }
 
@@ -255,12 +633,264 @@ impl WindowBackend for X11Backend {
             .width(width)
             .height(height);
 
-        self.conn.configure_window(*window_id, &values)?;
-        self.conn.flush()?;
+        self.conn().configure_window(*window_id, &values)?;
+        self.conn().flush()?;
+        Ok(())
+    }
+
+    async fn refresh_window(&self, id: &PlatformWindowId) -> anyhow::Result<Geometry> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        self.get_window_geometry(*window_id)
+    }
+
+    async fn minimize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        // ICCCM equivalent of XIconifyWindow: ask the WM to iconify via WM_CHANGE_STATE
+        self.send_root_client_message(
+            *window_id,
+            self.atoms.wm_change_state,
+            [ICONIC_STATE, 0, 0, 0, 0],
+        )
+    }
+
+    async fn maximize_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        // Always ADD rather than TOGGLE so repeated calls stay maximized
+        self.set_maximized(*window_id, NET_WM_STATE_ADD)
+    }
+
+    async fn restore_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        // Drop any maximized state, then activate to de-iconify a minimized window
+        self.set_maximized(*window_id, NET_WM_STATE_REMOVE)?;
+        self.send_root_client_message(
+            *window_id,
+            self.atoms.net_active_window,
+            [1, 0, 0, 0, 0], // Source indication: 1 = application
+        )
+    }
+
+    async fn close_window(&self, id: &PlatformWindowId, force: bool) -> anyhow::Result<CloseOutcome> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        let graceful_supported = self.supports_delete_window(*window_id);
+
+        if force {
+            self.conn().kill_client(*window_id)?;
+            self.conn().flush()?;
+            return Ok(CloseOutcome {
+                method: CloseMethod::Force,
+                graceful_supported,
+            });
+        }
+
+        if !graceful_supported {
+            anyhow::bail!("Window does not support WM_DELETE_WINDOW; retry with force to kill its client");
+        }
+
+        // WM_DELETE_WINDOW is sent directly to the client, not via the root window
+        let event = xproto::ClientMessageEvent::new(
+            32,
+            *window_id,
+            self.atoms.wm_protocols,
+            [self.atoms.wm_delete_window, x11rb::CURRENT_TIME, 0, 0, 0],
+        );
+        self.conn().send_event(false, *window_id, xproto::EventMask::NO_EVENT, event)?;
+        self.conn().flush()?;
+
+        Ok(CloseOutcome {
+            method: CloseMethod::Graceful,
+            graceful_supported,
+        })
+    }
+
+    async fn raise_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        // Restacking only; unlike _NET_ACTIVE_WINDOW this leaves keyboard focus alone
+        let values = xproto::ConfigureWindowAux::new().stack_mode(xproto::StackMode::ABOVE);
+        self.conn().configure_window(*window_id, &values)?;
+        self.conn().flush()?;
         Ok(())
     }
+
+    async fn lower_window(&self, id: &PlatformWindowId) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        let values = xproto::ConfigureWindowAux::new().stack_mode(xproto::StackMode::BELOW);
+        self.conn().configure_window(*window_id, &values)?;
+        self.conn().flush()?;
+        Ok(())
+    }
+
+    async fn set_always_on_top(&self, id: &PlatformWindowId, enabled: bool) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        let action = if enabled { NET_WM_STATE_ADD } else { NET_WM_STATE_REMOVE };
+        self.set_wm_state(*window_id, action, self.atoms.net_wm_state_above, 0)
+    }
+
+    async fn set_fullscreen(&self, id: &PlatformWindowId, enabled: bool) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        let action = if enabled { NET_WM_STATE_ADD } else { NET_WM_STATE_REMOVE };
+        self.set_wm_state(*window_id, action, self.atoms.net_wm_state_fullscreen, 0)
+    }
+
+    async fn set_opacity(&self, id: &PlatformWindowId, opacity: f64) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("Window opacity is only supported for X11 windows");
+        };
+
+        // _NET_WM_WINDOW_OPACITY scales 0..=0xFFFFFFFF to fully transparent..opaque
+        let value = (opacity.clamp(0.0, 1.0) * u32::MAX as f64).round() as u32;
+        self.conn().change_property32(
+            xproto::PropMode::REPLACE,
+            *window_id,
+            self.atoms.net_wm_window_opacity,
+            AtomEnum::CARDINAL,
+            &[value],
+        )?;
+        self.conn().flush()?;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> anyhow::Result<String> {
+        // Any request with a reply proves the connection is alive
+        self.conn().get_input_focus()?.reply()?;
+
+        let ewmh = self.get_active_window().is_some() || self.get_cardinal(self.root, self.atoms.net_number_of_desktops).is_some();
+        Ok(format!(
+            "X11 connection is alive ({})",
+            if ewmh { "EWMH window manager detected" } else { "window manager doesn't advertise EWMH hints" }
+        ))
+    }
+
+    async fn cursor_position(&self) -> anyhow::Result<(i32, i32)> {
+        let pointer = self.conn().query_pointer(self.root)?.reply()?;
+        Ok((pointer.root_x as i32, pointer.root_y as i32))
+    }
+
+    async fn window_icon(&self, id: &PlatformWindowId) -> anyhow::Result<Option<image::RgbaImage>> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        let Some(data) = self.get_u32_property(*window_id, self.atoms.net_wm_icon, AtomEnum::CARDINAL.into())? else {
+            return Ok(None);
+        };
+
+        Ok(largest_icon(&data))
+    }
+
+    async fn desktop_info(&self) -> anyhow::Result<DesktopInfo> {
+        let count = self.get_cardinal(self.root, self.atoms.net_number_of_desktops)
+            .ok_or_else(|| anyhow::anyhow!("Window manager does not set _NET_NUMBER_OF_DESKTOPS"))?;
+        let current = self.get_cardinal(self.root, self.atoms.net_current_desktop).unwrap_or(0);
+
+        Ok(DesktopInfo { current, count })
+    }
+
+    async fn switch_desktop(&self, desktop: u32) -> anyhow::Result<()> {
+        self.send_root_client_message(
+            self.root,
+            self.atoms.net_current_desktop,
+            [desktop, x11rb::CURRENT_TIME, 0, 0, 0],
+        )
+    }
+
+    async fn move_window_to_desktop(&self, id: &PlatformWindowId, desktop: u32) -> anyhow::Result<()> {
+        let PlatformWindowId::X11(window_id) = id else {
+            anyhow::bail!("X11 backend cannot handle non-X11 window IDs");
+        };
+
+        self.send_root_client_message(
+            *window_id,
+            self.atoms.net_wm_desktop,
+            [desktop, 1, 0, 0, 0], // Source indication: 1 = application
+        )
+    }
 }
 
-// Safety: RustConnection is Send + Sync
-unsafe impl Send for X11Backend {}
-unsafe impl Sync for X11Backend {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_instance_and_class() {
+        assert_eq!(parse_wm_class(b"navigator\0firefox\0"), ("navigator".to_string(), "firefox".to_string()));
+    }
+
+    #[test]
+    fn single_field_is_used_for_both() {
+        assert_eq!(parse_wm_class(b"xterm\0"), ("xterm".to_string(), "xterm".to_string()));
+        assert_eq!(parse_wm_class(b"xterm"), ("xterm".to_string(), "xterm".to_string()));
+    }
+
+    #[test]
+    fn missing_trailing_nul() {
+        assert_eq!(parse_wm_class(b"code\0Code"), ("code".to_string(), "Code".to_string()));
+    }
+
+    #[test]
+    fn empty_instance_is_kept() {
+        assert_eq!(parse_wm_class(b"\0Firefox\0"), (String::new(), "Firefox".to_string()));
+        assert_eq!(parse_wm_class(b"\0Firefox"), (String::new(), "Firefox".to_string()));
+    }
+
+    #[test]
+    fn empty_property() {
+        assert_eq!(parse_wm_class(b""), (String::new(), String::new()));
+        assert_eq!(parse_wm_class(b"\0\0"), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn frame_geometry_adds_extents() {
+        let client = Geometry { x: 100, y: 50, width: 800, height: 600 };
+        assert_eq!(
+            frame_geometry(&client, [2, 3, 30, 4]),
+            Some(Geometry { x: 98, y: 20, width: 805, height: 634 })
+        );
+    }
+
+    #[test]
+    fn frame_geometry_rejects_overflowing_extents() {
+        let client = Geometry { x: 0, y: 0, width: 800, height: 600 };
+        assert_eq!(frame_geometry(&client, [u32::MAX, 0, 0, 0]), None);
+        assert_eq!(frame_geometry(&client, [0, u32::MAX, 0, 0]), None);
+        assert_eq!(frame_geometry(&client, [0, 0, 0, u32::MAX]), None);
+
+        let client = Geometry { x: i32::MIN, y: 0, width: 800, height: 600 };
+        assert_eq!(frame_geometry(&client, [1, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn decodes_latin1_and_utf8() {
+        // "café" in Latin-1 isn't valid UTF-8
+        assert_eq!(parse_wm_class(b"caf\xe9\0Caf\xe9\0"), ("café".to_string(), "Café".to_string()));
+        assert_eq!(decode_string(b"\xff\xfe"), "ÿþ");
+        assert_eq!(decode_string("café".as_bytes()), "café");
+    }
+}
@@ -0,0 +1,122 @@
+//! Audit log of tool invocations
+//!
+//! When `MARIONETTE_ACTION_LOG` names a file, every tool call is appended to
+//! it as one JSON line with a timestamp, the tool name, its parameters, and
+//! whether it succeeded. This is separate from the stderr tracing output, so
+//! the record of what an agent did survives log level changes.
+
+use rmcp::ErrorData as McpError;
+use rmcp::model::{CallToolResult, JsonObject};
+use serde_json::json;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Append-only JSON lines file recording tool calls
+#[derive(Debug)]
+pub struct ActionLog {
+    path: PathBuf,
+    file: Mutex<File>,
+    /// Replace typed and pasted text with its length
+    omit_text: bool,
+}
+
+impl ActionLog {
+    /// Open the log named by `MARIONETTE_ACTION_LOG`, or None if it is unset
+    ///
+    /// `MARIONETTE_ACTION_LOG_OMIT_TEXT` keeps typed text out of the log.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(path) = std::env::var_os("MARIONETTE_ACTION_LOG").filter(|path| !path.is_empty()) else {
+            return Ok(None);
+        };
+        let omit_text = std::env::var_os("MARIONETTE_ACTION_LOG_OMIT_TEXT").is_some();
+
+        Self::open(PathBuf::from(path), omit_text).map(Some)
+    }
+
+    /// Open `path` for appending, creating it if needed
+    pub fn open(path: PathBuf, omit_text: bool) -> anyhow::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to open action log {}: {}", path.display(), e))?;
+
+        Ok(Self { path, file: Mutex::new(file), omit_text })
+    }
+
+    /// File the log is written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one tool call
+    ///
+    /// Write failures are logged rather than returned, so a full disk can't
+    /// fail the tool call itself.
+    pub fn record(
+        &self,
+        tool: &str,
+        params: Option<&JsonObject>,
+        outcome: &Result<CallToolResult, McpError>,
+        duration: Duration,
+    ) {
+        let mut params = params.map_or(serde_json::Value::Null, |params| json!(params));
+        if self.omit_text {
+            omit_text(&mut params);
+        }
+
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+
+        let mut entry = json!({
+            "timestamp_ms": timestamp_ms,
+            "tool": tool,
+            "params": params,
+            "duration_ms": duration.as_millis() as u64
+        });
+        match outcome {
+            Ok(result) if result.is_error == Some(true) => entry["status"] = json!("error"),
+            Ok(_) => entry["status"] = json!("ok"),
+            Err(error) => {
+                entry["status"] = json!("error");
+                entry["error"] = json!(error.message);
+                if let Some(code) = error.data.as_ref().and_then(|data| data.get("code")) {
+                    entry["error_code"] = code.clone();
+                }
+            }
+        }
+
+        let mut line = entry.to_string();
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            tracing::warn!("Failed to write action log {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Replace every string `text` field with a note of its length, at any depth
+///
+/// Covers window_type, window_paste, clipboard_set, and text steps nested in
+/// window_type_sequence and window_batch.
+fn omit_text(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    serde_json::Value::String(text) if key == "text" => {
+                        *value = json!(format!("<omitted {} chars>", text.chars().count()));
+                    }
+                    _ => omit_text(value),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(omit_text),
+        _ => {}
+    }
+}
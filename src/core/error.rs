@@ -0,0 +1,201 @@
+//! Structured errors returned by MCP tools
+//!
+//! Each variant maps to a stable JSON-RPC error code and a machine-readable
+//! `code` string in the error data, so clients can tell a stale ref apart from
+//! a backend failure without parsing messages.
+
+use rmcp::ErrorData as McpError;
+use rmcp::model::ErrorCode;
+use serde_json::json;
+use std::fmt::Display;
+
+/// JSON-RPC codes in the implementation-defined server error range
+pub const WINDOW_NOT_FOUND: ErrorCode = ErrorCode(-32001);
+pub const BACKEND_FAILURE: ErrorCode = ErrorCode(-32010);
+pub const INPUT_FAILURE: ErrorCode = ErrorCode(-32011);
+pub const SCREENSHOT_FAILURE: ErrorCode = ErrorCode(-32012);
+pub const CLIPBOARD_FAILURE: ErrorCode = ErrorCode(-32013);
+pub const INPUT_UNAVAILABLE: ErrorCode = ErrorCode(-32014);
+pub const RATE_LIMITED: ErrorCode = ErrorCode(-32020);
+pub const TOOL_DISABLED: ErrorCode = ErrorCode(-32021);
+pub const INPUT_STOPPED: ErrorCode = ErrorCode(-32022);
+
+/// Error returned by a Marionette tool
+#[derive(Debug, thiserror::Error)]
+pub enum MarionetteError {
+    /// The ref isn't in the registry (never listed, or the window went away)
+    #[error("Window not found: {window_ref}")]
+    WindowNotFound { window_ref: String },
+
+    /// Parameters are malformed or inconsistent
+    #[error("{message}")]
+    InvalidParams {
+        message: String,
+        suggestion: Option<String>,
+    },
+
+    /// The window backend (X11, KWin, Sway, ...) failed
+    #[error("{message}: {details}")]
+    BackendFailure { message: String, details: String },
+
+    /// The input tool (ydotool, xdotool, wtype) failed
+    #[error("{message}: {details}")]
+    InputFailure { message: String, details: String },
+
+    /// The input tool can't run until the setup is fixed (e.g. ydotoold isn't running)
+    #[error("{message}: {reason}")]
+    InputUnavailable {
+        message: String,
+        reason: String,
+        suggestion: String,
+    },
+
+    /// Capturing or saving a screenshot failed
+    #[error("{message}: {details}")]
+    ScreenshotFailure { message: String, details: String },
+
+    /// Reading or writing the clipboard failed
+    #[error("{message}: {details}")]
+    ClipboardFailure { message: String, details: String },
+
+    /// An input tool was called faster than the configured rate limit
+    #[error("Rate limited: {tool}, retry after {retry_after_ms} ms")]
+    RateLimited { tool: String, retry_after_ms: u64 },
+
+    /// The operator disabled a tool with MARIONETTE_ENABLED_TOOLS or MARIONETTE_DISABLED_TOOLS
+    #[error("Tool disabled: {tool}")]
+    ToolDisabled { tool: String },
+
+    /// The operator pressed the emergency-stop hotkey
+    #[error("Input is stopped by the operator's emergency-stop hotkey")]
+    InputStopped,
+}
+
+impl MarionetteError {
+    pub fn window_not_found(window_ref: impl Into<String>) -> Self {
+        Self::WindowNotFound { window_ref: window_ref.into() }
+    }
+
+    pub fn invalid_params(message: impl Into<String>, suggestion: Option<&str>) -> Self {
+        Self::InvalidParams {
+            message: message.into(),
+            suggestion: suggestion.map(str::to_string),
+        }
+    }
+
+    pub fn backend(message: impl Into<String>, error: impl Display) -> Self {
+        Self::BackendFailure { message: message.into(), details: error.to_string() }
+    }
+
+    /// An input failure, or [`Self::InputUnavailable`] when the cause is a known setup problem
+    pub fn input(message: impl Into<String>, error: anyhow::Error) -> Self {
+        match error.downcast::<crate::input::InputUnavailable>() {
+            Ok(unavailable) => Self::InputUnavailable {
+                message: message.into(),
+                reason: unavailable.reason,
+                suggestion: unavailable.suggestion,
+            },
+            Err(error) => Self::InputFailure { message: message.into(), details: error.to_string() },
+        }
+    }
+
+    pub fn screenshot(message: impl Into<String>, error: impl Display) -> Self {
+        Self::ScreenshotFailure { message: message.into(), details: error.to_string() }
+    }
+
+    pub fn clipboard(message: impl Into<String>, error: impl Display) -> Self {
+        Self::ClipboardFailure { message: message.into(), details: error.to_string() }
+    }
+
+    pub fn rate_limited(tool: impl Into<String>, retry_after: std::time::Duration) -> Self {
+        Self::RateLimited {
+            tool: tool.into(),
+            // Round up so retrying after exactly this long succeeds
+            retry_after_ms: retry_after.as_micros().div_ceil(1000) as u64,
+        }
+    }
+
+    pub fn tool_disabled(tool: impl Into<String>) -> Self {
+        Self::ToolDisabled { tool: tool.into() }
+    }
+
+    /// Stable machine-readable code, reported as `data.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::WindowNotFound { .. } => "window_not_found",
+            Self::InvalidParams { .. } => "invalid_params",
+            Self::BackendFailure { .. } => "backend_failure",
+            Self::InputFailure { .. } => "input_failure",
+            Self::InputUnavailable { .. } => "input_unavailable",
+            Self::ScreenshotFailure { .. } => "screenshot_failure",
+            Self::ClipboardFailure { .. } => "clipboard_failure",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::ToolDisabled { .. } => "tool_disabled",
+            Self::InputStopped => "input_stopped",
+        }
+    }
+
+    /// JSON-RPC error code for this variant
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::WindowNotFound { .. } => WINDOW_NOT_FOUND,
+            Self::InvalidParams { .. } => ErrorCode::INVALID_PARAMS,
+            Self::BackendFailure { .. } => BACKEND_FAILURE,
+            Self::InputFailure { .. } => INPUT_FAILURE,
+            Self::InputUnavailable { .. } => INPUT_UNAVAILABLE,
+            Self::ScreenshotFailure { .. } => SCREENSHOT_FAILURE,
+            Self::ClipboardFailure { .. } => CLIPBOARD_FAILURE,
+            Self::RateLimited { .. } => RATE_LIMITED,
+            Self::ToolDisabled { .. } => TOOL_DISABLED,
+            Self::InputStopped => INPUT_STOPPED,
+        }
+    }
+}
+
+impl From<MarionetteError> for McpError {
+    fn from(error: MarionetteError) -> Self {
+        let mut data = json!({ "code": error.code() });
+
+        // Keep the message to one sentence; underlying causes go in data.details
+        let message = match &error {
+            MarionetteError::WindowNotFound { window_ref } => {
+                data["ref"] = json!(window_ref);
+                data["suggestion"] = json!("Run window_list to get current window references");
+                error.to_string()
+            }
+            MarionetteError::InvalidParams { message, suggestion } => {
+                if let Some(suggestion) = suggestion {
+                    data["suggestion"] = json!(suggestion);
+                }
+                message.clone()
+            }
+            MarionetteError::BackendFailure { message, details }
+            | MarionetteError::InputFailure { message, details }
+            | MarionetteError::ScreenshotFailure { message, details }
+            | MarionetteError::ClipboardFailure { message, details } => {
+                data["details"] = json!(details);
+                message.clone()
+            }
+            MarionetteError::InputUnavailable { message, reason, suggestion } => {
+                data["details"] = json!(reason);
+                data["suggestion"] = json!(suggestion);
+                message.clone()
+            }
+            MarionetteError::RateLimited { tool, retry_after_ms } => {
+                data["tool"] = json!(tool);
+                data["retry_after_ms"] = json!(retry_after_ms);
+                error.to_string()
+            }
+            MarionetteError::ToolDisabled { tool } => {
+                data["tool"] = json!(tool);
+                error.to_string()
+            }
+            MarionetteError::InputStopped => {
+                data["suggestion"] = json!("Wait for the operator to press the stop hotkey again to resume input");
+                error.to_string()
+            }
+        };
+
+        McpError::new(error.error_code(), message, Some(data))
+    }
+}
@@ -0,0 +1,267 @@
+//! Rectangle math for placing windows on monitors
+//!
+//! All rectangles are in the global screen coordinate space shared by window
+//! geometry and monitor origins.
+
+use rmcp::schemars;
+
+use crate::core::registry::Geometry;
+
+/// Clamp a window position so the window stays inside `bounds`
+///
+/// On an axis where the window is larger than the bounds, it is pinned to the
+/// bounds' leading edge rather than pushed past it.
+pub fn clamp_position(x: i32, y: i32, width: u32, height: u32, bounds: &Geometry) -> (i32, i32) {
+    (
+        clamp_axis(x, width, bounds.x, bounds.width),
+        clamp_axis(y, height, bounds.y, bounds.height),
+    )
+}
+
+fn clamp_axis(position: i32, length: u32, start: i32, bounds_length: u32) -> i32 {
+    let max = start + (bounds_length as i64 - length as i64).max(0) as i32;
+    position.clamp(start, max)
+}
+
+/// Whether a point lies inside a rectangle (right and bottom edges excluded)
+pub fn contains(rect: &Geometry, x: i32, y: i32) -> bool {
+    let (x, y) = (x as i64, y as i64);
+    x >= rect.x as i64
+        && y >= rect.y as i64
+        && x < rect.x as i64 + rect.width as i64
+        && y < rect.y as i64 + rect.height as i64
+}
+
+/// Scale a point in logical screen coordinates to device pixels, rounding to the nearest pixel
+pub fn to_device_pixels(x: i32, y: i32, scale: f64) -> (i32, i32) {
+    ((x as f64 * scale).round() as i32, (y as f64 * scale).round() as i32)
+}
+
+/// Area of the intersection of two rectangles, or 0 if they don't overlap
+pub fn overlap_area(a: &Geometry, b: &Geometry) -> u64 {
+    intersection(a, b).map_or(0, |overlap| overlap.width as u64 * overlap.height as u64)
+}
+
+/// The rectangle two rectangles share, or None if they don't overlap
+pub fn intersection(a: &Geometry, b: &Geometry) -> Option<Geometry> {
+    let left = (a.x as i64).max(b.x as i64);
+    let top = (a.y as i64).max(b.y as i64);
+    let right = (a.x as i64 + a.width as i64).min(b.x as i64 + b.width as i64);
+    let bottom = (a.y as i64 + a.height as i64).min(b.y as i64 + b.height as i64);
+
+    if right <= left || bottom <= top {
+        return None;
+    }
+    // The overlap lies within both rectangles, so it fits their types
+    Some(Geometry {
+        x: left as i32,
+        y: top as i32,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    })
+}
+
+/// Smallest rectangle containing both rectangles
+pub fn union(a: &Geometry, b: &Geometry) -> Geometry {
+    let left = (a.x as i64).min(b.x as i64);
+    let top = (a.y as i64).min(b.y as i64);
+    let right = (a.x as i64 + a.width as i64).max(b.x as i64 + b.width as i64);
+    let bottom = (a.y as i64 + a.height as i64).max(b.y as i64 + b.height as i64);
+    Geometry {
+        x: left as i32,
+        y: top as i32,
+        width: (right - left).min(u32::MAX as i64) as u32,
+        height: (bottom - top).min(u32::MAX as i64) as u32,
+    }
+}
+
+/// Index of the monitor the window overlaps most, if it overlaps any
+///
+/// Ties go to the earlier monitor.
+pub fn most_overlapping(window: &Geometry, monitors: &[Geometry]) -> Option<usize> {
+    monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| (index, overlap_area(window, monitor)))
+        .filter(|&(_, area)| area > 0)
+        .fold(None, |best: Option<(usize, u64)>, (index, area)| match best {
+            Some((_, best_area)) if best_area >= area => best,
+            _ => Some((index, area)),
+        })
+        .map(|(index, _)| index)
+}
+
+/// Position that centers a window of the given size within `bounds`
+///
+/// On an axis where the window is larger than the bounds, it is pinned to the
+/// bounds' leading edge rather than given a position before it.
+pub fn centered_position(width: u32, height: u32, bounds: &Geometry) -> (i32, i32) {
+    let center = |start: i32, bounds_length: u32, length: u32| {
+        start + ((bounds_length as i64 - length as i64).max(0) / 2) as i32
+    };
+    (
+        center(bounds.x, bounds.width, width),
+        center(bounds.y, bounds.height, height),
+    )
+}
+
+/// Half- and quarter-screen tiling positions for window_snap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SnapPosition {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Full,
+}
+
+/// Target rectangle for snapping a window to `position` within `bounds`
+///
+/// Odd dimensions give the extra pixel to the right/bottom half, so the two
+/// halves always cover the bounds exactly.
+pub fn snap_geometry(position: SnapPosition, bounds: &Geometry) -> Geometry {
+    let half_width = bounds.width / 2;
+    let half_height = bounds.height / 2;
+    let left = (bounds.x, half_width);
+    let right = (bounds.x + half_width as i32, bounds.width - half_width);
+    let top = (bounds.y, half_height);
+    let bottom = (bounds.y + half_height as i32, bounds.height - half_height);
+    let full_x = (bounds.x, bounds.width);
+    let full_y = (bounds.y, bounds.height);
+
+    let ((x, width), (y, height)) = match position {
+        SnapPosition::Left => (left, full_y),
+        SnapPosition::Right => (right, full_y),
+        SnapPosition::Top => (full_x, top),
+        SnapPosition::Bottom => (full_x, bottom),
+        SnapPosition::TopLeft => (left, top),
+        SnapPosition::TopRight => (right, top),
+        SnapPosition::BottomLeft => (left, bottom),
+        SnapPosition::BottomRight => (right, bottom),
+        SnapPosition::Full => (full_x, full_y),
+    };
+
+    Geometry { x, y, width, height }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Geometry {
+        Geometry { x, y, width, height }
+    }
+
+    #[test]
+    fn most_overlapping_picks_monitor_with_largest_overlap() {
+        let monitors = [rect(0, 0, 1920, 1080), rect(1920, 0, 2560, 1440)];
+        // 100px on the first monitor, 700px on the second
+        assert_eq!(most_overlapping(&rect(1820, 100, 800, 600), &monitors), Some(1));
+        // 700px on the first monitor, 100px on the second
+        assert_eq!(most_overlapping(&rect(1220, 100, 800, 600), &monitors), Some(0));
+        // An even split goes to the earlier monitor
+        assert_eq!(most_overlapping(&rect(1520, 100, 800, 600), &monitors), Some(0));
+    }
+
+    #[test]
+    fn most_overlapping_without_overlap() {
+        let monitors = [rect(0, 0, 1920, 1080), rect(1920, 0, 2560, 1440)];
+        assert_eq!(most_overlapping(&rect(-900, -700, 800, 600), &monitors), None);
+        // Touching an edge isn't overlapping
+        assert_eq!(most_overlapping(&rect(0, 1440, 800, 600), &monitors), None);
+        assert_eq!(most_overlapping(&rect(0, 0, 800, 600), &[]), None);
+    }
+
+    #[test]
+    fn intersection_and_union() {
+        let a = rect(0, 0, 1920, 1080);
+        let b = rect(1920, 0, 2560, 1440);
+        let region = rect(0, 0, 100_000, 100_000);
+        assert_eq!(intersection(&region, &a), Some(a));
+        assert_eq!(intersection(&region, &b), Some(b));
+        assert_eq!(intersection(&a, &b), None);
+        assert_eq!(intersection(&rect(1800, 1000, 300, 300), &b), Some(rect(1920, 1000, 180, 300)));
+        assert_eq!(union(&a, &b), rect(0, 0, 4480, 1440));
+    }
+
+    #[test]
+    fn clamp_position_keeps_window_inside_bounds() {
+        let monitor = rect(1920, 0, 2560, 1440);
+        assert_eq!(clamp_position(2000, 100, 800, 600, &monitor), (2000, 100));
+        assert_eq!(clamp_position(0, -50, 800, 600, &monitor), (1920, 0));
+        assert_eq!(clamp_position(4400, 1000, 800, 600, &monitor), (3680, 840));
+    }
+
+    #[test]
+    fn oversized_window_pins_to_top_left() {
+        let monitor = rect(1920, 0, 2560, 1440);
+        assert_eq!(clamp_position(2400, 300, 3000, 2000, &monitor), (1920, 0));
+        assert_eq!(centered_position(3000, 2000, &monitor), (1920, 0));
+        // Only the oversized axis is pinned
+        assert_eq!(clamp_position(4000, 300, 3000, 600, &monitor), (1920, 300));
+        assert_eq!(centered_position(3000, 600, &monitor), (1920, 420));
+    }
+
+    #[test]
+    fn centered_position_centers_within_bounds() {
+        assert_eq!(centered_position(800, 600, &rect(1920, 0, 2560, 1440)), (2800, 420));
+        // Odd leftover space rounds toward the top-left
+        assert_eq!(centered_position(800, 600, &rect(-1281, 0, 1281, 1025)), (-1041, 212));
+    }
+
+    #[test]
+    fn snap_geometry_on_offset_odd_sized_monitor() {
+        // Secondary monitor left of and below the primary, with odd dimensions
+        let monitor = rect(-1281, 200, 1281, 1025);
+        let cases = [
+            (SnapPosition::Left, rect(-1281, 200, 640, 1025)),
+            (SnapPosition::Right, rect(-641, 200, 641, 1025)),
+            (SnapPosition::Top, rect(-1281, 200, 1281, 512)),
+            (SnapPosition::Bottom, rect(-1281, 712, 1281, 513)),
+            (SnapPosition::TopLeft, rect(-1281, 200, 640, 512)),
+            (SnapPosition::TopRight, rect(-641, 200, 641, 512)),
+            (SnapPosition::BottomLeft, rect(-1281, 712, 640, 513)),
+            (SnapPosition::BottomRight, rect(-641, 712, 641, 513)),
+            (SnapPosition::Full, monitor),
+        ];
+        for (position, expected) in cases {
+            assert_eq!(snap_geometry(position, &monitor), expected, "{:?}", position);
+        }
+    }
+
+    #[test]
+    fn snap_halves_and_quarters_tile_the_monitor() {
+        let monitor = rect(-1281, 200, 1281, 1025);
+        let area = |r: Geometry| r.width as u64 * r.height as u64;
+        let total = area(monitor);
+
+        let halves = [
+            [SnapPosition::Left, SnapPosition::Right],
+            [SnapPosition::Top, SnapPosition::Bottom],
+        ];
+        for [a, b] in halves {
+            let (a, b) = (snap_geometry(a, &monitor), snap_geometry(b, &monitor));
+            assert_eq!(overlap_area(&a, &b), 0);
+            assert_eq!(area(a) + area(b), total);
+        }
+
+        let quarters = [
+            SnapPosition::TopLeft,
+            SnapPosition::TopRight,
+            SnapPosition::BottomLeft,
+            SnapPosition::BottomRight,
+        ]
+        .map(|position| snap_geometry(position, &monitor));
+        assert_eq!(quarters.iter().map(|&q| area(q)).sum::<u64>(), total);
+        for (i, a) in quarters.iter().enumerate() {
+            for b in &quarters[i + 1..] {
+                assert_eq!(overlap_area(a, b), 0);
+            }
+        }
+    }
+}
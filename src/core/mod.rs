@@ -1,3 +1,7 @@
 //! Core data structures and utilities for Marionette
 
+pub mod action_log;
+pub mod error;
+pub mod geometry;
+pub mod rate_limit;
 pub mod registry;
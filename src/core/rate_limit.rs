@@ -0,0 +1,52 @@
+//! Per-tool rate limiting for input tools
+//!
+//! Each tool gets its own token bucket that refills continuously at the
+//! configured rate and holds up to one second's worth of actions, so short
+//! bursts are allowed but a runaway loop is throttled.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Token bucket for a single tool
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token buckets keyed by tool name
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Actions per second each tool may perform; 0 disables limiting
+    per_second: f64,
+    buckets: HashMap<&'static str, Bucket>,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `per_second` actions per tool (0 disables limiting)
+    pub fn new(per_second: f64) -> Self {
+        Self { per_second, buckets: HashMap::new() }
+    }
+
+    /// Take a token for `tool`, or return how long to wait until one is available
+    pub fn check(&mut self, tool: &'static str) -> Result<(), Duration> {
+        if self.per_second <= 0.0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let capacity = self.per_second.max(1.0);
+        let bucket = self.buckets.entry(tool).or_insert(Bucket { tokens: capacity, last_refill: now });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second))
+        }
+    }
+}
@@ -2,13 +2,18 @@
 //!
 //! Following Playwright MCP's pattern, we assign stable references (w0, w1, ...)
 //! to windows that persist across window_list calls as long as the window exists.
+//!
+//! A registry created with [`WindowRegistry::load_or_new`] also saves its ref
+//! assignments to disk, so refs survive server restarts.
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use crate::backend::WindowInfo;
 
 /// Unique identifier for a window across platforms
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PlatformWindowId {
     /// X11 window ID
     X11(u32),
@@ -17,7 +22,7 @@ pub enum PlatformWindowId {
 }
 
 /// Geometry of a window
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Geometry {
     pub x: i32,
     pub y: i32,
@@ -26,7 +31,7 @@ pub struct Geometry {
 }
 
 /// A window handle with stable reference
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct WindowHandle {
     /// Stable reference ID (e.g., "w0", "w1")
     pub ref_id: String,
@@ -36,12 +41,32 @@ pub struct WindowHandle {
     pub title: String,
     /// Window class/app name
     pub class: String,
-    /// Window geometry
+    /// Instance name (first `WM_CLASS` field), empty when unknown
+    pub instance: String,
+    /// Client area geometry, excluding decorations
     pub geometry: Geometry,
+    /// Outer geometry including decorations, if known
+    pub frame: Option<Geometry>,
     /// Whether the window is currently focused
     pub focused: bool,
     /// Whether the window is visible
     pub visible: bool,
+    /// Position in the stacking order, bottom-to-top
+    pub stacking_index: usize,
+    /// Owning process ID, if known
+    pub pid: Option<u32>,
+    /// Virtual desktop index, or -1 when shown on all desktops
+    pub desktop: Option<i64>,
+    /// EWMH window type ("normal", "dialog", "dock", ...), if known
+    pub window_type: Option<String>,
+    /// Whether the window is minimized
+    pub minimized: bool,
+    /// Whether the window is maximized horizontally
+    pub maximized_horz: bool,
+    /// Whether the window is maximized vertically
+    pub maximized_vert: bool,
+    /// Whether the window is fullscreen
+    pub fullscreen: bool,
 }
 
 /// Registry that maintains stable window references
@@ -52,8 +77,23 @@ pub struct WindowRegistry {
     platform_to_ref: HashMap<PlatformWindowId, String>,
     /// Next reference number to assign
     next_ref: u32,
-    /// Snapshot version (incremented on each update)
+    /// Snapshot version (incremented on each update that changes a window)
     version: u64,
+    /// File the ref assignments are saved to after each update, if persistent
+    persist_path: Option<PathBuf>,
+    /// Log each window change found by `update_windows`
+    log_changes: bool,
+    /// Human-readable names for windows, accepted wherever a ref is
+    aliases: HashMap<String, PlatformWindowId>,
+}
+
+/// Ref assignments as saved to disk
+///
+/// JSON object keys must be strings, so the map is stored as a list of pairs.
+#[derive(Serialize, Deserialize)]
+struct PersistedRefs {
+    next_ref: u32,
+    refs: Vec<(PlatformWindowId, String)>,
 }
 
 impl WindowRegistry {
@@ -64,7 +104,75 @@ impl WindowRegistry {
             platform_to_ref: HashMap::new(),
             next_ref: 0,
             version: 0,
+            persist_path: None,
+            log_changes: false,
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Log which windows appeared, closed, moved, resized, or changed title or
+    /// focus on each `update_windows`, as a timeline of what the desktop did
+    pub fn set_log_changes(&mut self, enabled: bool) {
+        self.log_changes = enabled;
+    }
+
+    /// Create a registry that saves its refs to `path`, restoring any saved there
+    ///
+    /// Restored refs only come back for windows that still exist at the next
+    /// `update_windows`; the rest are dropped. An unreadable file is logged and
+    /// ignored.
+    pub fn load_or_new(path: PathBuf) -> Self {
+        let mut registry = Self::new();
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<PersistedRefs>(&contents) {
+                Ok(persisted) => {
+                    tracing::info!("Restored {} window refs from {}", persisted.refs.len(), path.display());
+                    registry.next_ref = persisted.next_ref;
+                    registry.platform_to_ref = persisted.refs.into_iter().collect();
+                }
+                Err(e) => tracing::warn!("Ignoring corrupt registry file {}: {}", path.display(), e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to read registry file {}: {}", path.display(), e),
         }
+
+        registry.persist_path = Some(path);
+        registry
+    }
+
+    /// Default location of the persisted registry: `$XDG_STATE_HOME/marionette/registry.json`
+    ///
+    /// Falls back to `~/.local/state` when `XDG_STATE_HOME` is unset.
+    pub fn default_state_path() -> Option<PathBuf> {
+        let state_home = std::env::var_os("XDG_STATE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+
+        Some(state_home.join("marionette").join("registry.json"))
+    }
+
+    /// Write the current ref assignments to the persist path
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let persisted = PersistedRefs {
+            next_ref: self.next_ref,
+            refs: self
+                .platform_to_ref
+                .iter()
+                .map(|(platform_id, ref_id)| (platform_id.clone(), ref_id.clone()))
+                .collect(),
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        // Write then rename so a crash mid-write can't leave a truncated file
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&persisted)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
     }
 
     /// Get the current snapshot version
@@ -77,8 +185,11 @@ impl WindowRegistry {
     /// Windows that still exist keep their references.
     /// New windows get new references.
     /// Windows that no longer exist are removed.
+    ///
+    /// The snapshot version only advances when something actually changed, so
+    /// callers can compare versions to detect changes.
     pub fn update_windows(&mut self, windows: Vec<WindowInfo>) {
-        self.version += 1;
+        let mut changed = false;
 
         // Track which refs are still valid
         let mut seen_refs: Vec<String> = Vec::new();
@@ -86,36 +197,45 @@ impl WindowRegistry {
         for info in windows {
             let platform_id = info.platform_id.clone();
 
-            // Check if we already have a ref for this window
-            if let Some(ref_id) = self.platform_to_ref.get(&platform_id) {
-                // Update existing window
-                seen_refs.push(ref_id.clone());
-                if let Some(handle) = self.windows.get_mut(ref_id) {
-                    handle.title = info.title;
-                    handle.class = info.class;
-                    handle.geometry = info.geometry;
-                    handle.focused = info.focused;
-                    handle.visible = info.visible;
+            // Existing and restored windows keep their ref; new windows get the next one
+            let ref_id = match self.platform_to_ref.get(&platform_id) {
+                Some(ref_id) => ref_id.clone(),
+                None => {
+                    let ref_id = format!("w{}", self.next_ref);
+                    self.next_ref += 1;
+                    self.platform_to_ref.insert(platform_id.clone(), ref_id.clone());
+                    ref_id
+                }
+            };
+
+            let handle = WindowHandle {
+                ref_id: ref_id.clone(),
+                platform_id,
+                title: info.title,
+                class: info.class,
+                instance: info.instance,
+                geometry: info.geometry,
+                frame: info.frame,
+                focused: info.focused,
+                visible: info.visible,
+                stacking_index: info.stacking_index,
+                pid: info.pid,
+                desktop: info.desktop,
+                window_type: info.window_type,
+                minimized: info.minimized,
+                maximized_horz: info.maximized_horz,
+                maximized_vert: info.maximized_vert,
+                fullscreen: info.fullscreen,
+            };
+
+            if self.windows.get(&ref_id) != Some(&handle) {
+                changed = true;
+                if self.log_changes {
+                    log_window_change(self.windows.get(&ref_id), &handle);
                 }
-            } else {
-                // New window - assign a new ref
-                let ref_id = format!("w{}", self.next_ref);
-                self.next_ref += 1;
-
-                let handle = WindowHandle {
-                    ref_id: ref_id.clone(),
-                    platform_id: platform_id.clone(),
-                    title: info.title,
-                    class: info.class,
-                    geometry: info.geometry,
-                    focused: info.focused,
-                    visible: info.visible,
-                };
-
-                seen_refs.push(ref_id.clone());
-                self.platform_to_ref.insert(platform_id, ref_id.clone());
-                self.windows.insert(ref_id, handle);
+                self.windows.insert(ref_id.clone(), handle);
             }
+            seen_refs.push(ref_id);
         }
 
         // Remove windows that no longer exist
@@ -128,14 +248,113 @@ impl WindowRegistry {
 
         for ref_id in stale_refs {
             if let Some(handle) = self.windows.remove(&ref_id) {
+                changed = true;
+                if self.log_changes {
+                    tracing::info!(window = %ref_id, title = %handle.title, "Window closed");
+                }
+                self.aliases.retain(|_, platform_id| *platform_id != handle.platform_id);
                 self.platform_to_ref.remove(&handle.platform_id);
             }
         }
+
+        // Drop restored refs whose windows didn't come back
+        self.platform_to_ref.retain(|_, ref_id| seen_refs.contains(ref_id));
+
+        if changed {
+            self.version += 1;
+        }
+
+        if let Some(path) = &self.persist_path
+            && let Err(e) = self.save(path)
+        {
+            tracing::warn!("Failed to save registry to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Record new geometry for a window after it was moved or resized
+    ///
+    /// Returns false if the ref isn't in the registry.
+    pub fn update_geometry(&mut self, ref_id: &str, geometry: Geometry) -> bool {
+        let Some(handle) = self.windows.get_mut(ref_id) else {
+            return false;
+        };
+
+        if handle.geometry != geometry {
+            // Decorations keep their size, so the frame follows the client area
+            if let Some(frame) = &mut handle.frame {
+                frame.x += geometry.x - handle.geometry.x;
+                frame.y += geometry.y - handle.geometry.y;
+                frame.width = (frame.width as i64 + geometry.width as i64 - handle.geometry.width as i64).max(0) as u32;
+                frame.height = (frame.height as i64 + geometry.height as i64 - handle.geometry.height as i64).max(0) as u32;
+            }
+            handle.geometry = geometry;
+            self.version += 1;
+        }
+        true
     }
 
-    /// Get a window by its reference ID
+    /// Get a window by its reference ID or alias
     pub fn get_window(&self, ref_id: &str) -> Option<&WindowHandle> {
-        self.windows.get(ref_id)
+        self.windows.get(self.resolve_ref(ref_id)?)
+    }
+
+    /// Resolve a ref or alias to the window's ref ID
+    pub fn resolve_ref<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        if self.windows.contains_key(name) {
+            return Some(name);
+        }
+        let platform_id = self.aliases.get(name)?;
+        self.platform_to_ref.get(platform_id).map(String::as_str)
+    }
+
+    /// Give a window an alias that tools accept in place of its ref
+    ///
+    /// An alias already naming another window moves to this one. Aliases are
+    /// dropped when their window closes.
+    pub fn set_alias(&mut self, ref_id: &str, alias: &str) -> Result<(), String> {
+        if alias.is_empty() {
+            return Err("Alias must not be empty".to_string());
+        }
+        if is_ref_like(alias) {
+            return Err(format!("Alias '{}' looks like a window ref", alias));
+        }
+        let handle = self.get_window(ref_id).ok_or_else(|| format!("Window '{}' not found", ref_id))?;
+
+        self.aliases.insert(alias.to_string(), handle.platform_id.clone());
+        Ok(())
+    }
+
+    /// Remove every alias of a window, returning the removed names
+    pub fn clear_aliases(&mut self, ref_id: &str) -> Vec<String> {
+        let Some(platform_id) = self.get_window(ref_id).map(|w| w.platform_id.clone()) else {
+            return Vec::new();
+        };
+
+        let mut removed: Vec<String> = self
+            .aliases
+            .iter()
+            .filter(|(_, id)| **id == platform_id)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        self.aliases.retain(|_, id| *id != platform_id);
+        removed.sort();
+        removed
+    }
+
+    /// Aliases naming a window, sorted
+    pub fn aliases_of(&self, ref_id: &str) -> Vec<String> {
+        let Some(platform_id) = self.get_window(ref_id).map(|w| &w.platform_id) else {
+            return Vec::new();
+        };
+
+        let mut aliases: Vec<String> = self
+            .aliases
+            .iter()
+            .filter(|(_, id)| *id == platform_id)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        aliases.sort();
+        aliases
     }
 
     /// Get all windows
@@ -149,6 +368,88 @@ impl WindowRegistry {
         });
         windows
     }
+
+    /// Get all windows sorted bottom-to-top by stacking order
+    pub fn windows_by_stacking(&self) -> Vec<&WindowHandle> {
+        let mut windows = self.windows();
+        // Stable sort keeps ref order for ties
+        windows.sort_by_key(|w| w.stacking_index);
+        windows
+    }
+
+    /// The topmost visible, unminimized window at a screen point
+    ///
+    /// Hit-tests the decorated frame where known, so a point on a title bar
+    /// belongs to its window. Minimized windows can keep their last geometry,
+    /// so they are skipped even when reported visible.
+    pub fn window_at(&self, x: i32, y: i32) -> Option<&WindowHandle> {
+        self.windows_by_stacking().into_iter().rfind(|w| {
+            w.visible && !w.minimized && super::geometry::contains(&w.frame.unwrap_or(w.geometry), x, y)
+        })
+    }
+
+    /// Find windows whose title contains the substring (case-insensitive), sorted by ref
+    pub fn find_by_title(&self, substring: &str) -> Vec<&WindowHandle> {
+        let needle = substring.to_lowercase();
+        self.windows()
+            .into_iter()
+            .filter(|w| w.title.to_lowercase().contains(&needle))
+            .collect()
+    }
+
+    /// Find windows whose class contains the substring (case-insensitive), sorted by ref
+    pub fn find_by_class(&self, substring: &str) -> Vec<&WindowHandle> {
+        let needle = substring.to_lowercase();
+        self.windows()
+            .into_iter()
+            .filter(|w| w.class.to_lowercase().contains(&needle) || w.instance.to_lowercase().contains(&needle))
+            .collect()
+    }
+}
+
+/// Whether a name has the form of a generated ref ("w" followed by digits)
+fn is_ref_like(name: &str) -> bool {
+    name.strip_prefix('w')
+        .is_some_and(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Emit one log record per field that differs between two snapshots of a window
+fn log_window_change(old: Option<&WindowHandle>, new: &WindowHandle) {
+    let window = &new.ref_id;
+    let Some(old) = old else {
+        tracing::info!(window = %window, title = %new.title, class = %new.class, "Window opened");
+        return;
+    };
+
+    if (old.geometry.x, old.geometry.y) != (new.geometry.x, new.geometry.y) {
+        tracing::info!(
+            window = %window,
+            from_x = old.geometry.x,
+            from_y = old.geometry.y,
+            x = new.geometry.x,
+            y = new.geometry.y,
+            "Window moved"
+        );
+    }
+    if (old.geometry.width, old.geometry.height) != (new.geometry.width, new.geometry.height) {
+        tracing::info!(
+            window = %window,
+            from_width = old.geometry.width,
+            from_height = old.geometry.height,
+            width = new.geometry.width,
+            height = new.geometry.height,
+            "Window resized"
+        );
+    }
+    if old.title != new.title {
+        tracing::info!(window = %window, from = %old.title, to = %new.title, "Window title changed");
+    }
+    if old.focused != new.focused {
+        tracing::info!(window = %window, focused = new.focused, "Window focus changed");
+    }
+    if old.visible != new.visible {
+        tracing::info!(window = %window, visible = new.visible, "Window visibility changed");
+    }
 }
 
 impl Default for WindowRegistry {
@@ -156,3 +457,91 @@ impl Default for WindowRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: u32, stacking_index: usize, geometry: Geometry) -> WindowInfo {
+        WindowInfo {
+            platform_id: PlatformWindowId::X11(id),
+            title: format!("Window {}", id),
+            class: "test".to_string(),
+            instance: String::new(),
+            geometry,
+            frame: None,
+            focused: false,
+            visible: true,
+            stacking_index,
+            pid: None,
+            desktop: None,
+            window_type: None,
+            minimized: false,
+            maximized_horz: false,
+            maximized_vert: false,
+            fullscreen: false,
+        }
+    }
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Geometry {
+        Geometry { x, y, width, height }
+    }
+
+    fn registry(windows: Vec<WindowInfo>) -> WindowRegistry {
+        let mut registry = WindowRegistry::new();
+        registry.update_windows(windows);
+        registry
+    }
+
+    fn id_at(registry: &WindowRegistry, x: i32, y: i32) -> Option<PlatformWindowId> {
+        registry.window_at(x, y).map(|w| w.platform_id.clone())
+    }
+
+    #[test]
+    fn window_at_picks_topmost_overlapping_window() {
+        // Listed top-first, to check the stacking index decides rather than list order
+        let registry = registry(vec![
+            window(2, 1, rect(300, 200, 800, 600)),
+            window(1, 0, rect(0, 0, 800, 600)),
+        ]);
+        assert_eq!(id_at(&registry, 400, 300), Some(PlatformWindowId::X11(2)));
+        assert_eq!(id_at(&registry, 100, 100), Some(PlatformWindowId::X11(1)));
+    }
+
+    #[test]
+    fn window_at_skips_invisible_and_minimized_windows() {
+        let mut hidden = window(2, 1, rect(0, 0, 800, 600));
+        hidden.visible = false;
+        let mut minimized = window(3, 2, rect(0, 0, 800, 600));
+        minimized.minimized = true;
+        let registry = registry(vec![window(1, 0, rect(0, 0, 800, 600)), hidden, minimized]);
+        assert_eq!(id_at(&registry, 400, 300), Some(PlatformWindowId::X11(1)));
+    }
+
+    #[test]
+    fn window_at_edges() {
+        let registry = registry(vec![window(1, 0, rect(100, 100, 800, 600))]);
+        assert_eq!(id_at(&registry, 100, 100), Some(PlatformWindowId::X11(1)));
+        assert_eq!(id_at(&registry, 899, 699), Some(PlatformWindowId::X11(1)));
+        // Right and bottom edges are outside
+        assert_eq!(id_at(&registry, 900, 300), None);
+        assert_eq!(id_at(&registry, 300, 700), None);
+        assert_eq!(id_at(&registry, 99, 300), None);
+    }
+
+    #[test]
+    fn window_at_uses_frame_for_decorations() {
+        let mut decorated = window(1, 0, rect(100, 130, 800, 600));
+        decorated.frame = Some(rect(100, 100, 800, 630));
+        let registry = registry(vec![decorated]);
+        // On the title bar, outside the client area
+        assert_eq!(id_at(&registry, 400, 110), Some(PlatformWindowId::X11(1)));
+    }
+
+    #[test]
+    fn window_at_misses() {
+        let registry = registry(vec![window(1, 0, rect(0, 0, 800, 600)), window(2, 1, rect(1000, 0, 800, 600))]);
+        assert_eq!(id_at(&registry, 900, 300), None);
+        assert_eq!(id_at(&WindowRegistry::new(), 0, 0), None);
+    }
+}
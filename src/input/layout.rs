@@ -0,0 +1,77 @@
+//! Keyboard layout detection
+//!
+//! ydotool sends kernel keycodes chosen for a US QWERTY layout, so on AZERTY,
+//! Dvorak, and other layouts the characters that arrive differ from the ones
+//! requested. The layout is detected once at startup so window_type can warn
+//! about it. xdotool and wtype type by keysym and are unaffected.
+
+use std::fmt;
+use tokio::process::Command as AsyncCommand;
+
+/// Active XKB layout and variant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardLayout {
+    /// Layout name, e.g. "us" or "de", or a comma-separated list like "us,de"
+    pub layout: String,
+    /// Layout variant, e.g. "dvorak", if any
+    pub variant: Option<String>,
+}
+
+impl KeyboardLayout {
+    /// Whether the first (active by default) layout types like plain US QWERTY
+    pub fn is_us_qwerty(&self) -> bool {
+        let first_layout = self.layout.split(',').next().unwrap_or_default().trim();
+        let first_variant = self
+            .variant
+            .as_deref()
+            .and_then(|variant| variant.split(',').next())
+            .unwrap_or_default()
+            .trim();
+
+        first_layout == "us" && first_variant.is_empty()
+    }
+}
+
+impl fmt::Display for KeyboardLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.variant {
+            Some(variant) => write!(f, "{} ({})", self.layout, variant),
+            None => write!(f, "{}", self.layout),
+        }
+    }
+}
+
+/// Detect the active keyboard layout, or None if it can't be determined
+///
+/// Tries `setxkbmap -query` (X11 and XWayland), then `XKB_DEFAULT_LAYOUT` and
+/// `XKB_DEFAULT_VARIANT`, which wlroots compositors read at startup.
+pub async fn detect_layout() -> Option<KeyboardLayout> {
+    if let Some(layout) = query_setxkbmap().await {
+        return Some(layout);
+    }
+
+    let layout = std::env::var("XKB_DEFAULT_LAYOUT").ok().filter(|layout| !layout.is_empty())?;
+    let variant = std::env::var("XKB_DEFAULT_VARIANT").ok().filter(|variant| !variant.is_empty());
+    Some(KeyboardLayout { layout, variant })
+}
+
+/// Parse the `layout:` and `variant:` lines of `setxkbmap -query`
+async fn query_setxkbmap() -> Option<KeyboardLayout> {
+    let output = AsyncCommand::new("setxkbmap").arg("-query").output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let field = |name: &str| {
+        stdout.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim().to_string()).filter(|value| !value.is_empty())
+        })
+    };
+
+    Some(KeyboardLayout {
+        layout: field("layout")?,
+        variant: field("variant"),
+    })
+}
@@ -1,108 +1,343 @@
-//! Input simulation via ydotool
+//! Input simulation
 //!
-//! This module provides cross-platform input simulation by shelling out to ydotool,
-//! which uses uinput at the kernel level and works on both X11 and Wayland.
+//! Input is injected by shelling out to one of several tools behind the
+//! [`InputBackend`] trait. ydotool is preferred because it uses uinput at the
+//! kernel level and works on both X11 and Wayland; xdotool (X11) and wtype
+//! (Wayland) are fallbacks for systems without the ydotool daemon.
 
-use tokio::process::Command as AsyncCommand;
+pub mod clipboard;
+pub mod layout;
+mod wtype;
+mod xdotool;
+mod ydotool;
 
-/// Click at screen coordinates
-pub async fn click(x: i32, y: i32, button: &str) -> anyhow::Result<()> {
-    // Move mouse to position
-    let move_status = AsyncCommand::new("ydotool")
-        .args(["mousemove", "--absolute", "-x", &x.to_string(), "-y", &y.to_string()])
-        .status()
-        .await?;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-    if !move_status.success() {
-        anyhow::bail!("ydotool mousemove failed");
+use crate::core::error::MarionetteError;
+
+/// How to invoke ydotool, resolved once at startup
+#[derive(Debug, Clone)]
+pub struct InputConfig {
+    /// ydotool binary name or path, from `MARIONETTE_YDOTOOL_PATH` (default "ydotool")
+    pub ydotool_path: String,
+    /// ydotoold socket from `YDOTOOL_SOCKET`, passed to every invocation when set
+    pub ydotool_socket: Option<String>,
+    /// Delays around input events
+    pub timing: InputTiming,
+    /// Fixed factor from screen coordinates to pointer coordinates, from
+    /// `MARIONETTE_POINTER_SCALE`, replacing the per-monitor scale detected for ydotool
+    pub pointer_scale: Option<f64>,
+}
+
+impl InputConfig {
+    /// Read the configuration from the environment
+    pub fn from_env() -> Self {
+        Self {
+            ydotool_path: std::env::var("MARIONETTE_YDOTOOL_PATH")
+                .ok()
+                .filter(|path| !path.is_empty())
+                .unwrap_or_else(|| "ydotool".to_string()),
+            ydotool_socket: std::env::var("YDOTOOL_SOCKET").ok().filter(|socket| !socket.is_empty()),
+            timing: InputTiming::from_env(),
+            pointer_scale: pointer_scale_from_env(),
+        }
     }
 
-    // Small delay to ensure move completes
-    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    /// Whether the configured ydotool binary can be found
+    pub fn ydotool_available(&self) -> bool {
+        find_binary(&self.ydotool_path).is_some()
+    }
+}
 
-    // Click
-    let button_code = match button {
-        "left" => "0xC0",      // Left button click (down + up)
-        "right" => "0xC1",     // Right button click
-        "middle" => "0xC2",    // Middle button click
-        _ => "0xC0",           // Default to left
-    };
+/// Delays around input events
+///
+/// The defaults suit a typical local session. Fast setups can lower them;
+/// slow remote X servers may need them raised so events aren't dropped.
+#[derive(Debug, Clone, Copy)]
+pub struct InputTiming {
+    /// Pause after moving the pointer before clicking, scrolling, or dragging
+    pub click_settle_ms: u64,
+    /// Pause before each key press so the target is ready for input
+    pub key_press_delay_ms: u64,
+    /// How long ydotool holds the main key of a key press
+    pub key_hold_ms: u64,
+    /// Delay between typed characters when a call doesn't specify one
+    pub type_delay_ms: u32,
+}
+
+impl Default for InputTiming {
+    fn default() -> Self {
+        Self {
+            click_settle_ms: 10,
+            key_press_delay_ms: 100,
+            key_hold_ms: 50,
+            type_delay_ms: 12,
+        }
+    }
+}
 
-    let click_status = AsyncCommand::new("ydotool")
-        .args(["click", button_code])
-        .status()
-        .await?;
+impl InputTiming {
+    /// Read overrides from `MARIONETTE_CLICK_SETTLE_MS`, `MARIONETTE_KEY_PRESS_DELAY_MS`,
+    /// `MARIONETTE_KEY_HOLD_MS`, and `MARIONETTE_TYPE_DELAY_MS`
+    ///
+    /// Unset or unparseable values keep their defaults.
+    pub fn from_env() -> Self {
+        fn env_ms<T: std::str::FromStr>(name: &str) -> Option<T> {
+            let value = std::env::var(name).ok()?;
+            let parsed = value.trim().parse().ok();
+            if parsed.is_none() {
+                tracing::warn!("Ignoring {}={:?}: expected a number of milliseconds", name, value);
+            }
+            parsed
+        }
 
-    if !click_status.success() {
-        anyhow::bail!("ydotool click failed");
+        let defaults = Self::default();
+        Self {
+            click_settle_ms: env_ms("MARIONETTE_CLICK_SETTLE_MS").unwrap_or(defaults.click_settle_ms),
+            key_press_delay_ms: env_ms("MARIONETTE_KEY_PRESS_DELAY_MS").unwrap_or(defaults.key_press_delay_ms),
+            key_hold_ms: env_ms("MARIONETTE_KEY_HOLD_MS").unwrap_or(defaults.key_hold_ms),
+            type_delay_ms: env_ms("MARIONETTE_TYPE_DELAY_MS").unwrap_or(defaults.type_delay_ms),
+        }
     }
+}
 
-    Ok(())
+/// Read `MARIONETTE_POINTER_SCALE`, ignoring values that aren't a positive number
+fn pointer_scale_from_env() -> Option<f64> {
+    let value = std::env::var("MARIONETTE_POINTER_SCALE").ok()?;
+    let scale = value.trim().parse::<f64>().ok().filter(|scale| scale.is_finite() && *scale > 0.0);
+    if scale.is_none() {
+        tracing::warn!("Ignoring MARIONETTE_POINTER_SCALE={:?}: expected a positive number", value);
+    }
+    scale
 }
 
-/// Type text
-pub async fn type_text(text: &str, delay_ms: u32) -> anyhow::Result<()> {
-    let status = AsyncCommand::new("ydotool")
-        .args(["type", "--key-delay", &delay_ms.to_string(), "--", text])
-        .status()
-        .await?;
+/// The input tool can't work until the user fixes their setup
+///
+/// Returned instead of a plain failure when the cause is known, so tools can
+/// tell the client what to do rather than relaying a cryptic tool error.
+#[derive(Debug, thiserror::Error)]
+#[error("{reason}")]
+pub struct InputUnavailable {
+    /// What is missing
+    pub reason: String,
+    /// How to fix it
+    pub suggestion: String,
+}
+
+/// Check that ydotoold is listening on `socket`
+///
+/// ydotoold binds a datagram socket, so connecting fails unless the daemon is running.
+pub fn probe_ydotoold(socket: &Path) -> Result<(), InputUnavailable> {
+    let connected = std::os::unix::net::UnixDatagram::unbound().and_then(|probe| probe.connect(socket));
+    connected.map_err(|e| InputUnavailable {
+        reason: format!("ydotoold is not running or its socket is missing ({}: {})", socket.display(), e),
+        suggestion: "Start ydotoold (e.g. systemctl start ydotoold), or set YDOTOOL_SOCKET to the socket it \
+                     listens on if it isn't in the default location"
+            .to_string(),
+    })
+}
 
-    if !status.success() {
-        anyhow::bail!("ydotool type failed");
+/// Describe a failed input tool invocation with its exit status and output
+///
+/// Both streams are included because ydotool reports some failures (such as a
+/// missing ydotoold socket) on stdout rather than stderr.
+pub(crate) fn command_failure(command: &str, output: &std::process::Output) -> anyhow::Error {
+    let status = match output.status.code() {
+        Some(code) => format!("exit status {}", code),
+        None => "killed by a signal".to_string(),
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let mut message = format!("{} failed ({})", command, status);
+    if !stderr.trim().is_empty() {
+        message.push_str(&format!(": {}", stderr.trim()));
+    }
+    if !stdout.trim().is_empty() {
+        message.push_str(&format!(" [stdout: {}]", stdout.trim()));
+    }
+    anyhow::anyhow!(message)
+}
+
+/// Resolve a binary name or path the same way the shell would
+///
+/// Names containing a `/` are checked directly; bare names are searched for in `$PATH`.
+pub fn find_binary(name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        return path.is_file().then_some(path);
     }
 
-    Ok(())
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    })
 }
 
-/// Press a key with optional modifiers
-pub async fn key_press(key: &str, modifiers: &[String]) -> anyhow::Result<()> {
-    // Build the key string with modifiers
-    // ydotool key format: key[:state] where state is 1 for down, 0 for up, or omit for press
-    // For modifiers, we need to press them down, press the key, then release modifiers
+/// Delay between consecutive clicks, kept well under the typical 200ms+
+/// double-click threshold used by GTK and Qt
+const MULTI_CLICK_DELAY_MS: u32 = 60;
+
+/// Maximum wheel notches sent per axis in a single scroll call
+const MAX_SCROLL_NOTCHES: i32 = 50;
+
+/// Upper bound on how long a hover may rest, so a bad value can't stall the server
+const MAX_HOVER_DURATION_MS: u64 = 10_000;
 
-    // Delay before starting key press to ensure system is ready
-    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+/// Number of intermediate pointer positions between drag start and end
+const DRAG_STEPS: i32 = 10;
 
-    // Map common key names to ydotool key codes
-    let key_code = map_key_to_code(key);
+/// Delay between drag steps, so apps that ignore instantaneous jumps see motion
+const DRAG_STEP_DELAY_MS: u64 = 15;
 
-    let mut args: Vec<String> = vec!["key".to_string()];
+/// Trait for input backend implementations
+///
+/// All coordinates are absolute screen coordinates.
+#[async_trait]
+pub trait InputBackend: Send + Sync {
+    /// Short name of the tool driving input (e.g., "ydotool")
+    fn name(&self) -> &'static str;
 
-    // Press modifiers down
-    for modifier in modifiers {
-        let mod_code = map_modifier_to_code(modifier);
-        args.push(format!("{}:1", mod_code)); // Press down
+    /// Move the mouse to absolute screen coordinates without clicking
+    async fn move_to(&self, x: i32, y: i32) -> anyhow::Result<()>;
+
+    /// Click at screen coordinates
+    ///
+    /// `count` repeats the full down/up sequence, so `2` produces a double-click.
+    async fn click(&self, x: i32, y: i32, button: &str, count: u32) -> anyhow::Result<()>;
+
+    /// Scroll the mouse wheel at screen coordinates
+    ///
+    /// `dx`/`dy` are in wheel notches. Positive `dy` scrolls down, negative scrolls up;
+    /// positive `dx` scrolls right, negative scrolls left. Both are clamped to
+    /// `MAX_SCROLL_NOTCHES` to avoid flooding the target with wheel events.
+    async fn scroll(&self, x: i32, y: i32, dx: i32, dy: i32) -> anyhow::Result<()>;
+
+    /// Drag from one screen coordinate to another while holding a mouse button
+    async fn drag(&self, from_x: i32, from_y: i32, to_x: i32, to_y: i32, button: &str) -> anyhow::Result<()>;
+
+    /// Release a mouse button, e.g. one left down by an interrupted drag
+    async fn button_up(&self, button: &str) -> anyhow::Result<()>;
+
+    /// Type text
+    async fn type_text(&self, text: &str, delay_ms: u32) -> anyhow::Result<()>;
+
+    /// Press a key with optional modifiers
+    async fn key_press(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()>;
+
+    /// Press a key (and its modifiers) down without releasing it
+    async fn key_down(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()>;
+
+    /// Release a key and then its modifiers, in reverse press order
+    async fn key_up(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()>;
+
+    /// Key codes or keysyms this tool would send for a key combination, modifiers first
+    fn key_codes(&self, key: &str, modifiers: &[String]) -> Vec<String>;
+
+    /// Check that the tool (and any daemon it needs) is usable without sending input
+    ///
+    /// Returns a short description of what was verified.
+    async fn health_check(&self) -> anyhow::Result<String>;
+
+    /// Whether pointer coordinates are device pixels rather than logical screen coordinates
+    ///
+    /// True for tools that position the pointer with relative motion from a
+    /// corner, which compositors may apply unscaled on HiDPI monitors.
+    fn pointer_uses_device_pixels(&self) -> bool {
+        false
     }
 
-    // Explicitly press down and release the main key with delay between
-    args.push(format!("{}:1", key_code)); // Key down
-    args.push("50".to_string()); // 50ms delay (ydotool interprets non-keycode values as delays)
-    args.push(format!("{}:0", key_code)); // Key up
+    /// Move the pointer to screen coordinates and rest there without clicking
+    ///
+    /// Only a pointer move is issued, so no button events are ever sent. The sleep
+    /// gives tooltips and hover menus time to appear before the caller continues.
+    async fn hover(&self, x: i32, y: i32, duration_ms: u64) -> anyhow::Result<()> {
+        self.move_to(x, y).await?;
 
-    // Release modifiers (in reverse order)
-    for modifier in modifiers.iter().rev() {
-        let mod_code = map_modifier_to_code(modifier);
-        args.push(format!("{}:0", mod_code)); // Release
+        let duration_ms = duration_ms.min(MAX_HOVER_DURATION_MS);
+        tokio::time::sleep(std::time::Duration::from_millis(duration_ms)).await;
+
+        Ok(())
     }
+}
 
-    tracing::debug!("Executing ydotool key with args: {:?}", args);
+/// Create the input backend for the current session
+///
+/// ydotool is preferred everywhere. Without it, xdotool is used on X11 and
+/// wtype on Wayland, so users lacking ydotoold or uinput access still get input.
+pub fn create_input_backend(config: InputConfig) -> Arc<dyn InputBackend> {
+    if config.ydotool_available() {
+        tracing::info!("Using ydotool input backend ({})", config.ydotool_path);
+        let input = ydotool::YdotoolInput::new(config);
+        if let Err(e) = input.ensure_daemon() {
+            tracing::warn!("{}. {}; input tools will fail until it is", e.reason, e.suggestion);
+        }
+        return Arc::new(input);
+    }
 
-    let output = AsyncCommand::new("ydotool")
-        .args(&args)
-        .output()
-        .await?;
+    tracing::warn!(
+        "ydotool not found at '{}'. Install ydotool or set MARIONETTE_YDOTOOL_PATH to its location",
+        config.ydotool_path
+    );
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        anyhow::bail!("ydotool key failed: stderr={}, stdout={}", stderr, stdout);
+    if crate::backend::is_wayland() {
+        if find_binary("wtype").is_some() {
+            tracing::info!("Using wtype input backend (keyboard only)");
+            return Arc::new(wtype::WtypeInput::new(config.timing));
+        }
+    } else if std::env::var("DISPLAY").is_ok() && find_binary("xdotool").is_some() {
+        tracing::info!("Using xdotool input backend");
+        return Arc::new(xdotool::XdotoolInput::new(config.timing));
     }
 
-    Ok(())
+    // Nothing usable was found; keep ydotool so errors name the missing tool
+    tracing::warn!("No input tool found; input tools will fail until ydotool is installed");
+    Arc::new(ydotool::YdotoolInput::new(config))
+}
+
+/// Split a combo string like "ctrl+shift+t" into its key and modifiers
+///
+/// The last `+`-separated token is the key and the rest are modifiers, which
+/// are merged after `modifiers` without duplicates. A lone `+`, or a combo
+/// ending in `++` (e.g. "ctrl++"), presses the `+` key itself. Keys without
+/// a `+` are returned unchanged. A combo with nothing after its last `+`
+/// (e.g. "shift+") is rejected.
+pub(crate) fn parse_key_combo(key: &str, modifiers: &[String]) -> Result<(String, Vec<String>), MarionetteError> {
+    let mut merged = modifiers.to_vec();
+    if !key.contains('+') {
+        return Ok((key.to_string(), merged));
+    }
+
+    // Allow "Ctrl + Shift + T"
+    let combo: String = key.split_whitespace().collect();
+    let (prefix, key) = if combo == "+" {
+        ("", "+")
+    } else if let Some(prefix) = combo.strip_suffix("++") {
+        (prefix, "+")
+    } else {
+        combo.rsplit_once('+').unwrap_or(("", combo.as_str()))
+    };
+    if key.is_empty() {
+        return Err(MarionetteError::invalid_params(
+            format!("Key combo '{}' has no key after the last '+'", combo),
+            Some("Name the key after the modifiers, e.g. \"shift+a\", or use \"shift++\" for the + key itself"),
+        ));
+    }
+
+    for modifier in prefix.split('+').filter(|m| !m.is_empty()) {
+        if !merged.iter().any(|m| m.eq_ignore_ascii_case(modifier)) {
+            merged.push(modifier.to_string());
+        }
+    }
+
+    Ok((key.to_string(), merged))
 }
 
 /// Map human-readable key names to ydotool key codes
-fn map_key_to_code(key: &str) -> String {
+pub(crate) fn map_key_to_code(key: &str) -> String {
     // ydotool uses Linux input event codes
     // See: /usr/include/linux/input-event-codes.h
     match key.to_lowercase().as_str() {
@@ -118,6 +353,9 @@ fn map_key_to_code(key: &str) -> String {
         "end" => "107".to_string(),                 // KEY_END
         "pageup" => "104".to_string(),              // KEY_PAGEUP
         "pagedown" => "109".to_string(),            // KEY_PAGEDOWN
+        "capslock" => "58".to_string(),             // KEY_CAPSLOCK
+        "numlock" => "69".to_string(),              // KEY_NUMLOCK
+        "scrolllock" => "70".to_string(),           // KEY_SCROLLLOCK
 
         // Arrow keys
         "up" => "103".to_string(),                  // KEY_UP
@@ -179,18 +417,293 @@ fn map_key_to_code(key: &str) -> String {
         "8" => "9".to_string(),
         "9" => "10".to_string(),
 
+        // Punctuation
+        "minus" | "-" => "12".to_string(),          // KEY_MINUS
+        "equal" | "=" => "13".to_string(),          // KEY_EQUAL
+        "bracketleft" | "[" => "26".to_string(),    // KEY_LEFTBRACE
+        "bracketright" | "]" => "27".to_string(),   // KEY_RIGHTBRACE
+        "semicolon" | ";" => "39".to_string(),      // KEY_SEMICOLON
+        "apostrophe" | "'" => "40".to_string(),     // KEY_APOSTROPHE
+        "grave" | "`" => "41".to_string(),          // KEY_GRAVE
+        "backslash" | "\\" => "43".to_string(),     // KEY_BACKSLASH
+        "comma" | "," => "51".to_string(),          // KEY_COMMA
+        "period" | "." => "52".to_string(),         // KEY_DOT
+        "slash" | "/" => "53".to_string(),          // KEY_SLASH
+        "plus" | "+" => "13".to_string(),           // KEY_EQUAL, pressed with shift (see key_needs_shift)
+
+        // Numeric keypad
+        "kp0" => "82".to_string(),
+        "kp1" => "79".to_string(),
+        "kp2" => "80".to_string(),
+        "kp3" => "81".to_string(),
+        "kp4" => "75".to_string(),
+        "kp5" => "76".to_string(),
+        "kp6" => "77".to_string(),
+        "kp7" => "71".to_string(),
+        "kp8" => "72".to_string(),
+        "kp9" => "73".to_string(),
+        "kpplus" | "kpadd" => "78".to_string(),              // KEY_KPPLUS
+        "kpminus" | "kpsubtract" => "74".to_string(),        // KEY_KPMINUS
+        "kpmultiply" | "kpasterisk" => "55".to_string(),     // KEY_KPASTERISK
+        "kpdivide" | "kpslash" => "98".to_string(),          // KEY_KPSLASH
+        "kpdecimal" | "kpdot" => "83".to_string(),           // KEY_KPDOT
+        "kpenter" => "96".to_string(),                       // KEY_KPENTER
+
+        // Media keys
+        "volumeup" => "115".to_string(),            // KEY_VOLUMEUP
+        "volumedown" => "114".to_string(),          // KEY_VOLUMEDOWN
+        "mute" => "113".to_string(),                // KEY_MUTE
+        "playpause" | "play" => "164".to_string(),  // KEY_PLAYPAUSE
+        "stop" => "166".to_string(),                // KEY_STOPCD
+        "next" => "163".to_string(),                // KEY_NEXTSONG
+        "previous" | "prev" => "165".to_string(),   // KEY_PREVIOUSSONG
+
         // Default: try to parse as raw code
         other => other.to_string(),
     }
 }
 
+/// Whether a key shares its key code with another key and needs shift held for ydotool
+///
+/// Keysym backends send "plus" directly, but ydotool only knows key codes, so
+/// `+` is the equal key with shift.
+pub(crate) fn key_needs_shift(key: &str) -> bool {
+    matches!(key.to_lowercase().as_str(), "plus" | "+")
+}
+
 /// Map modifier names to ydotool key codes
-fn map_modifier_to_code(modifier: &str) -> String {
+pub(crate) fn map_modifier_to_code(modifier: &str) -> String {
     match modifier.to_lowercase().as_str() {
         "ctrl" | "control" => "29".to_string(),     // KEY_LEFTCTRL
         "alt" => "56".to_string(),                   // KEY_LEFTALT
         "shift" => "42".to_string(),                 // KEY_LEFTSHIFT
         "super" | "meta" | "win" => "125".to_string(), // KEY_LEFTMETA
+        "rctrl" | "rcontrol" => "97".to_string(),   // KEY_RIGHTCTRL
+        "ralt" | "altgr" => "100".to_string(),       // KEY_RIGHTALT
+        "rshift" => "54".to_string(),                // KEY_RIGHTSHIFT
+        "rsuper" | "rmeta" | "rwin" => "126".to_string(), // KEY_RIGHTMETA
+        // Lock keys toggle rather than being held; see split_lock_modifiers
+        lock if is_lock_key(lock) => map_key_to_code(lock),
+        other => other.to_string(),
+    }
+}
+
+/// Whether a key name is a lock key, which toggles state on each press
+pub(crate) fn is_lock_key(name: &str) -> bool {
+    matches!(name.to_lowercase().as_str(), "capslock" | "numlock" | "scrolllock")
+}
+
+/// Separate lock keys from the modifiers held during a key press
+///
+/// Holding CapsLock or NumLock doesn't work like holding Shift: the lock
+/// toggles on press. Backends tap each lock before the key and again after,
+/// so the key is sent with the lock engaged and the original state is restored.
+pub(crate) fn split_lock_modifiers(modifiers: &[String]) -> (Vec<String>, Vec<String>) {
+    modifiers.iter().cloned().partition(|modifier| is_lock_key(modifier))
+}
+
+/// Map human-readable key names to X keysym names (used by xdotool and wtype)
+pub(crate) fn map_key_to_keysym(key: &str) -> String {
+    match key.to_lowercase().as_str() {
+        "return" | "enter" => "Return".to_string(),
+        "escape" | "esc" => "Escape".to_string(),
+        "tab" => "Tab".to_string(),
+        "backspace" => "BackSpace".to_string(),
+        "space" => "space".to_string(),
+        "delete" => "Delete".to_string(),
+        "insert" => "Insert".to_string(),
+        "home" => "Home".to_string(),
+        "end" => "End".to_string(),
+        "pageup" => "Page_Up".to_string(),
+        "pagedown" => "Page_Down".to_string(),
+        "capslock" => "Caps_Lock".to_string(),
+        "numlock" => "Num_Lock".to_string(),
+        "scrolllock" => "Scroll_Lock".to_string(),
+        "up" => "Up".to_string(),
+        "down" => "Down".to_string(),
+        "left" => "Left".to_string(),
+        "right" => "Right".to_string(),
+        "minus" | "-" => "minus".to_string(),
+        "equal" | "=" => "equal".to_string(),
+        "bracketleft" | "[" => "bracketleft".to_string(),
+        "bracketright" | "]" => "bracketright".to_string(),
+        "semicolon" | ";" => "semicolon".to_string(),
+        "apostrophe" | "'" => "apostrophe".to_string(),
+        "grave" | "`" => "grave".to_string(),
+        "backslash" | "\\" => "backslash".to_string(),
+        "comma" | "," => "comma".to_string(),
+        "period" | "." => "period".to_string(),
+        "slash" | "/" => "slash".to_string(),
+        "plus" | "+" => "plus".to_string(),
+        "kpplus" | "kpadd" => "KP_Add".to_string(),
+        "kpminus" | "kpsubtract" => "KP_Subtract".to_string(),
+        "kpmultiply" | "kpasterisk" => "KP_Multiply".to_string(),
+        "kpdivide" | "kpslash" => "KP_Divide".to_string(),
+        "kpdecimal" | "kpdot" => "KP_Decimal".to_string(),
+        "kpenter" => "KP_Enter".to_string(),
+        "volumeup" => "XF86AudioRaiseVolume".to_string(),
+        "volumedown" => "XF86AudioLowerVolume".to_string(),
+        "mute" => "XF86AudioMute".to_string(),
+        "playpause" | "play" => "XF86AudioPlay".to_string(),
+        "stop" => "XF86AudioStop".to_string(),
+        "next" => "XF86AudioNext".to_string(),
+        "previous" | "prev" => "XF86AudioPrev".to_string(),
+        // Keypad digits are KP_0..KP_9
+        other if other.len() == 3 && other.starts_with("kp") && other.as_bytes()[2].is_ascii_digit() => {
+            format!("KP_{}", &other[2..])
+        }
+        // Function keys are uppercase keysyms (F1..F12)
+        other if other.len() > 1 && other.starts_with('f') && other[1..].parse::<u8>().is_ok() => {
+            other.to_uppercase()
+        }
+        // Letters, digits, and raw keysyms pass through unchanged
+        _ => key.to_string(),
+    }
+}
+
+/// Map modifier names to the names xdotool accepts in key chords
+pub(crate) fn map_modifier_to_keysym(modifier: &str) -> String {
+    match modifier.to_lowercase().as_str() {
+        "ctrl" | "control" => "ctrl".to_string(),
+        "alt" => "alt".to_string(),
+        "shift" => "shift".to_string(),
+        "super" | "meta" | "win" => "super".to_string(),
+        "rctrl" | "rcontrol" => "Control_R".to_string(),
+        "ralt" => "Alt_R".to_string(),
+        // AltGr is a distinct level shift on most non-US layouts
+        "altgr" => "ISO_Level3_Shift".to_string(),
+        "rshift" => "Shift_R".to_string(),
+        "rsuper" | "rmeta" | "rwin" => "Super_R".to_string(),
+        lock if is_lock_key(lock) => map_key_to_keysym(lock),
         other => other.to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(key: &str, modifiers: &[&str]) -> (String, Vec<String>) {
+        let modifiers: Vec<String> = modifiers.iter().map(|m| m.to_string()).collect();
+        parse_key_combo(key, &modifiers).expect("combo should parse")
+    }
+
+    #[test]
+    fn parses_modifier_combo() {
+        assert_eq!(combo("ctrl+shift+t", &[]), ("t".to_string(), vec!["ctrl".to_string(), "shift".to_string()]));
+    }
+
+    #[test]
+    fn parses_plus_key() {
+        assert_eq!(combo("+", &[]), ("+".to_string(), vec![]));
+        assert_eq!(combo("ctrl++", &[]), ("+".to_string(), vec!["ctrl".to_string()]));
+        assert_eq!(combo(" ctrl + + ", &[]), ("+".to_string(), vec!["ctrl".to_string()]));
+    }
+
+    #[test]
+    fn rejects_combo_without_key() {
+        assert!(matches!(parse_key_combo("shift+", &[]), Err(MarionetteError::InvalidParams { .. })));
+        assert!(matches!(parse_key_combo("ctrl+shift+ ", &[]), Err(MarionetteError::InvalidParams { .. })));
+    }
+
+    #[test]
+    fn ignores_surrounding_whitespace() {
+        assert_eq!(combo("  Ctrl + Shift + T ", &[]), ("T".to_string(), vec!["Ctrl".to_string(), "Shift".to_string()]));
+    }
+
+    #[test]
+    fn merges_with_modifiers_array() {
+        assert_eq!(
+            combo("CTRL+alt+t", &["ctrl", "shift"]),
+            ("t".to_string(), vec!["ctrl".to_string(), "shift".to_string(), "alt".to_string()])
+        );
+        // Keys without a `+` are left alone
+        assert_eq!(combo("Return", &["alt"]), ("Return".to_string(), vec!["alt".to_string()]));
+    }
+
+    #[test]
+    fn maps_plus_key() {
+        assert_eq!(map_key_to_code("+"), "13");
+        assert!(key_needs_shift("+"));
+        assert!(!key_needs_shift("equal"));
+        assert_eq!(map_key_to_keysym("+"), "plus");
+    }
+
+    #[test]
+    fn maps_keypad_media_and_punctuation_keys() {
+        let cases = [
+            // (name, ydotool code, keysym)
+            ("kp0", "82", "KP_0"),
+            ("kp5", "76", "KP_5"),
+            ("kp9", "73", "KP_9"),
+            ("kpadd", "78", "KP_Add"),
+            ("KPPlus", "78", "KP_Add"),
+            ("kpsubtract", "74", "KP_Subtract"),
+            ("kpmultiply", "55", "KP_Multiply"),
+            ("kpdivide", "98", "KP_Divide"),
+            ("kpdecimal", "83", "KP_Decimal"),
+            ("kpenter", "96", "KP_Enter"),
+            ("volumeup", "115", "XF86AudioRaiseVolume"),
+            ("volumedown", "114", "XF86AudioLowerVolume"),
+            ("mute", "113", "XF86AudioMute"),
+            ("playpause", "164", "XF86AudioPlay"),
+            ("stop", "166", "XF86AudioStop"),
+            ("next", "163", "XF86AudioNext"),
+            ("prev", "165", "XF86AudioPrev"),
+            ("minus", "12", "minus"),
+            ("=", "13", "equal"),
+            ("[", "26", "bracketleft"),
+            ("]", "27", "bracketright"),
+            (";", "39", "semicolon"),
+            ("'", "40", "apostrophe"),
+            ("`", "41", "grave"),
+            ("\\", "43", "backslash"),
+            (",", "51", "comma"),
+            (".", "52", "period"),
+            ("/", "53", "slash"),
+        ];
+        for (name, code, keysym) in cases {
+            assert_eq!(map_key_to_code(name), code, "code for {}", name);
+            assert_eq!(map_key_to_keysym(name), keysym, "keysym for {}", name);
+        }
+    }
+
+    #[test]
+    fn unknown_keys_fall_through() {
+        // Raw codes and keysyms are passed on for the backend to interpret
+        assert_eq!(map_key_to_code("183"), "183");
+        assert_eq!(map_key_to_keysym("XF86Calculator"), "XF86Calculator");
+        assert_eq!(map_key_to_keysym("kp"), "kp");
+    }
+
+    #[test]
+    fn maps_right_side_and_lock_modifiers() {
+        let cases = [
+            // (name, ydotool code, xdotool keysym)
+            ("rctrl", "97", "Control_R"),
+            ("RCtrl", "97", "Control_R"),
+            ("ralt", "100", "Alt_R"),
+            ("altgr", "100", "ISO_Level3_Shift"),
+            ("rshift", "54", "Shift_R"),
+            ("rsuper", "126", "Super_R"),
+            ("capslock", "58", "Caps_Lock"),
+            ("numlock", "69", "Num_Lock"),
+        ];
+        for (name, code, keysym) in cases {
+            assert_eq!(map_modifier_to_code(name), code, "code for {}", name);
+            assert_eq!(map_modifier_to_keysym(name), keysym, "keysym for {}", name);
+        }
+    }
+
+    #[test]
+    fn lock_modifiers_are_split_from_held_set() {
+        let modifiers: Vec<String> = ["ctrl", "CapsLock", "rshift", "numlock"].iter().map(|m| m.to_string()).collect();
+        let (locks, held) = split_lock_modifiers(&modifiers);
+        assert_eq!(locks, vec!["CapsLock".to_string(), "numlock".to_string()]);
+        assert_eq!(held, vec!["ctrl".to_string(), "rshift".to_string()]);
+
+        let (locks, held) = split_lock_modifiers(&["altgr".to_string()]);
+        assert!(locks.is_empty());
+        assert_eq!(held, vec!["altgr".to_string()]);
+    }
+}
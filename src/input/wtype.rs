@@ -0,0 +1,154 @@
+//! wtype input backend for Wayland sessions
+//!
+//! wtype uses the virtual-keyboard protocol, so it needs no daemon or uinput
+//! access. It is keyboard-only: pointer tools fail with an explanatory error.
+//! GNOME's Mutter does not implement the protocol.
+
+use async_trait::async_trait;
+use tokio::process::Command as AsyncCommand;
+
+use super::{command_failure, map_key_to_keysym, map_modifier_to_keysym, split_lock_modifiers, InputBackend, InputTiming};
+
+/// Input backend that shells out to wtype
+pub struct WtypeInput {
+    timing: InputTiming,
+}
+
+impl WtypeInput {
+    /// Create a wtype backend with the given input delays
+    pub fn new(timing: InputTiming) -> Self {
+        Self { timing }
+    }
+
+    /// Run wtype with the given arguments, failing with its exit status and output on error
+    async fn run(&self, args: &[String]) -> anyhow::Result<()> {
+        tracing::debug!("Executing wtype with args: {:?}", args);
+
+        let output = AsyncCommand::new("wtype")
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to run wtype: {}", e))?;
+
+        if !output.status.success() {
+            return Err(command_failure("wtype", &output));
+        }
+
+        Ok(())
+    }
+}
+
+/// Modifier name as accepted by wtype's `-M`/`-m` flags
+fn wtype_modifier(modifier: &str) -> String {
+    // wtype calls the super key "logo" and doesn't distinguish left from right
+    match map_modifier_to_keysym(modifier).as_str() {
+        "super" | "Super_R" => "logo".to_string(),
+        "Control_R" => "ctrl".to_string(),
+        "Alt_R" => "alt".to_string(),
+        "ISO_Level3_Shift" => "altgr".to_string(),
+        "Shift_R" => "shift".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Error for held keys, which wtype cannot keep down
+///
+/// wtype's virtual keyboard only exists while the process runs, so anything it
+/// presses is released as soon as it exits.
+fn key_hold_unsupported() -> anyhow::Error {
+    anyhow::anyhow!(
+        "The wtype input backend cannot hold keys down, since wtype releases them when it \
+         exits. Install ydotool and run ydotoold to hold keys on Wayland"
+    )
+}
+
+/// Error for pointer operations, which wtype cannot perform
+fn pointer_unsupported() -> anyhow::Error {
+    anyhow::anyhow!(
+        "The wtype input backend cannot control the pointer. Install ydotool and run \
+         ydotoold to use mouse tools on Wayland"
+    )
+}
+
+#[async_trait]
+impl InputBackend for WtypeInput {
+    fn name(&self) -> &'static str {
+        "wtype"
+    }
+
+    async fn move_to(&self, _x: i32, _y: i32) -> anyhow::Result<()> {
+        Err(pointer_unsupported())
+    }
+
+    async fn click(&self, _x: i32, _y: i32, _button: &str, _count: u32) -> anyhow::Result<()> {
+        Err(pointer_unsupported())
+    }
+
+    async fn scroll(&self, _x: i32, _y: i32, _dx: i32, _dy: i32) -> anyhow::Result<()> {
+        Err(pointer_unsupported())
+    }
+
+    async fn drag(&self, _from_x: i32, _from_y: i32, _to_x: i32, _to_y: i32, _button: &str) -> anyhow::Result<()> {
+        Err(pointer_unsupported())
+    }
+
+    async fn button_up(&self, _button: &str) -> anyhow::Result<()> {
+        Err(pointer_unsupported())
+    }
+
+    async fn type_text(&self, text: &str, delay_ms: u32) -> anyhow::Result<()> {
+        self.run(&[
+            "-d".to_string(),
+            delay_ms.to_string(),
+            "--".to_string(),
+            text.to_string(),
+        ])
+        .await
+    }
+
+    async fn key_press(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()> {
+        // Delay before starting key press to ensure system is ready
+        tokio::time::sleep(std::time::Duration::from_millis(self.timing.key_press_delay_ms)).await;
+
+        // Lock keys are tapped before and after rather than held
+        let (locks, modifiers) = split_lock_modifiers(modifiers);
+        let lock_taps: Vec<String> = locks
+            .iter()
+            .flat_map(|lock| ["-k".to_string(), map_key_to_keysym(lock)])
+            .collect();
+
+        let mut args = lock_taps.clone();
+        for modifier in &modifiers {
+            args.extend(["-M".to_string(), wtype_modifier(modifier)]);
+        }
+        args.extend(["-k".to_string(), map_key_to_keysym(key)]);
+        for modifier in modifiers.iter().rev() {
+            args.extend(["-m".to_string(), wtype_modifier(modifier)]);
+        }
+        args.extend(lock_taps);
+
+        self.run(&args).await
+    }
+
+    async fn key_down(&self, _key: &str, _modifiers: &[String]) -> anyhow::Result<()> {
+        Err(key_hold_unsupported())
+    }
+
+    async fn key_up(&self, _key: &str, _modifiers: &[String]) -> anyhow::Result<()> {
+        Err(key_hold_unsupported())
+    }
+
+    async fn health_check(&self) -> anyhow::Result<String> {
+        let binary = super::find_binary("wtype").ok_or_else(|| anyhow::anyhow!("wtype not found in PATH"))?;
+        // wtype has no side-effect-free command, so only its presence can be checked
+        Ok(format!("{} found (keyboard only; not probed further)", binary.display()))
+    }
+
+    fn key_codes(&self, key: &str, modifiers: &[String]) -> Vec<String> {
+        modifiers
+            .iter()
+            .map(|modifier| wtype_modifier(modifier))
+            .chain(std::iter::once(map_key_to_keysym(key)))
+            .collect()
+    }
+}
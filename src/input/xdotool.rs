@@ -0,0 +1,194 @@
+//! xdotool input backend for X11 sessions
+//!
+//! xdotool sends synthetic events through the XTEST extension, so it needs no
+//! daemon or uinput access, but it only reaches X11 (and XWayland) clients.
+
+use async_trait::async_trait;
+use tokio::process::Command as AsyncCommand;
+
+use super::{
+    command_failure, map_key_to_keysym, map_modifier_to_keysym, split_lock_modifiers, InputBackend, InputTiming,
+    DRAG_STEPS, DRAG_STEP_DELAY_MS, MAX_SCROLL_NOTCHES, MULTI_CLICK_DELAY_MS,
+};
+
+/// Input backend that shells out to xdotool
+pub struct XdotoolInput {
+    timing: InputTiming,
+}
+
+impl XdotoolInput {
+    /// Create an xdotool backend with the given input delays
+    pub fn new(timing: InputTiming) -> Self {
+        Self { timing }
+    }
+
+    /// Run xdotool with the given arguments, failing with its exit status and output on error
+    async fn run(&self, args: &[String]) -> anyhow::Result<()> {
+        tracing::debug!("Executing xdotool with args: {:?}", args);
+
+        let output = AsyncCommand::new("xdotool")
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to run xdotool: {}", e))?;
+
+        if !output.status.success() {
+            return Err(command_failure(&format!("xdotool {}", args.first().map_or("", |a| a.as_str())), &output));
+        }
+
+        Ok(())
+    }
+
+    /// Click a button number `repeat` times at the current pointer position
+    async fn click_button(&self, button: u8, repeat: u32) -> anyhow::Result<()> {
+        self.run(&[
+            "click".to_string(),
+            "--repeat".to_string(),
+            repeat.to_string(),
+            "--delay".to_string(),
+            MULTI_CLICK_DELAY_MS.to_string(),
+            button.to_string(),
+        ])
+        .await
+    }
+}
+
+/// X11 pointer button numbers
+fn button_number(button: &str) -> u8 {
+    match button {
+        "left" => 1,
+        "middle" => 2,
+        "right" => 3,
+        _ => 1,                // Default to left
+    }
+}
+
+/// Build an xdotool key chord such as "ctrl+shift+Return"
+fn chord(key: &str, modifiers: &[String]) -> String {
+    modifiers
+        .iter()
+        .map(|modifier| map_modifier_to_keysym(modifier))
+        .chain(std::iter::once(map_key_to_keysym(key)))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+#[async_trait]
+impl InputBackend for XdotoolInput {
+    fn name(&self) -> &'static str {
+        "xdotool"
+    }
+
+    async fn move_to(&self, x: i32, y: i32) -> anyhow::Result<()> {
+        self.run(&["mousemove".to_string(), x.to_string(), y.to_string()]).await
+    }
+
+    async fn click(&self, x: i32, y: i32, button: &str, count: u32) -> anyhow::Result<()> {
+        self.move_to(x, y).await?;
+        tokio::time::sleep(std::time::Duration::from_millis(self.timing.click_settle_ms)).await;
+
+        self.click_button(button_number(button), count.max(1)).await
+    }
+
+    async fn scroll(&self, x: i32, y: i32, dx: i32, dy: i32) -> anyhow::Result<()> {
+        let dx = dx.clamp(-MAX_SCROLL_NOTCHES, MAX_SCROLL_NOTCHES);
+        let dy = dy.clamp(-MAX_SCROLL_NOTCHES, MAX_SCROLL_NOTCHES);
+
+        self.move_to(x, y).await?;
+        tokio::time::sleep(std::time::Duration::from_millis(self.timing.click_settle_ms)).await;
+
+        // X11 reports wheel notches as clicks of buttons 4/5 (vertical) and 6/7 (horizontal)
+        if dy != 0 {
+            let button = if dy > 0 { 5 } else { 4 };
+            self.click_button(button, dy.unsigned_abs()).await?;
+        }
+        if dx != 0 {
+            let button = if dx > 0 { 7 } else { 6 };
+            self.click_button(button, dx.unsigned_abs()).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn drag(&self, from_x: i32, from_y: i32, to_x: i32, to_y: i32, button: &str) -> anyhow::Result<()> {
+        let button = button_number(button).to_string();
+
+        self.move_to(from_x, from_y).await?;
+        tokio::time::sleep(std::time::Duration::from_millis(self.timing.click_settle_ms)).await;
+
+        self.run(&["mousedown".to_string(), button.clone()]).await?;
+
+        // Interpolate towards the target, always ending exactly on it
+        let mut move_result = Ok(());
+        for step in 1..=DRAG_STEPS {
+            tokio::time::sleep(std::time::Duration::from_millis(DRAG_STEP_DELAY_MS)).await;
+            let x = from_x + (to_x - from_x) * step / DRAG_STEPS;
+            let y = from_y + (to_y - from_y) * step / DRAG_STEPS;
+            if let Err(e) = self.move_to(x, y).await {
+                move_result = Err(e);
+                break;
+            }
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(DRAG_STEP_DELAY_MS)).await;
+
+        // Always release the button, even if a move failed, so it isn't left held
+        let up_result = self.run(&["mouseup".to_string(), button]).await;
+
+        move_result?;
+        up_result
+    }
+
+    async fn button_up(&self, button: &str) -> anyhow::Result<()> {
+        self.run(&["mouseup".to_string(), button_number(button).to_string()]).await
+    }
+
+    async fn type_text(&self, text: &str, delay_ms: u32) -> anyhow::Result<()> {
+        self.run(&[
+            "type".to_string(),
+            "--delay".to_string(),
+            delay_ms.to_string(),
+            "--".to_string(),
+            text.to_string(),
+        ])
+        .await
+    }
+
+    async fn key_press(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()> {
+        // Delay before starting key press to ensure system is ready
+        tokio::time::sleep(std::time::Duration::from_millis(self.timing.key_press_delay_ms)).await;
+
+        // Lock keys are tapped around the chord rather than held in it
+        let (locks, modifiers) = split_lock_modifiers(modifiers);
+        let locks: Vec<String> = locks.iter().map(|lock| map_key_to_keysym(lock)).collect();
+
+        let mut args = vec!["key".to_string()];
+        args.extend(locks.iter().cloned());
+        args.push(chord(key, &modifiers));
+        args.extend(locks);
+
+        self.run(&args).await
+    }
+
+    async fn key_down(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()> {
+        self.run(&["keydown".to_string(), chord(key, modifiers)]).await
+    }
+
+    async fn key_up(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()> {
+        self.run(&["keyup".to_string(), chord(key, modifiers)]).await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<String> {
+        // A harmless query exercises both the binary and the X server connection
+        self.run(&["getmouselocation".to_string()]).await?;
+        Ok("xdotool can reach the X server".to_string())
+    }
+
+    fn key_codes(&self, key: &str, modifiers: &[String]) -> Vec<String> {
+        modifiers
+            .iter()
+            .map(|modifier| map_modifier_to_keysym(modifier))
+            .chain(std::iter::once(map_key_to_keysym(key)))
+            .collect()
+    }
+}
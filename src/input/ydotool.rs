@@ -0,0 +1,297 @@
+//! ydotool input backend
+//!
+//! ydotool injects events through uinput at the kernel level, so it works on
+//! X11 and every Wayland compositor, but it needs the ydotoold daemon running
+//! and write access to `/dev/uinput`.
+
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::process::Command as AsyncCommand;
+
+use super::{
+    command_failure, find_binary, key_needs_shift, map_key_to_code, map_modifier_to_code, probe_ydotoold,
+    split_lock_modifiers, InputBackend, InputConfig, InputUnavailable, DRAG_STEPS, DRAG_STEP_DELAY_MS, MAX_SCROLL_NOTCHES,
+    MULTI_CLICK_DELAY_MS,
+};
+
+/// Input backend that shells out to ydotool
+pub struct YdotoolInput {
+    config: InputConfig,
+    /// Set once ydotoold has been found listening, so later calls skip the probe
+    daemon_ready: AtomicBool,
+}
+
+impl YdotoolInput {
+    /// Create a ydotool backend using the given binary and socket configuration
+    pub fn new(config: InputConfig) -> Self {
+        Self { config, daemon_ready: AtomicBool::new(false) }
+    }
+
+    /// Check that ydotoold is reachable, probing only until it first is
+    ///
+    /// A failed probe isn't cached, so starting ydotoold fixes input without a
+    /// server restart.
+    pub fn ensure_daemon(&self) -> Result<(), InputUnavailable> {
+        if self.daemon_ready.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        probe_ydotoold(&self.socket_path())?;
+        self.daemon_ready.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Build a ydotool command with the configured socket
+    fn ydotool(&self) -> AsyncCommand {
+        let mut command = AsyncCommand::new(&self.config.ydotool_path);
+        if let Some(socket) = &self.config.ydotool_socket {
+            command.env("YDOTOOL_SOCKET", socket);
+        }
+        command
+    }
+
+    /// Run ydotool with the given arguments, failing with its exit status and output on error
+    async fn run<S: AsRef<str>>(&self, args: &[S]) -> anyhow::Result<()> {
+        self.ensure_daemon()?;
+
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+        tracing::debug!("Executing ydotool with args: {:?}", args);
+
+        let output = self.ydotool()
+            .args(&args)
+            .output()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", self.config.ydotool_path, e))?;
+
+        if !output.status.success() {
+            return Err(command_failure(&format!("ydotool {}", args.first().unwrap_or(&"")), &output));
+        }
+
+        Ok(())
+    }
+
+    /// Socket ydotool will use: `YDOTOOL_SOCKET`, or ydotoold's default location
+    fn socket_path(&self) -> std::path::PathBuf {
+        if let Some(socket) = &self.config.ydotool_socket {
+            return socket.into();
+        }
+
+        // Newer ydotoold versions use the runtime directory; older ones use /tmp
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(|dir| std::path::PathBuf::from(dir).join(".ydotool_socket"))
+            .filter(|path| path.exists())
+            .unwrap_or_else(|| "/tmp/.ydotool_socket".into())
+    }
+
+    /// Send a single `ydotool key` invocation setting each code to the given state
+    async fn set_key_states(&self, codes: &[String], pressed: bool) -> anyhow::Result<()> {
+        if codes.is_empty() {
+            return Ok(());
+        }
+
+        let state = if pressed { 1 } else { 0 };
+        let mut args: Vec<String> = vec!["key".to_string()];
+        args.extend(codes.iter().map(|code| format!("{}:{}", code, state)));
+
+        self.run(&args).await
+    }
+}
+
+/// Linux input event code of a mouse button
+///
+/// Buttons are pressed and released through `ydotool key`, which supports the
+/// same :1/:0 state semantics for them as for keys.
+fn button_code(button: &str) -> &'static str {
+    match button {
+        "left" => "272",       // BTN_LEFT
+        "right" => "273",      // BTN_RIGHT
+        "middle" => "274",     // BTN_MIDDLE
+        _ => "272",            // Default to left
+    }
+}
+
+/// Modifiers to hold for a key, adding shift for keys like `+` that need it
+fn with_shift(key: &str, modifiers: &[String]) -> Vec<String> {
+    let mut modifiers = modifiers.to_vec();
+    if key_needs_shift(key) && !modifiers.iter().any(|m| m.eq_ignore_ascii_case("shift")) {
+        modifiers.push("shift".to_string());
+    }
+    modifiers
+}
+
+/// Key codes for a key and its modifiers, in press order (modifiers first)
+fn key_codes(key: &str, modifiers: &[String]) -> Vec<String> {
+    with_shift(key, modifiers)
+        .iter()
+        .map(|modifier| map_modifier_to_code(modifier))
+        .chain(std::iter::once(map_key_to_code(key)))
+        .collect()
+}
+
+#[async_trait]
+impl InputBackend for YdotoolInput {
+    fn name(&self) -> &'static str {
+        "ydotool"
+    }
+
+    fn pointer_uses_device_pixels(&self) -> bool {
+        // --absolute moves to the top-left corner, then by relative motion in device units
+        true
+    }
+
+    async fn move_to(&self, x: i32, y: i32) -> anyhow::Result<()> {
+        self.run(&["mousemove", "--absolute", "-x", &x.to_string(), "-y", &y.to_string()]).await
+    }
+
+    async fn click(&self, x: i32, y: i32, button: &str, count: u32) -> anyhow::Result<()> {
+        // Move mouse to position
+        self.move_to(x, y).await?;
+
+        // Small delay to ensure move completes
+        tokio::time::sleep(std::time::Duration::from_millis(self.config.timing.click_settle_ms)).await;
+
+        // Click
+        let button_code = match button {
+            "left" => "0xC0",      // Left button click (down + up)
+            "right" => "0xC1",     // Right button click
+            "middle" => "0xC2",    // Middle button click
+            _ => "0xC0",           // Default to left
+        };
+
+        self.run(&[
+            "click",
+            "--repeat",
+            &count.max(1).to_string(),
+            "--next-delay",
+            &MULTI_CLICK_DELAY_MS.to_string(),
+            button_code,
+        ])
+        .await
+    }
+
+    async fn scroll(&self, x: i32, y: i32, dx: i32, dy: i32) -> anyhow::Result<()> {
+        let dx = dx.clamp(-MAX_SCROLL_NOTCHES, MAX_SCROLL_NOTCHES);
+        let dy = dy.clamp(-MAX_SCROLL_NOTCHES, MAX_SCROLL_NOTCHES);
+
+        // Move mouse to position
+        self.move_to(x, y).await?;
+
+        // Small delay to ensure move completes
+        tokio::time::sleep(std::time::Duration::from_millis(self.config.timing.click_settle_ms)).await;
+
+        if dx == 0 && dy == 0 {
+            return Ok(());
+        }
+
+        // ydotool --wheel emits REL_HWHEEL/REL_WHEEL, where positive REL_WHEEL scrolls up,
+        // so the vertical delta is negated to make positive dy scroll down
+        self.run(&["mousemove", "--wheel", "-x", &dx.to_string(), "-y", &(-dy).to_string()]).await
+    }
+
+    async fn drag(&self, from_x: i32, from_y: i32, to_x: i32, to_y: i32, button: &str) -> anyhow::Result<()> {
+        let button_code = button_code(button);
+
+        self.move_to(from_x, from_y).await?;
+        tokio::time::sleep(std::time::Duration::from_millis(self.config.timing.click_settle_ms)).await;
+
+        self.run(&["key", &format!("{}:1", button_code)]).await?;
+
+        // Interpolate towards the target, always ending exactly on it
+        let mut move_result = Ok(());
+        for step in 1..=DRAG_STEPS {
+            tokio::time::sleep(std::time::Duration::from_millis(DRAG_STEP_DELAY_MS)).await;
+            let x = from_x + (to_x - from_x) * step / DRAG_STEPS;
+            let y = from_y + (to_y - from_y) * step / DRAG_STEPS;
+            if let Err(e) = self.move_to(x, y).await {
+                move_result = Err(e);
+                break;
+            }
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(DRAG_STEP_DELAY_MS)).await;
+
+        // Always release the button, even if a move failed, so it isn't left held
+        let release_result = self.run(&["key", &format!("{}:0", button_code)]).await;
+
+        move_result?;
+        release_result
+    }
+
+    async fn button_up(&self, button: &str) -> anyhow::Result<()> {
+        self.run(&["key", &format!("{}:0", button_code(button))]).await
+    }
+
+    async fn type_text(&self, text: &str, delay_ms: u32) -> anyhow::Result<()> {
+        self.run(&["type", "--key-delay", &delay_ms.to_string(), "--", text]).await
+    }
+
+    async fn key_press(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()> {
+        // Build the key string with modifiers
+        // ydotool key format: key[:state] where state is 1 for down, 0 for up, or omit for press
+        // For modifiers, we need to press them down, press the key, then release modifiers
+
+        // Delay before starting key press to ensure system is ready
+        tokio::time::sleep(std::time::Duration::from_millis(self.config.timing.key_press_delay_ms)).await;
+
+        // Map common key names to ydotool key codes
+        let key_code = map_key_to_code(key);
+        let (locks, modifiers) = split_lock_modifiers(&with_shift(key, modifiers));
+        let lock_taps: Vec<String> = locks
+            .iter()
+            .flat_map(|lock| {
+                let code = map_key_to_code(lock);
+                [format!("{}:1", code), format!("{}:0", code)]
+            })
+            .collect();
+
+        let mut args: Vec<String> = vec!["key".to_string()];
+
+        // Tap lock keys to toggle them before the key
+        args.extend(lock_taps.iter().cloned());
+
+        // Press modifiers down
+        for modifier in &modifiers {
+            let mod_code = map_modifier_to_code(modifier);
+            args.push(format!("{}:1", mod_code)); // Press down
+        }
+
+        // Explicitly press down and release the main key with delay between
+        args.push(format!("{}:1", key_code)); // Key down
+        args.push(self.config.timing.key_hold_ms.to_string()); // Hold delay (ydotool interprets non-keycode values as delays)
+        args.push(format!("{}:0", key_code)); // Key up
+
+        // Release modifiers (in reverse order)
+        for modifier in modifiers.iter().rev() {
+            let mod_code = map_modifier_to_code(modifier);
+            args.push(format!("{}:0", mod_code)); // Release
+        }
+
+        // Toggle lock keys back to their original state
+        args.extend(lock_taps);
+
+        self.run(&args).await
+    }
+
+    async fn key_down(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()> {
+        self.set_key_states(&key_codes(key, modifiers), true).await
+    }
+
+    async fn key_up(&self, key: &str, modifiers: &[String]) -> anyhow::Result<()> {
+        let mut codes = key_codes(key, modifiers);
+        codes.reverse();
+        self.set_key_states(&codes, false).await
+    }
+
+    fn key_codes(&self, key: &str, modifiers: &[String]) -> Vec<String> {
+        key_codes(key, modifiers)
+    }
+
+    async fn health_check(&self) -> anyhow::Result<String> {
+        let binary = find_binary(&self.config.ydotool_path)
+            .ok_or_else(|| anyhow::anyhow!("ydotool not found at '{}'", self.config.ydotool_path))?;
+
+        let socket = self.socket_path();
+        probe_ydotoold(&socket).map_err(|e| anyhow::anyhow!("{}. {}", e.reason, e.suggestion))?;
+
+        Ok(format!("{} found; ydotoold is listening at {}", binary.display(), socket.display()))
+    }
+}
@@ -3,11 +3,107 @@
 //! This MCP server enables AI assistants to interact with windows on Linux desktops,
 //! supporting both X11 (including XWayland) and native Wayland environments.
 
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::Request;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 use marionette::server::MarionetteServer;
 use rmcp::ServiceExt;
 use rmcp::transport::io::stdio;
+use rmcp::transport::streamable_http_server::{
+    StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Address the HTTP transport binds to unless `--bind` is given
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:8080";
+
+/// Path the MCP endpoint is served under
+const HTTP_ENDPOINT: &str = "/mcp";
+
+const USAGE: &str = "\
+Usage: marionette [OPTIONS]
+
+Options:
+  --transport <stdio|http>  Transport to serve MCP over (default: stdio, env: MARIONETTE_TRANSPORT).
+                            `sse` is accepted as an alias for `http`
+  --bind <ADDR>             Address for the HTTP transport (default: 127.0.0.1:8080, env: MARIONETTE_BIND)
+  --allow-remote            Allow binding the HTTP transport to a non-loopback address
+  -h, --help                Print this help";
+
+/// Transport the server is exposed over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    /// JSON-RPC over stdin/stdout, for locally spawned servers
+    Stdio,
+    /// Streamable HTTP (with SSE streaming), for remote clients
+    Http,
+}
+
+impl Transport {
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        match name.to_lowercase().as_str() {
+            "stdio" => Ok(Self::Stdio),
+            "http" | "sse" => Ok(Self::Http),
+            other => anyhow::bail!("Unknown transport: {} (expected stdio or http)", other),
+        }
+    }
+}
+
+/// Command-line options, with environment variable fallbacks
+#[derive(Debug)]
+struct Args {
+    transport: Transport,
+    bind: SocketAddr,
+    allow_remote: bool,
+}
+
+impl Args {
+    fn parse() -> anyhow::Result<Self> {
+        let mut transport = std::env::var("MARIONETTE_TRANSPORT").ok();
+        let mut bind = std::env::var("MARIONETTE_BIND").ok();
+        let mut allow_remote = false;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = |name: &str| {
+                inline.clone().or_else(|| args.next()).ok_or_else(|| anyhow::anyhow!("{} requires a value", name))
+            };
+
+            match flag.as_str() {
+                "--transport" => transport = Some(value("--transport")?),
+                "--bind" => bind = Some(value("--bind")?),
+                "--allow-remote" => allow_remote = true,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                other => anyhow::bail!("Unknown argument: {}\n\n{}", other, USAGE),
+            }
+        }
+
+        let transport = match transport {
+            Some(name) => Transport::parse(&name)?,
+            None => Transport::Stdio,
+        };
+        let bind = bind
+            .as_deref()
+            .unwrap_or(DEFAULT_BIND_ADDRESS)
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid bind address: {}", e))?;
+
+        Ok(Self { transport, bind, allow_remote })
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize tracing (stderr to keep stdout clean for MCP protocol)
@@ -19,21 +115,193 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
+    let args = Args::parse()?;
+
+    // Anyone who can reach the HTTP endpoint can drive the desktop
+    if args.transport == Transport::Http && !args.bind.ip().is_loopback() && !args.allow_remote {
+        anyhow::bail!(
+            "Refusing to bind to non-loopback address {} without --allow-remote",
+            args.bind
+        );
+    }
+
     tracing::info!("Starting Marionette MCP Server");
 
     // Create the server
     let server = MarionetteServer::new().await?;
 
-    // Run with stdio transport
+    let served = match args.transport {
+        Transport::Stdio => serve_stdio(server.clone()).await,
+        Transport::Http => serve_http(server.clone(), args.bind).await,
+    };
+
+    tracing::info!("Marionette MCP Server shutting down");
+    // Don't leave keys or buttons stuck down, however serving ended
+    server.release_pressed().await;
+    served
+}
+
+/// Wait for SIGINT or SIGTERM
+async fn shutdown_signal() {
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => tracing::info!("Received SIGINT"),
+        _ = terminate => tracing::info!("Received SIGTERM"),
+    }
+}
+
+/// Serve a single client over stdin/stdout until it disconnects
+async fn serve_stdio(server: MarionetteServer) -> anyhow::Result<()> {
     let transport = stdio();
 
     tracing::info!("Marionette MCP Server ready, listening on stdio");
 
     let service = server.serve(transport).await?;
 
+    // Stop serving on a signal too, so the caller can clean up before exiting
+    let cancellation = service.cancellation_token();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        cancellation.cancel();
+    });
+
     // Wait for graceful shutdown
     service.waiting().await?;
 
-    tracing::info!("Marionette MCP Server shutting down");
     Ok(())
 }
+
+/// Serve clients over streamable HTTP until interrupted
+///
+/// Every session shares the same server, so refs stay consistent across clients.
+async fn serve_http(server: MarionetteServer, bind: SocketAddr) -> anyhow::Result<()> {
+    let config = StreamableHttpServerConfig::default();
+    let cancellation = config.cancellation_token.clone();
+
+    let service = StreamableHttpService::new(
+        move || Ok(server.clone()),
+        Arc::new(LocalSessionManager::default()),
+        config,
+    );
+    let mut router = axum::Router::new().nest_service(HTTP_ENDPOINT, service);
+
+    match std::env::var("MARIONETTE_AUTH_TOKEN") {
+        Ok(token) if token.is_empty() => anyhow::bail!("MARIONETTE_AUTH_TOKEN is set but empty"),
+        Ok(token) => {
+            let token: Arc<str> = token.into();
+            router = router.layer(axum::middleware::from_fn(move |request, next| {
+                require_bearer_token(token.clone(), request, next)
+            }));
+            tracing::info!("HTTP transport requires a bearer token");
+        }
+        Err(_) => {
+            if !bind.ip().is_loopback() {
+                tracing::warn!("HTTP transport has no MARIONETTE_AUTH_TOKEN; any client that can connect can control the desktop");
+            }
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    if !bind.ip().is_loopback() {
+        tracing::warn!("HTTP transport is reachable from the network at {}", bind);
+    }
+
+    tracing::info!("Marionette MCP Server ready, listening on http://{}{}", listener.local_addr()?, HTTP_ENDPOINT);
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            cancellation.cancel();
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Reject requests whose `Authorization: Bearer` token doesn't match
+async fn require_bearer_token(token: Arc<str>, request: Request, next: Next) -> Response {
+    if !has_bearer_token(request.headers(), &token) {
+        tracing::warn!("Rejected HTTP request without a valid bearer token");
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Missing or invalid bearer token",
+        ).into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Whether the `Authorization` header carries `Bearer <token>`
+fn has_bearer_token(headers: &HeaderMap, token: &str) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|provided| constant_time_eq(provided.trim().as_bytes(), token.as_bytes()))
+}
+
+/// Compare two byte strings without short-circuiting on the first mismatch,
+/// so response timing doesn't reveal how much of a guessed token is correct
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(authorization: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = authorization {
+            headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn accepts_correct_token() {
+        assert!(has_bearer_token(&headers(Some("Bearer s3cret")), "s3cret"));
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        assert!(!has_bearer_token(&headers(None), "s3cret"));
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        assert!(!has_bearer_token(&headers(Some("Basic s3cret")), "s3cret"));
+        assert!(!has_bearer_token(&headers(Some("s3cret")), "s3cret"));
+    }
+
+    #[test]
+    fn rejects_wrong_token() {
+        assert!(!has_bearer_token(&headers(Some("Bearer s3crex")), "s3cret"));
+        assert!(!has_bearer_token(&headers(Some("Bearer s3cre")), "s3cret"));
+        assert!(!has_bearer_token(&headers(Some("Bearer ")), "s3cret"));
+    }
+
+    #[test]
+    fn constant_time_eq_compares_length_and_content() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"token", b"tokens"));
+        assert!(!constant_time_eq(b"token", b"tokeN"));
+    }
+}
@@ -0,0 +1,200 @@
+//! Screenshot files written by the "file" output format
+//!
+//! Captures go to `MARIONETTE_SCREENSHOT_DIR`, or the system temp directory by
+//! default. Every capture is followed by a cleanup pass that deletes the oldest
+//! Marionette screenshots (by modification time) once the directory holds too
+//! many or too large a total, so long sessions don't fill `/tmp`.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+/// Filename prefix of every screenshot file, so cleanup never touches other files
+pub const FILE_PREFIX: &str = "marionette_";
+
+/// Extensions written for each [`super::ImageEncoding`]
+const EXTENSIONS: &[&str] = &["png", "jpg", "webp"];
+
+/// Most screenshot files kept before the oldest are deleted
+const MAX_FILES: usize = 100;
+
+/// Most total bytes of screenshot files kept before the oldest are deleted
+const MAX_TOTAL_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Captures saved so far, so two in the same instant still get distinct names
+static FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Directory screenshots are saved to: `MARIONETTE_SCREENSHOT_DIR`, or the temp directory
+///
+/// The directory is created if it doesn't exist yet.
+pub fn screenshot_dir_from_env() -> PathBuf {
+    let Some(dir) = std::env::var_os("MARIONETTE_SCREENSHOT_DIR").filter(|dir| !dir.is_empty()) else {
+        return std::env::temp_dir();
+    };
+
+    let dir = PathBuf::from(dir);
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::warn!("Failed to create screenshot directory {}: {}", dir.display(), e);
+    }
+    dir
+}
+
+/// Path for a new screenshot of `name` in `dir`
+///
+/// The name carries a nanosecond timestamp and a per-process counter, so
+/// captures taken within the same second don't overwrite each other.
+pub fn file_path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or(0);
+    let counter = FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    dir.join(format!("{}{}_{}_{}.{}", FILE_PREFIX, name, nanos, counter, extension))
+}
+
+/// Delete the oldest screenshots in `dir` until it is within the file count and size limits
+///
+/// `keep` (the file just written) is never deleted. Failures are logged rather
+/// than returned, since the capture itself already succeeded.
+pub fn cleanup(dir: &Path, keep: &Path) {
+    cleanup_with_limits(dir, keep, MAX_FILES, MAX_TOTAL_BYTES);
+}
+
+/// [`cleanup`] with explicit limits
+fn cleanup_with_limits(dir: &Path, keep: &Path, max_files: usize, max_total_bytes: u64) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::debug!("Failed to read screenshot directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    let mut files: Vec<(PathBuf, SystemTime, u64)> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| is_screenshot(&entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((entry.path(), modified, metadata.len()))
+        })
+        .collect();
+    // Oldest first
+    files.sort_by_key(|(_, modified, _)| *modified);
+
+    let mut count = files.len();
+    let mut total_bytes: u64 = files.iter().map(|(_, _, size)| size).sum();
+    for (path, _, size) in files {
+        if count <= max_files && total_bytes <= max_total_bytes {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                tracing::debug!("Deleted old screenshot {}", path.display());
+                count -= 1;
+                total_bytes = total_bytes.saturating_sub(size);
+            }
+            Err(e) => tracing::debug!("Failed to delete old screenshot {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Whether a path looks like a screenshot Marionette wrote
+fn is_screenshot(path: &Path) -> bool {
+    let has_prefix = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(FILE_PREFIX));
+    let has_extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension));
+    has_prefix && has_extension
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// A fresh, empty directory under the system temp directory
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("marionette-files-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write `files` in order, each with a modification time a second after the last
+    fn write_files(dir: &Path, files: &[(&str, usize)]) {
+        let start = SystemTime::now() - Duration::from_secs(files.len() as u64 + 1);
+        for (i, (name, size)) in files.iter().enumerate() {
+            let path = dir.join(name);
+            std::fs::write(&path, vec![0u8; *size]).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(start + Duration::from_secs(i as u64)).unwrap();
+        }
+    }
+
+    fn remaining(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn file_paths_are_unique_within_a_second() {
+        let dir = Path::new("/screenshots");
+        let first = file_path(dir, "w0", "png");
+        let second = file_path(dir, "w0", "png");
+        assert_ne!(first, second);
+        assert!(is_screenshot(&first));
+    }
+
+    #[test]
+    fn count_limit_deletes_oldest_first() {
+        let dir = test_dir("count");
+        write_files(&dir, &[("marionette_a.png", 1), ("marionette_b.png", 1), ("marionette_c.png", 1)]);
+        cleanup_with_limits(&dir, &dir.join("marionette_c.png"), 2, u64::MAX);
+        assert_eq!(remaining(&dir), vec!["marionette_b.png", "marionette_c.png"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn size_limit_deletes_until_under_budget() {
+        let dir = test_dir("size");
+        write_files(&dir, &[("marionette_a.png", 10), ("marionette_b.jpg", 10), ("marionette_c.webp", 10)]);
+        cleanup_with_limits(&dir, &dir.join("marionette_c.webp"), usize::MAX, 15);
+        assert_eq!(remaining(&dir), vec!["marionette_c.webp"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn other_files_are_never_deleted() {
+        let dir = test_dir("filter");
+        write_files(&dir, &[
+            ("notes.png", 1),
+            ("marionette_a.txt", 1),
+            ("marionette_b.png", 1),
+            ("marionette_c.png", 1),
+        ]);
+        cleanup_with_limits(&dir, &dir.join("marionette_c.png"), 0, 0);
+        assert_eq!(remaining(&dir), vec!["marionette_a.txt", "marionette_c.png", "notes.png"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn kept_file_survives_even_when_oldest() {
+        let dir = test_dir("keep");
+        write_files(&dir, &[("marionette_a.png", 1), ("marionette_b.png", 1)]);
+        cleanup_with_limits(&dir, &dir.join("marionette_a.png"), 1, u64::MAX);
+        assert_eq!(remaining(&dir), vec!["marionette_a.png"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
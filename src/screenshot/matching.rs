@@ -0,0 +1,228 @@
+//! Template matching to locate UI elements in a capture
+//!
+//! Uses zero-mean normalized cross-correlation, so scores range from -1 to 1
+//! and are insensitive to uniform brightness and contrast changes. Large
+//! searches run coarse-to-fine: the best match is found on downscaled copies,
+//! then refined at full resolution around it.
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, RgbaImage};
+
+use crate::core::registry::{Geometry, PlatformWindowId};
+
+/// Templates are downscaled until their shorter side is about this many pixels
+/// for the coarse pass
+const COARSE_TEMPLATE_SIZE: u32 = 16;
+
+/// Best match of a template within an image
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateMatch {
+    /// Top-left corner of the match
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Normalized cross-correlation, from -1 to 1 (1 is a perfect match)
+    pub score: f32,
+}
+
+/// Capture a window and find the best match of an encoded template image in it
+///
+/// Coordinates in the result are window-relative.
+pub async fn find_in_window(
+    platform_id: &PlatformWindowId,
+    geometry: &Geometry,
+    template: &[u8],
+) -> anyhow::Result<Option<TemplateMatch>> {
+    let template = image::load_from_memory(template)
+        .map_err(|e| anyhow::anyhow!("Template is not a valid image: {}", e))?
+        .into_rgba8();
+    let capture = super::capture_window_image(platform_id, geometry).await?;
+
+    // Matching is CPU-bound, keep it off the async runtime
+    let found = tokio::task::spawn_blocking(move || find_template(&capture, &template)).await?;
+
+    Ok(found)
+}
+
+/// Find the position in `haystack` that best matches `template`
+///
+/// Returns `None` if the template is larger than the haystack. The match is
+/// returned regardless of score; callers apply their own threshold.
+pub fn find_template(haystack: &RgbaImage, template: &RgbaImage) -> Option<TemplateMatch> {
+    let haystack = imageops::grayscale(haystack);
+    let template = imageops::grayscale(template);
+    let (width, height) = template.dimensions();
+
+    if width == 0 || height == 0 || width > haystack.width() || height > haystack.height() {
+        return None;
+    }
+
+    let scale = (width.min(height) / COARSE_TEMPLATE_SIZE).max(1);
+
+    let (x, y, score) = if scale == 1 {
+        search(&haystack, &template, 0..=haystack.width() - width, 0..=haystack.height() - height)
+    } else {
+        let small_haystack = imageops::resize(&haystack, haystack.width() / scale, haystack.height() / scale, FilterType::Triangle);
+        let small_template = imageops::resize(&template, width / scale, height / scale, FilterType::Triangle);
+        let (coarse_x, coarse_y, _) = search(
+            &small_haystack,
+            &small_template,
+            0..=small_haystack.width() - small_template.width(),
+            0..=small_haystack.height() - small_template.height(),
+        );
+
+        // Refine within one coarse pixel of the coarse hit
+        let max_x = haystack.width() - width;
+        let max_y = haystack.height() - height;
+        let around = |coarse: u32, max: u32| {
+            let center = coarse * scale;
+            center.saturating_sub(scale).min(max)..=(center + scale).min(max)
+        };
+        search(&haystack, &template, around(coarse_x, max_x), around(coarse_y, max_y))
+    };
+
+    Some(TemplateMatch { x, y, width, height, score })
+}
+
+/// Exhaustively score every top-left position in the given ranges
+fn search(
+    haystack: &GrayImage,
+    template: &GrayImage,
+    xs: std::ops::RangeInclusive<u32>,
+    ys: std::ops::RangeInclusive<u32>,
+) -> (u32, u32, f32) {
+    let (width, height) = template.dimensions();
+    let count = (width * height) as f64;
+
+    // Zero-mean template, so the numerator only needs the raw haystack pixels
+    let mean = template.pixels().map(|p| p[0] as f64).sum::<f64>() / count;
+    let centered: Vec<f64> = template.pixels().map(|p| p[0] as f64 - mean).collect();
+    let template_norm = centered.iter().map(|v| v * v).sum::<f64>().sqrt();
+
+    let integral = Integral::new(haystack);
+    let stride = haystack.width() as usize;
+    let pixels = haystack.as_raw();
+
+    let mut best = (*xs.start(), *ys.start(), f32::MIN);
+    for y in ys {
+        for x in xs.clone() {
+            let (sum, sum_sq) = integral.sums(x, y, width, height);
+            let variance = sum_sq - sum * sum / count;
+
+            let score = if variance <= f64::EPSILON || template_norm <= f64::EPSILON {
+                0.0
+            } else {
+                let mut numerator = 0.0;
+                for ty in 0..height as usize {
+                    let row = (y as usize + ty) * stride + x as usize;
+                    let template_row = &centered[ty * width as usize..(ty + 1) * width as usize];
+                    for (pixel, weight) in pixels[row..row + width as usize].iter().zip(template_row) {
+                        numerator += *pixel as f64 * weight;
+                    }
+                }
+                (numerator / (variance.sqrt() * template_norm)) as f32
+            };
+
+            if score > best.2 {
+                best = (x, y, score);
+            }
+        }
+    }
+
+    best
+}
+
+/// Summed-area tables of pixel values and their squares
+struct Integral {
+    stride: usize,
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl Integral {
+    fn new(image: &GrayImage) -> Self {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let stride = width + 1;
+        let mut sum = vec![0.0; stride * (height + 1)];
+        let mut sum_sq = vec![0.0; stride * (height + 1)];
+
+        for y in 0..height {
+            let mut row_sum = 0.0;
+            let mut row_sum_sq = 0.0;
+            for x in 0..width {
+                let value = image.as_raw()[y * width + x] as f64;
+                row_sum += value;
+                row_sum_sq += value * value;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row_sum;
+                sum_sq[(y + 1) * stride + x + 1] = sum_sq[y * stride + x + 1] + row_sum_sq;
+            }
+        }
+
+        Self { stride, sum, sum_sq }
+    }
+
+    /// Sum and sum of squares over a rectangle
+    fn sums(&self, x: u32, y: u32, width: u32, height: u32) -> (f64, f64) {
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = (x0 + width as usize, y0 + height as usize);
+        let area = |table: &[f64]| {
+            table[y1 * self.stride + x1] - table[y0 * self.stride + x1] - table[y1 * self.stride + x0]
+                + table[y0 * self.stride + x0]
+        };
+        (area(&self.sum), area(&self.sum_sq))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic noise, so every region of the image looks different
+    fn noise(width: u32, height: u32, seed: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let mut h = x.wrapping_mul(374_761_393) ^ y.wrapping_mul(668_265_263) ^ seed.wrapping_mul(2_246_822_519);
+            h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+            let v = (h >> 24) as u8;
+            image::Rgba([v, v, v, 255])
+        })
+    }
+
+    #[test]
+    fn finds_small_template_exactly() {
+        let haystack = noise(64, 48, 1);
+        let template = imageops::crop_imm(&haystack, 21, 13, 10, 8).to_image();
+
+        let found = find_template(&haystack, &template).unwrap();
+        assert_eq!((found.x, found.y, found.width, found.height), (21, 13, 10, 8));
+        assert!(found.score > 0.99, "score {}", found.score);
+    }
+
+    #[test]
+    fn finds_large_template_through_coarse_pass() {
+        let haystack = noise(160, 120, 2);
+        let template = imageops::crop_imm(&haystack, 70, 45, 40, 36).to_image();
+        assert!(template.width().min(template.height()) / COARSE_TEMPLATE_SIZE > 1, "must take the coarse path");
+
+        let found = find_template(&haystack, &template).unwrap();
+        assert_eq!((found.x, found.y), (70, 45));
+        assert!(found.score > 0.99, "score {}", found.score);
+    }
+
+    #[test]
+    fn unrelated_template_scores_low() {
+        let haystack = noise(64, 48, 3);
+        let template = noise(12, 12, 4);
+
+        let found = find_template(&haystack, &template).unwrap();
+        assert!(found.score < 0.8, "score {}", found.score);
+    }
+
+    #[test]
+    fn template_larger_than_haystack_is_none() {
+        let haystack = noise(20, 20, 5);
+        assert!(find_template(&haystack, &noise(21, 10, 6)).is_none());
+        assert!(find_template(&haystack, &noise(10, 21, 6)).is_none());
+        assert!(find_template(&haystack, &RgbaImage::new(0, 0)).is_none());
+    }
+}
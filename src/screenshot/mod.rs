@@ -2,89 +2,616 @@
 //!
 //! This module provides cross-platform screenshot capabilities using the xcap crate,
 //! which handles both X11 and Wayland (via portal) transparently.
+//!
+//! Per-window capture is only available for X11/XWayland windows. Native Wayland
+//! windows are captured by cropping their geometry out of a monitor capture, which
+//! xcap performs through wlr-screencopy on wlroots compositors and the XDG desktop
+//! portal elsewhere.
+
+pub mod files;
+pub mod matching;
+pub mod monitors;
+pub mod ocr;
+pub mod store;
+
+use crate::core::geometry::{intersection, union};
+use crate::core::registry::{Geometry, PlatformWindowId};
+use image::{ImageEncoder, RgbaImage};
+
+/// Output encoding for captured images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageEncoding {
+    /// Lossless PNG (default)
+    Png,
+    /// Lossy JPEG at the given quality (1-100)
+    Jpeg { quality: u8 },
+    /// Lossless WebP
+    Webp,
+}
+
+impl ImageEncoding {
+    /// Parse an encoding name as used by the screenshot tools
+    pub fn parse(name: &str, quality: u8) -> anyhow::Result<Self> {
+        match name.to_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "jpeg" | "jpg" => Ok(Self::Jpeg { quality: quality.clamp(1, 100) }),
+            "webp" => Ok(Self::Webp),
+            other => anyhow::bail!("Unknown image encoding: {} (expected png, jpeg, or webp)", other),
+        }
+    }
+
+    /// MIME type for the encoded data
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg { .. } => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
 
-use crate::core::registry::PlatformWindowId;
-use image::ImageEncoder;
+    /// File extension for the encoded data
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg { .. } => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// Options applied to every capture before it is returned
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureOptions {
+    /// Output encoding
+    pub encoding: ImageEncoding,
+    /// Downscale so the width does not exceed this, preserving aspect ratio
+    pub max_width: Option<u32>,
+    /// Downscale so the height does not exceed this, preserving aspect ratio
+    pub max_height: Option<u32>,
+    /// Draw a cursor marker at this position, relative to the captured image
+    pub cursor: Option<(i32, i32)>,
+    /// Crop to this rectangle, relative to the captured image, clamped to its bounds
+    pub crop: Option<Geometry>,
+}
+
+/// An encoded capture and its final dimensions
+#[derive(Debug, Clone)]
+pub struct CapturedImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
 
 /// Capture a screenshot of a specific window
-pub async fn capture_window(platform_id: &PlatformWindowId) -> anyhow::Result<Vec<u8>> {
+///
+/// `geometry` is used to crop the window out of a monitor capture when per-window
+/// capture isn't available (native Wayland windows, or xcap failing to find it).
+pub async fn capture_window(platform_id: &PlatformWindowId, geometry: &Geometry, options: CaptureOptions) -> anyhow::Result<CapturedImage> {
     // xcap is not async, so we run it in a blocking task
     let platform_id = platform_id.clone();
+    let geometry = *geometry;
 
     let result = tokio::task::spawn_blocking(move || {
-        capture_window_blocking(&platform_id)
+        capture_window_blocking(&platform_id, &geometry, options)
     }).await??;
 
     Ok(result)
 }
 
-fn capture_window_blocking(platform_id: &PlatformWindowId) -> anyhow::Result<Vec<u8>> {
-    let PlatformWindowId::X11(window_id) = platform_id else {
-        anyhow::bail!("Only X11 windows are currently supported for screenshots");
+/// Capture a window as raw pixels, without any capture options applied
+pub async fn capture_window_image(platform_id: &PlatformWindowId, geometry: &Geometry) -> anyhow::Result<RgbaImage> {
+    let platform_id = platform_id.clone();
+    let geometry = *geometry;
+
+    let image = tokio::task::spawn_blocking(move || {
+        grab_window(&platform_id, &geometry)
+    }).await??;
+
+    Ok(image)
+}
+
+fn capture_window_blocking(platform_id: &PlatformWindowId, geometry: &Geometry, options: CaptureOptions) -> anyhow::Result<CapturedImage> {
+    let image = grab_window(platform_id, geometry)?;
+
+    finish(image, options)
+}
+
+fn grab_window(platform_id: &PlatformWindowId, geometry: &Geometry) -> anyhow::Result<RgbaImage> {
+    let image = match platform_id {
+        PlatformWindowId::X11(window_id) => match capture_x11_window(*window_id) {
+            Ok(image) => image,
+            Err(e) => {
+                tracing::debug!("Per-window capture failed, falling back to region capture: {}", e);
+                crop_region(geometry.x, geometry.y, geometry.width, geometry.height, None)?
+            }
+        },
+        // No per-window capture on Wayland; crop the window out of its monitor
+        PlatformWindowId::Wayland(_) => {
+            crop_region(geometry.x, geometry.y, geometry.width, geometry.height, None)?
+        }
     };
 
+    Ok(image)
+}
+
+fn capture_x11_window(window_id: u32) -> anyhow::Result<RgbaImage> {
     // Get all windows and find the one with matching ID
     let windows = xcap::Window::all()?;
 
     let window = windows
         .into_iter()
-        .find(|w| w.id().ok() == Some(*window_id))
+        .find(|w| w.id().ok() == Some(window_id))
         .ok_or_else(|| anyhow::anyhow!("Window not found for screenshot"))?;
 
     // Capture the window
-    let image = window.capture_image()?;
+    Ok(window.capture_image()?)
+}
 
-    // Encode as PNG
-    let mut buffer = Vec::new();
-    let encoder = image::codecs::png::PngEncoder::new(&mut buffer);
-    encoder.write_image(
-        image.as_raw(),
-        image.width(),
-        image.height(),
-        image::ExtendedColorType::Rgba8,
-    )?;
+/// Capture a whole monitor
+///
+/// `monitor` selects by index (e.g., "0") or name (e.g., "DP-1"); the primary
+/// monitor is used when omitted.
+pub async fn capture_monitor(monitor: Option<String>, options: CaptureOptions) -> anyhow::Result<CapturedImage> {
+    let result = tokio::task::spawn_blocking(move || {
+        capture_monitor_blocking(monitor.as_deref(), options)
+    }).await??;
 
-    Ok(buffer)
+    Ok(result)
+}
+
+fn capture_monitor_blocking(selector: Option<&str>, options: CaptureOptions) -> anyhow::Result<CapturedImage> {
+    let monitor = select_monitor(selector)?;
+    let image = monitor.capture_image()?;
+
+    finish(image, options)
+}
+
+/// Find a monitor by index or name, defaulting to the primary monitor
+fn select_monitor(selector: Option<&str>) -> anyhow::Result<xcap::Monitor> {
+    let monitors = xcap::Monitor::all()?;
+
+    let found = match selector {
+        Some(selector) => match selector.parse::<usize>() {
+            Ok(index) => monitors.into_iter().nth(index),
+            Err(_) => monitors
+                .into_iter()
+                .find(|m| m.name().is_ok_and(|name| name == selector)),
+        },
+        None => {
+            let primary = monitors.iter().position(|m| m.is_primary().unwrap_or(false));
+            monitors.into_iter().nth(primary.unwrap_or(0))
+        }
+    };
+
+    found.ok_or_else(|| match selector {
+        Some(selector) => anyhow::anyhow!("Monitor not found: {}", selector),
+        None => anyhow::anyhow!("No monitors found"),
+    })
 }
 
 /// Capture a region of the screen
-#[allow(dead_code)]
-pub async fn capture_region(x: i32, y: i32, width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+///
+/// Coordinates are in the global screen space. A region spanning several
+/// monitors is stitched together from each of them; parts that no monitor
+/// covers are left transparent. With a `monitor` selector (index or name),
+/// only that monitor is captured and the region is clamped to it.
+pub async fn capture_region(
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    monitor: Option<String>,
+    options: CaptureOptions,
+) -> anyhow::Result<CapturedImage> {
     let result = tokio::task::spawn_blocking(move || {
-        capture_region_blocking(x, y, width, height)
+        capture_region_blocking(x, y, width, height, monitor.as_deref(), options)
     }).await??;
 
     Ok(result)
 }
 
-fn capture_region_blocking(x: i32, y: i32, width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
-    // Capture the primary monitor
-    let monitors = xcap::Monitor::all()?;
-    let monitor = monitors
-        .into_iter()
-        .next()
-        .ok_or_else(|| anyhow::anyhow!("No monitors found"))?;
+fn capture_region_blocking(
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    monitor: Option<&str>,
+    options: CaptureOptions,
+) -> anyhow::Result<CapturedImage> {
+    let image = crop_region(x, y, width, height, monitor)?;
 
-    let full_image = monitor.capture_image()?;
+    finish(image, options)
+}
+
+/// Capture a region from whichever monitors it overlaps
+///
+/// The region is trimmed to the monitors it overlaps, so an oversized request
+/// never allocates more than the monitors cover.
+fn crop_region(x: i32, y: i32, width: u32, height: u32, monitor: Option<&str>) -> anyhow::Result<RgbaImage> {
+    let region = Geometry { x, y, width, height };
+
+    if let Some(selector) = monitor {
+        let monitor = select_monitor(Some(selector))?;
+        let image = monitor.capture_image()?;
+        return crop_monitor_part(&image, &monitor_bounds(&monitor)?, &region)
+            .map_err(|_| anyhow::anyhow!("Region lies outside monitor {}", selector));
+    }
+
+    let mut overlapping = Vec::new();
+    for monitor in xcap::Monitor::all()? {
+        let bounds = monitor_bounds(&monitor)?;
+        if let Some(part) = intersection(&region, &bounds) {
+            overlapping.push((monitor, bounds, part));
+        }
+    }
 
-    // Crop to region
-    let cropped = image::imageops::crop_imm(
-        &full_image,
-        x.max(0) as u32,
-        y.max(0) as u32,
-        width,
-        height,
-    ).to_image();
+    match overlapping.as_slice() {
+        [] => anyhow::bail!("Region lies outside all monitors"),
+        // The common case needs no compositing
+        [(monitor, bounds, _)] => crop_monitor_part(&monitor.capture_image()?, bounds, &region),
+        [(_, _, first), rest @ ..] => {
+            // Canvas in logical pixels, covering only what the monitors show; gaps
+            // between monitors inside it stay transparent
+            let covered = rest.iter().fold(*first, |covered, (_, _, part)| union(&covered, part));
+            let mut canvas = RgbaImage::new(covered.width, covered.height);
+            for (monitor, bounds, part) in &overlapping {
+                let image = monitor.capture_image()?;
+                let Ok(mut pixels) = crop_monitor_part(&image, bounds, &region) else {
+                    continue;
+                };
+                // Scaled monitors capture more pixels than their logical size
+                if (pixels.width(), pixels.height()) != (part.width, part.height) {
+                    pixels = image::imageops::resize(&pixels, part.width, part.height, image::imageops::FilterType::Triangle);
+                }
+                let offset_x = (part.x as i64) - covered.x as i64;
+                let offset_y = (part.y as i64) - covered.y as i64;
+                image::imageops::replace(&mut canvas, &pixels, offset_x, offset_y);
+            }
+            Ok(canvas)
+        }
+    }
+}
+
+/// A monitor's bounds in logical screen coordinates
+fn monitor_bounds(monitor: &xcap::Monitor) -> anyhow::Result<Geometry> {
+    Ok(Geometry {
+        x: monitor.x()?,
+        y: monitor.y()?,
+        width: monitor.width()?,
+        height: monitor.height()?,
+    })
+}
 
-    // Encode as PNG
+/// Crop the part of a screen-space region that lies on a monitor out of its capture
+///
+/// The capture can be larger than the monitor's logical size under scaling,
+/// so the part is scaled into the capture's pixels; the result keeps the
+/// capture's resolution.
+fn crop_monitor_part(image: &RgbaImage, bounds: &Geometry, region: &Geometry) -> anyhow::Result<RgbaImage> {
+    let part = intersection(region, bounds).ok_or_else(|| anyhow::anyhow!("Region lies outside the monitor"))?;
+    let scale_x = image.width() as f64 / bounds.width.max(1) as f64;
+    let scale_y = image.height() as f64 / bounds.height.max(1) as f64;
+
+    let pixels = Geometry {
+        x: ((part.x - bounds.x) as f64 * scale_x).round() as i32,
+        y: ((part.y - bounds.y) as f64 * scale_y).round() as i32,
+        width: ((part.width as f64 * scale_x).round() as u32).max(1),
+        height: ((part.height as f64 * scale_y).round() as u32).max(1),
+    };
+    crop_image(image, &pixels)
+}
+
+/// Check that screen capture works by grabbing a single pixel
+pub async fn health_check() -> anyhow::Result<String> {
+    let options = CaptureOptions {
+        encoding: ImageEncoding::Png,
+        max_width: None,
+        max_height: None,
+        cursor: None,
+        crop: None,
+    };
+    capture_region(0, 0, 1, 1, None, options).await?;
+    Ok("Captured a 1x1 region at the screen origin".to_string())
+}
+
+/// Apply capture options to a raw capture: crop, downscale, then encode
+fn finish(mut image: RgbaImage, options: CaptureOptions) -> anyhow::Result<CapturedImage> {
+    // Draw before cropping and downscaling so the marker lands on the exact pixel
+    if let Some((x, y)) = options.cursor {
+        draw_cursor_marker(&mut image, x, y);
+    }
+
+    if let Some(crop) = options.crop {
+        image = crop_image(&image, &crop)?;
+    }
+
+    let image = downscale(image, options.max_width, options.max_height);
+    let data = encode_image(&image, options.encoding)?;
+
+    Ok(CapturedImage {
+        data,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+/// Cut a rectangle out of an image, clamping it to the image bounds
+fn crop_image(image: &RgbaImage, region: &Geometry) -> anyhow::Result<RgbaImage> {
+    let x = region.x.clamp(0, image.width() as i32) as u32;
+    let y = region.y.clamp(0, image.height() as i32) as u32;
+    // Shrink by however much of the region lay before the image's leading edge
+    let width = (region.width as i64 - (x as i64 - region.x as i64)).clamp(0, (image.width() - x) as i64) as u32;
+    let height = (region.height as i64 - (y as i64 - region.y as i64)).clamp(0, (image.height() - y) as i64) as u32;
+
+    if width == 0 || height == 0 {
+        anyhow::bail!("Region lies outside the captured image");
+    }
+
+    Ok(image::imageops::crop_imm(image, x, y, width, height).to_image())
+}
+
+/// Pixels that differ between two captures of the same size
+#[derive(Debug, Clone, Copy)]
+pub struct ImageDiff {
+    pub changed_pixels: u64,
+    pub total_pixels: u64,
+    /// Smallest rectangle containing every changed pixel, or None if nothing changed
+    pub bounds: Option<Geometry>,
+}
+
+impl ImageDiff {
+    /// Share of pixels that changed, from 0 to 100
+    pub fn changed_percent(&self) -> f64 {
+        if self.total_pixels == 0 {
+            return 0.0;
+        }
+        self.changed_pixels as f64 * 100.0 / self.total_pixels as f64
+    }
+}
+
+/// Compare two images pixel by pixel
+///
+/// A pixel counts as changed when any channel differs by more than
+/// `tolerance`, which absorbs compression noise and subpixel rendering jitter.
+/// Returns None if the images differ in size.
+pub fn diff_images(before: &RgbaImage, after: &RgbaImage, tolerance: u8) -> Option<ImageDiff> {
+    if before.dimensions() != after.dimensions() {
+        return None;
+    }
+
+    let mut changed_pixels = 0;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+    for ((x, y, a), b) in before.enumerate_pixels().zip(after.pixels()) {
+        let changed = a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > tolerance);
+        if changed {
+            changed_pixels += 1;
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+
+    let bounds = (changed_pixels > 0).then(|| Geometry {
+        x: min_x as i32,
+        y: min_y as i32,
+        width: max_x - min_x + 1,
+        height: max_y - min_y + 1,
+    });
+
+    Some(ImageDiff {
+        changed_pixels,
+        total_pixels: before.width() as u64 * before.height() as u64,
+        bounds,
+    })
+}
+
+/// Most windows shown in one overview grid
+pub const MAX_OVERVIEW_WINDOWS: usize = 24;
+
+/// Largest thumbnail edge in an overview grid, in pixels
+pub const MAX_OVERVIEW_THUMBNAIL_SIZE: u32 = 480;
+
+/// Gap around each overview cell, in pixels
+const OVERVIEW_PADDING: u32 = 8;
+
+/// Each font pixel of an overview label is drawn as a square this size
+const LABEL_SCALE: u32 = 3;
+
+/// Height of the label strip above each thumbnail
+const LABEL_HEIGHT: u32 = 5 * LABEL_SCALE + OVERVIEW_PADDING;
+
+/// Position of a thumbnail in an overview grid
+#[derive(Debug, Clone, Copy)]
+pub struct OverviewCell {
+    pub row: u32,
+    pub column: u32,
+}
+
+/// Arrange labeled thumbnails in a grid and encode it as PNG
+///
+/// Each image is downscaled to fit a `thumbnail_size` square and drawn below
+/// its label. Returns the grid and the cell each thumbnail landed in, in input order.
+pub fn composite_overview(thumbnails: Vec<(String, RgbaImage)>, thumbnail_size: u32) -> anyhow::Result<(CapturedImage, Vec<OverviewCell>)> {
+    if thumbnails.is_empty() {
+        anyhow::bail!("No thumbnails to arrange");
+    }
+
+    let columns = (thumbnails.len() as f64).sqrt().ceil() as u32;
+    let rows = (thumbnails.len() as u32).div_ceil(columns);
+    let cell_width = thumbnail_size + OVERVIEW_PADDING * 2;
+    let cell_height = LABEL_HEIGHT + thumbnail_size + OVERVIEW_PADDING;
+
+    let background = image::Rgba([40, 40, 40, 255]);
+    let mut grid = RgbaImage::from_pixel(columns * cell_width, rows * cell_height, background);
+    let mut cells = Vec::with_capacity(thumbnails.len());
+
+    for (index, (label, image)) in thumbnails.into_iter().enumerate() {
+        let cell = OverviewCell {
+            row: index as u32 / columns,
+            column: index as u32 % columns,
+        };
+        let cell_x = cell.column * cell_width + OVERVIEW_PADDING;
+        let cell_y = cell.row * cell_height;
+
+        draw_label(&mut grid, &label, cell_x, cell_y + OVERVIEW_PADDING / 2);
+
+        // Center the thumbnail horizontally within its cell
+        let thumbnail = downscale(image, Some(thumbnail_size), Some(thumbnail_size));
+        let x = cell_x + (thumbnail_size - thumbnail.width()) / 2;
+        image::imageops::overlay(&mut grid, &thumbnail, x as i64, (cell_y + LABEL_HEIGHT) as i64);
+
+        cells.push(cell);
+    }
+
+    let data = encode_image(&grid, ImageEncoding::Png)?;
+    Ok((
+        CapturedImage {
+            data,
+            width: grid.width(),
+            height: grid.height(),
+        },
+        cells,
+    ))
+}
+
+/// Draw a window ref in white using a 3x5 pixel font
+///
+/// Only lowercase `w` and digits are needed for refs; other characters are
+/// left blank.
+fn draw_label(image: &mut RgbaImage, label: &str, x: u32, y: u32) {
+    let white = image::Rgba([255, 255, 255, 255]);
+
+    for (index, c) in label.chars().enumerate() {
+        let rows = label_glyph(c);
+        let glyph_x = x + index as u32 * 4 * LABEL_SCALE;
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..LABEL_SCALE {
+                    for dx in 0..LABEL_SCALE {
+                        let px = glyph_x + column * LABEL_SCALE + dx;
+                        let py = y + row as u32 * LABEL_SCALE + dy;
+                        if px < image.width() && py < image.height() {
+                            image.put_pixel(px, py, white);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rows of a 3x5 glyph, most significant bit on the left
+fn label_glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'w' => [0b000, 0b101, 0b101, 0b111, 0b101],
+        _ => [0; 5],
+    }
+}
+
+/// Radius of the cursor marker ring in pixels
+const CURSOR_MARKER_RADIUS: i32 = 8;
+
+/// Draw a red ring with a white outline and a center dot at the given position
+///
+/// Pixels falling outside the image are skipped.
+fn draw_cursor_marker(image: &mut RgbaImage, x: i32, y: i32) {
+    let red = image::Rgba([255, 0, 0, 255]);
+    let white = image::Rgba([255, 255, 255, 255]);
+    let outer = CURSOR_MARKER_RADIUS + 1;
+
+    for dy in -outer..=outer {
+        for dx in -outer..=outer {
+            let (px, py) = (x + dx, y + dy);
+            if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+                continue;
+            }
+
+            let distance = ((dx * dx + dy * dy) as f64).sqrt();
+            let ring_offset = (distance - CURSOR_MARKER_RADIUS as f64).abs();
+            let color = if distance <= 1.5 || ring_offset <= 1.0 {
+                Some(red)
+            } else if ring_offset <= 2.0 {
+                Some(white)
+            } else {
+                None
+            };
+
+            if let Some(color) = color {
+                image.put_pixel(px as u32, py as u32, color);
+            }
+        }
+    }
+}
+
+/// Resize an image to fit within the given bounds, preserving aspect ratio
+///
+/// Images already within bounds are returned unchanged; images are never upscaled.
+fn downscale(image: RgbaImage, max_width: Option<u32>, max_height: Option<u32>) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let scale_x = max_width.map_or(1.0, |max| max.max(1) as f64 / width as f64);
+    let scale_y = max_height.map_or(1.0, |max| max.max(1) as f64 / height as f64);
+    let scale = scale_x.min(scale_y);
+
+    if scale >= 1.0 {
+        return image;
+    }
+
+    let new_width = ((width as f64 * scale).round() as u32).max(1);
+    let new_height = ((height as f64 * scale).round() as u32).max(1);
+
+    image::imageops::resize(&image, new_width, new_height, image::imageops::FilterType::Lanczos3)
+}
+
+/// Encode an RGBA image in the requested format
+pub fn encode_image(image: &RgbaImage, encoding: ImageEncoding) -> anyhow::Result<Vec<u8>> {
     let mut buffer = Vec::new();
-    let encoder = image::codecs::png::PngEncoder::new(&mut buffer);
-    encoder.write_image(
-        cropped.as_raw(),
-        cropped.width(),
-        cropped.height(),
-        image::ExtendedColorType::Rgba8,
-    )?;
+
+    match encoding {
+        ImageEncoding::Png => {
+            let encoder = image::codecs::png::PngEncoder::new(&mut buffer);
+            encoder.write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                image::ExtendedColorType::Rgba8,
+            )?;
+        }
+        ImageEncoding::Jpeg { quality } => {
+            // JPEG has no alpha channel
+            let rgb = image::DynamicImage::ImageRgba8(image.clone()).into_rgb8();
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, quality);
+            encoder.write_image(
+                rgb.as_raw(),
+                rgb.width(),
+                rgb.height(),
+                image::ExtendedColorType::Rgb8,
+            )?;
+        }
+        ImageEncoding::Webp => {
+            // The image crate only ships a lossless WebP encoder
+            let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut buffer);
+            encoder.write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                image::ExtendedColorType::Rgba8,
+            )?;
+        }
+    }
 
     Ok(buffer)
 }
@@ -0,0 +1,30 @@
+//! Monitor enumeration using xcap
+
+use crate::backend::MonitorInfo;
+
+/// List all monitors known to xcap
+pub async fn list_monitors() -> anyhow::Result<Vec<MonitorInfo>> {
+    // xcap is not async, so we run it in a blocking task
+    let result = tokio::task::spawn_blocking(list_monitors_blocking).await??;
+
+    Ok(result)
+}
+
+fn list_monitors_blocking() -> anyhow::Result<Vec<MonitorInfo>> {
+    let monitors = xcap::Monitor::all()?;
+
+    monitors
+        .iter()
+        .map(|m| {
+            Ok(MonitorInfo {
+                name: m.name()?,
+                x: m.x()?,
+                y: m.y()?,
+                width: m.width()?,
+                height: m.height()?,
+                scale_factor: m.scale_factor().unwrap_or(1.0) as f64,
+                primary: m.is_primary().unwrap_or(false),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,140 @@
+//! Text recognition via the tesseract CLI
+//!
+//! Captures are piped to `tesseract stdin stdout tsv`, whose word-level boxes
+//! are relative to the captured image, i.e. window-relative for window
+//! captures, so they can be passed straight to `window_click`.
+
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as AsyncCommand;
+
+use super::{CaptureOptions, ImageEncoding};
+use crate::core::registry::{Geometry, PlatformWindowId};
+
+/// A recognized word and its bounding box
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OcrWord {
+    pub text: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Tesseract's confidence, 0-100
+    pub confidence: f32,
+}
+
+/// Capture a window and recognize the text in it
+pub async fn ocr_window(platform_id: &PlatformWindowId, geometry: &Geometry, lang: &str) -> anyhow::Result<Vec<OcrWord>> {
+    // Full resolution PNG: downscaling or lossy encoding hurts recognition
+    let options = CaptureOptions {
+        encoding: ImageEncoding::Png,
+        max_width: None,
+        max_height: None,
+        cursor: None,
+        crop: None,
+    };
+    let image = super::capture_window(platform_id, geometry, options).await?;
+
+    recognize(&image.data, lang).await
+}
+
+/// Recognize text in an encoded image
+pub async fn recognize(image: &[u8], lang: &str) -> anyhow::Result<Vec<OcrWord>> {
+    let mut child = AsyncCommand::new("tesseract")
+        .args(["stdin", "stdout", "-l", lang, "tsv"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                anyhow::anyhow!("tesseract is not installed; install tesseract (and the language data for \"{}\") to use OCR", lang)
+            }
+            _ => anyhow::anyhow!("Failed to run tesseract: {}", e),
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // tesseract reads the whole image before writing anything, so this can't deadlock
+        stdin.write_all(image).await?;
+    }
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("tesseract failed: {}", stderr.trim());
+    }
+
+    Ok(parse_tsv(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse tesseract TSV output into words
+///
+/// Columns: level, page_num, block_num, par_num, line_num, word_num, left,
+/// top, width, height, conf, text. Only word rows (level 5) with text are kept.
+fn parse_tsv(tsv: &str) -> Vec<OcrWord> {
+    tsv.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(12, '\t').collect();
+            if fields.len() < 12 || fields[0] != "5" {
+                return None;
+            }
+
+            let text = fields[11].trim();
+            let confidence: f32 = fields[10].parse().ok()?;
+            if text.is_empty() || confidence < 0.0 {
+                return None;
+            }
+
+            Some(OcrWord {
+                text: text.to_string(),
+                x: fields[6].parse().ok()?,
+                y: fields[7].parse().ok()?,
+                width: fields[8].parse().ok()?,
+                height: fields[9].parse().ok()?,
+                confidence,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext";
+
+    #[test]
+    fn keeps_only_confident_word_rows_with_text() {
+        let tsv = [
+            HEADER,
+            "1\t1\t0\t0\t0\t0\t0\t0\t800\t600\t-1\t",
+            "4\t1\t1\t1\t1\t0\t10\t20\t200\t18\t-1\t",
+            "5\t1\t1\t1\t1\t1\t10\t20\t60\t18\t96.5\tHello",
+            "5\t1\t1\t1\t1\t2\t80\t20\t70\t18\t91\tworld ",
+            "5\t1\t1\t1\t1\t3\t160\t20\t5\t18\t-1\tghost",
+            "5\t1\t1\t1\t1\t4\t170\t20\t5\t18\t95\t   ",
+        ]
+        .join("\n");
+
+        let words = parse_tsv(&tsv);
+        let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, vec!["Hello", "world"]);
+
+        let hello = &words[0];
+        assert_eq!((hello.x, hello.y, hello.width, hello.height), (10, 20, 60, 18));
+        assert_eq!(hello.confidence, 96.5);
+    }
+
+    #[test]
+    fn header_only_and_empty_output_yield_nothing() {
+        assert!(parse_tsv(HEADER).is_empty());
+        assert!(parse_tsv("").is_empty());
+    }
+
+    #[test]
+    fn word_text_may_contain_tabs() {
+        let tsv = format!("{}\n5\t1\t1\t1\t1\t1\t0\t0\t10\t10\t90\ta\tb", HEADER);
+        assert_eq!(parse_tsv(&tsv)[0].text, "a\tb");
+    }
+}
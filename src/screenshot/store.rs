@@ -0,0 +1,125 @@
+//! Screenshots kept in memory for clients to fetch as MCP resources
+//!
+//! The "resource" output format stores an encoded capture here and returns a
+//! `marionette://screenshots/<id>` link in place of inline image data, so
+//! clients only download the images they actually look at. A fixed number of
+//! captures is kept, and the least recently used is evicted first.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// An encoded capture waiting to be fetched
+#[derive(Debug)]
+pub struct StoredScreenshot {
+    pub data: Vec<u8>,
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    /// What was captured, e.g. the window ref
+    pub source: String,
+}
+
+/// Least recently used store of captures, keyed by id
+#[derive(Debug)]
+pub struct ScreenshotStore {
+    capacity: usize,
+    next_id: u64,
+    /// Entries from least to most recently used
+    entries: VecDeque<(String, Arc<StoredScreenshot>)>,
+}
+
+impl ScreenshotStore {
+    /// Create a store holding at most `capacity` captures
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), next_id: 1, entries: VecDeque::new() }
+    }
+
+    /// Store a capture and return its id, evicting the least recently used if full
+    pub fn insert(&mut self, screenshot: StoredScreenshot) -> String {
+        let id = self.next_id.to_string();
+        self.next_id += 1;
+
+        while self.entries.len() >= self.capacity {
+            if let Some((evicted, _)) = self.entries.pop_front() {
+                tracing::debug!("Evicting stored screenshot {}", evicted);
+            }
+        }
+        self.entries.push_back((id.clone(), Arc::new(screenshot)));
+        id
+    }
+
+    /// Look up a capture, marking it as recently used
+    pub fn get(&mut self, id: &str) -> Option<Arc<StoredScreenshot>> {
+        let index = self.entries.iter().position(|(entry_id, _)| entry_id == id)?;
+        let entry = self.entries.remove(index)?;
+        let screenshot = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(screenshot)
+    }
+
+    /// Stored captures from least to most recently used
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &StoredScreenshot)> {
+        self.entries.iter().map(|(id, screenshot)| (id.as_str(), screenshot.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screenshot(source: &str) -> StoredScreenshot {
+        StoredScreenshot {
+            data: vec![0; 4],
+            mime_type: "image/png",
+            width: 1,
+            height: 1,
+            source: source.to_string(),
+        }
+    }
+
+    fn ids(store: &ScreenshotStore) -> Vec<String> {
+        store.iter().map(|(id, _)| id.to_string()).collect()
+    }
+
+    #[test]
+    fn evicts_least_recently_inserted_first() {
+        let mut store = ScreenshotStore::new(2);
+        let first = store.insert(screenshot("w0"));
+        let second = store.insert(screenshot("w1"));
+        let third = store.insert(screenshot("w2"));
+
+        assert!(store.get(&first).is_none());
+        assert_eq!(ids(&store), vec![second, third]);
+    }
+
+    #[test]
+    fn get_refreshes_recency() {
+        let mut store = ScreenshotStore::new(2);
+        let first = store.insert(screenshot("w0"));
+        let second = store.insert(screenshot("w1"));
+
+        assert_eq!(store.get(&first).unwrap().source, "w0");
+        let third = store.insert(screenshot("w2"));
+
+        assert!(store.get(&second).is_none());
+        assert_eq!(ids(&store), vec![first, third]);
+    }
+
+    #[test]
+    fn never_holds_more_than_capacity() {
+        let mut store = ScreenshotStore::new(3);
+        for i in 0..10 {
+            store.insert(screenshot(&format!("w{}", i)));
+        }
+        assert_eq!(store.iter().count(), 3);
+        assert_eq!(ids(&store), vec!["8", "9", "10"]);
+    }
+
+    #[test]
+    fn zero_capacity_still_keeps_the_latest() {
+        let mut store = ScreenshotStore::new(0);
+        store.insert(screenshot("w0"));
+        let latest = store.insert(screenshot("w1"));
+        assert_eq!(ids(&store), vec![latest]);
+    }
+}
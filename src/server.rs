@@ -4,18 +4,30 @@
 //! window manipulation tools to AI assistants.
 
 use rmcp::{
-    handler::server::{tool::ToolRouter, wrapper::Parameters},
+    handler::server::{tool::{ToolCallContext, ToolRouter}, wrapper::Parameters},
     model::*,
     schemars, serde,
     service::RequestContext,
-    tool, tool_handler, tool_router, ErrorData as McpError, RoleServer, ServerHandler,
+    tool, tool_router, ErrorData as McpError, Peer, RoleServer, ServerHandler,
 };
+use image::RgbaImage;
 use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
 
-use crate::backend::WindowBackend;
-use crate::core::registry::WindowRegistry;
+use crate::backend::atspi::{AccessibleTree, AtspiClient, TreeLimits};
+use crate::backend::{CloseMethod, MonitorInfo, WindowBackend, UNKNOWN_WINDOW_TYPE, WINDOW_TYPES};
+use crate::core::action_log::ActionLog;
+use crate::core::error::MarionetteError;
+use crate::core::geometry::{self, SnapPosition};
+use crate::core::rate_limit::RateLimiter;
+use crate::core::registry::{Geometry, WindowHandle, WindowRegistry};
+use crate::input::InputBackend;
+use crate::screenshot::store::{ScreenshotStore, StoredScreenshot};
+use crate::screenshot::{CaptureOptions, CapturedImage, ImageEncoding};
 
 /// Parameters for window_list tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -23,16 +35,180 @@ pub struct WindowListParams {
     /// Filter windows by title (case-insensitive substring match)
     #[serde(default)]
     pub title_filter: Option<String>,
-    /// Filter windows by class/app name
+    /// Filter windows by class/app name or WM_CLASS instance name
     #[serde(default)]
     pub class_filter: Option<String>,
+    /// Filter windows by a regular expression matched against the full title (case-sensitive;
+    /// use (?i) to ignore case, and ^/$ to anchor)
+    #[serde(default)]
+    pub title_regex: Option<String>,
+    /// Filter windows by a regular expression matched against the class or WM_CLASS instance name
+    #[serde(default)]
+    pub class_regex: Option<String>,
+    /// Only include windows owned by this process ID
+    #[serde(default)]
+    pub pid_filter: Option<u32>,
+    /// Sort order: "ref" (default) or "stacking" (bottom-to-top, topmost last)
+    #[serde(default = "default_sort")]
+    pub sort: String,
+    /// Window types to include, e.g. ["normal", "dialog", "dock"] (default: ["normal", "dialog"]; [] for all types)
+    #[serde(default)]
+    pub include_types: Option<Vec<String>>,
+    /// Window types to leave out, applied after include_types
+    #[serde(default)]
+    pub exclude_types: Vec<String>,
+    /// Include each window's icon as a base64 PNG (default: false; adds a property read and encode per window)
+    #[serde(default)]
+    pub include_icons: bool,
+}
+
+fn default_sort() -> String {
+    "ref".to_string()
+}
+
+/// Window types window_list returns when include_types is omitted
+const DEFAULT_WINDOW_TYPES: &[&str] = &["normal", "dialog"];
+
+/// Reject window type names that aren't in [`WINDOW_TYPES`] or [`UNKNOWN_WINDOW_TYPE`]
+fn validate_window_types(types: &[String]) -> Result<(), McpError> {
+    match types.iter().find(|t| !WINDOW_TYPES.contains(&t.as_str()) && t.as_str() != UNKNOWN_WINDOW_TYPE) {
+        Some(unknown) => Err(MarionetteError::invalid_params(
+            format!("Unknown window type: {}", unknown),
+            Some(&format!("Use one of: {}, {}", WINDOW_TYPES.join(", "), UNKNOWN_WINDOW_TYPE)),
+        )
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Compile an optional regex filter, naming the parameter in the error if it is invalid
+fn compile_filter_regex(pattern: Option<&str>, param: &str) -> Result<Option<regex::Regex>, McpError> {
+    let Some(pattern) = pattern else {
+        return Ok(None);
+    };
+    regex::Regex::new(pattern).map(Some).map_err(|e| {
+        MarionetteError::invalid_params(
+            format!("Invalid {}: {}", param, e),
+            Some("Use Rust regex syntax, e.g. \"— Firefox$\" or \"(?i)^terminal\""),
+        )
+        .into()
+    })
+}
+
+/// Parameters for window_find tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowFindParams {
+    /// Match windows whose title contains this (case-insensitive)
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Match windows whose class/app name contains this (case-insensitive)
+    #[serde(default)]
+    pub class: Option<String>,
+}
+
+/// Parameters for window_wait tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowWaitParams {
+    /// Match windows whose title contains this (case-insensitive)
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Match windows whose class/app name contains this (case-insensitive)
+    #[serde(default)]
+    pub class: Option<String>,
+    /// How long to wait for a match in milliseconds (default: 5000, max: 60000)
+    #[serde(default = "default_wait_timeout")]
+    pub timeout_ms: u64,
+}
+
+fn default_wait_timeout() -> u64 {
+    5000
+}
+
+/// Parameters for window_wait_state tool
+///
+/// All given conditions must hold at the same time.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowWaitStateParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Wait until the title contains this (case-insensitive)
+    #[serde(default)]
+    pub title_contains: Option<String>,
+    /// Wait until the window is (true) or is not (false) focused
+    #[serde(default)]
+    pub focused: Option<bool>,
+    /// Wait until the geometry has not changed for this many milliseconds
+    #[serde(default)]
+    pub geometry_stable_ms: Option<u64>,
+    /// How long to wait in milliseconds (default: 5000, max: 60000)
+    #[serde(default = "default_wait_timeout")]
+    pub timeout_ms: u64,
 }
 
+/// Interval between window list refreshes while waiting
+const WAIT_POLL_INTERVAL_MS: u64 = 250;
+
+/// Upper bound on wait timeouts, so a bad value can't stall the server
+const MAX_WAIT_TIMEOUT_MS: u64 = 60_000;
+
+/// How many times to re-read geometry after a move or resize, waiting for it to change
+const GEOMETRY_REFRESH_ATTEMPTS: u32 = 5;
+
+/// Interval between geometry re-reads after a move or resize
+const GEOMETRY_REFRESH_INTERVAL_MS: u64 = 20;
+
+/// Time given to the window manager to apply a focus change before sending input
+const FOCUS_SETTLE_DELAY_MS: u64 = 150;
+
+/// URI of the subscribable window list resource
+const WINDOWS_RESOURCE_URI: &str = "marionette://windows";
+
+/// URI template of the per-window resources, e.g. `marionette://windows/w0`
+const WINDOW_RESOURCE_TEMPLATE: &str = "marionette://windows/{ref}";
+
+/// URI prefix of stored screenshots, followed by the screenshot id
+const SCREENSHOT_RESOURCE_PREFIX: &str = "marionette://screenshots/";
+
+/// URI template of stored screenshots
+const SCREENSHOT_RESOURCE_TEMPLATE: &str = "marionette://screenshots/{id}";
+
+/// Most screenshots kept for the "resource" format before the least recently used is dropped
+const MAX_STORED_SCREENSHOTS: usize = 32;
+
+/// Default interval between window list polls for resource subscribers
+const DEFAULT_WATCH_INTERVAL_MS: u64 = 1000;
+
+/// Default actions per second allowed for each input tool
+const DEFAULT_INPUT_RATE_LIMIT: f64 = 20.0;
+
 /// Parameters for window_snapshot tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct WindowSnapshotParams {
     /// Window reference (e.g., "w0") from window_list
     pub r#ref: String,
+    /// Re-enumerate windows first so title and geometry are current (default: false, use cached state)
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// Parameters for window_exists tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowExistsParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Re-enumerate windows first to confirm the window is still open (default: false, check the cached registry)
+    #[serde(default)]
+    pub verify: bool,
+}
+
+/// Parameters for window_alias tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowAliasParams {
+    /// Window reference (e.g., "w0") or an existing alias
+    pub r#ref: String,
+    /// Name to give the window, e.g. "editor". Omit to remove the window's aliases
+    #[serde(default)]
+    pub alias: Option<String>,
 }
 
 /// Parameters for window_focus tool
@@ -50,15 +226,182 @@ pub struct WindowFocusParams {
 pub struct WindowScreenshotParams {
     /// Window reference (e.g., "w0") from window_list
     pub r#ref: String,
-    /// Output format: "base64" (default) or "file"
+    /// Output format: "base64" (default), "file", or "resource" (a marionette://screenshots/<id>
+    /// link the client fetches with resources/read only if it needs the image)
     #[serde(default = "default_format")]
     pub format: String,
+    /// Image encoding: "png" (default), "jpeg", or "webp" (lossless)
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    /// Quality for lossy encodings (1-100)
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// Downscale so the image is at most this wide, preserving aspect ratio
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    /// Downscale so the image is at most this tall, preserving aspect ratio
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    /// Draw a marker at the pointer position if it is over the window
+    #[serde(default)]
+    pub include_cursor: bool,
+    /// Left edge of a window-relative region to capture instead of the whole window (default: 0)
+    #[serde(default)]
+    pub x: Option<i32>,
+    /// Top edge of the region to capture (default: 0)
+    #[serde(default)]
+    pub y: Option<i32>,
+    /// Width of the region to capture (default: to the window's right edge)
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// Height of the region to capture (default: to the window's bottom edge)
+    #[serde(default)]
+    pub height: Option<u32>,
 }
 
 fn default_format() -> String {
     "base64".to_string()
 }
 
+fn default_encoding() -> String {
+    "png".to_string()
+}
+
+fn default_quality() -> u8 {
+    70
+}
+
+/// Parameters for screen_screenshot tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ScreenScreenshotParams {
+    /// Monitor index (e.g., "0") or name (e.g., "DP-1") from monitor_list; defaults to the primary monitor
+    #[serde(default)]
+    pub monitor: Option<String>,
+    /// Output format: "base64" (default) or "file"
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Image encoding: "png" (default), "jpeg", or "webp" (lossless)
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    /// Quality for lossy encodings (1-100)
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// Downscale so the image is at most this wide, preserving aspect ratio
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    /// Downscale so the image is at most this tall, preserving aspect ratio
+    #[serde(default)]
+    pub max_height: Option<u32>,
+}
+
+/// Parameters for screen_region tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ScreenRegionParams {
+    /// X coordinate of the region in screen coordinates
+    pub x: i32,
+    /// Y coordinate of the region in screen coordinates
+    pub y: i32,
+    /// Region width
+    pub width: u32,
+    /// Region height
+    pub height: u32,
+    /// Capture only from this monitor (index or name from monitor_list), clamping the region to it
+    #[serde(default)]
+    pub monitor: Option<String>,
+    /// Output format: "base64" (default) or "file"
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Image encoding: "png" (default), "jpeg", or "webp" (lossless)
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    /// Quality for lossy encodings (1-100)
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// Downscale so the image is at most this wide, preserving aspect ratio
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    /// Downscale so the image is at most this tall, preserving aspect ratio
+    #[serde(default)]
+    pub max_height: Option<u32>,
+}
+
+/// Coordinate space for pointer tool coordinates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CoordinateSpace {
+    /// Relative to the window's top-left corner
+    #[default]
+    Window,
+    /// Absolute screen coordinates, e.g. read off a screen_screenshot
+    Screen,
+}
+
+/// Parameters for window_ocr tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowOcrParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Tesseract language code(s), e.g. "eng" (default) or "eng+deu"
+    #[serde(default = "default_ocr_lang")]
+    pub lang: String,
+}
+
+fn default_ocr_lang() -> String {
+    "eng".to_string()
+}
+
+/// Parameters for window_find_image tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowFindImageParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Base64-encoded template image (PNG) to look for
+    pub template: String,
+    /// Minimum match confidence from 0.0 to 1.0 (default: 0.8); weaker matches report not found
+    #[serde(default = "default_match_threshold")]
+    pub threshold: f32,
+}
+
+fn default_match_threshold() -> f32 {
+    0.8
+}
+
+/// Parameters for windows_overview tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowsOverviewParams {
+    /// Most windows to include (default: 12, at most 24)
+    #[serde(default = "default_overview_max_windows")]
+    pub max_windows: usize,
+    /// Largest thumbnail edge in pixels (default: 320, at most 480)
+    #[serde(default = "default_overview_thumbnail_size")]
+    pub thumbnail_size: u32,
+}
+
+fn default_overview_max_windows() -> usize {
+    12
+}
+
+fn default_overview_thumbnail_size() -> u32 {
+    320
+}
+
+/// Smallest thumbnail edge windows_overview accepts, in pixels
+const MIN_OVERVIEW_THUMBNAIL_SIZE: u32 = 64;
+
+/// Parameters for window_screenshot_diff tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowScreenshotDiffParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// How much a color channel may change (0-255) before a pixel counts as changed (default: 16)
+    #[serde(default = "default_diff_tolerance")]
+    pub tolerance: u8,
+}
+
+fn default_diff_tolerance() -> u8 {
+    16
+}
+
 /// Parameters for window_click tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct WindowClickParams {
@@ -71,37 +414,329 @@ pub struct WindowClickParams {
     /// Mouse button: "left" (default), "right", "middle"
     #[serde(default = "default_button")]
     pub button: String,
+    /// Number of clicks (1 = single click, 2 = double-click)
+    #[serde(default = "default_count")]
+    pub count: u32,
     /// Human-readable description of what's being clicked
     #[serde(default)]
     pub description: Option<String>,
+    /// Focus the window before clicking (default: false)
+    #[serde(default)]
+    pub focus_first: bool,
+    /// How x/y are interpreted: "window" (default, relative to the window) or "screen" (absolute)
+    #[serde(default)]
+    pub coordinate_space: CoordinateSpace,
+    /// Allow coordinates outside the window, e.g. for deliberate edge clicks (default: false)
+    #[serde(default)]
+    pub allow_out_of_bounds: bool,
 }
 
 fn default_button() -> String {
     "left".to_string()
 }
 
+fn default_count() -> u32 {
+    1
+}
+
+/// Parameters for window_click_type tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowClickTypeParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// X coordinate of the field within the window
+    pub x: i32,
+    /// Y coordinate of the field within the window
+    pub y: i32,
+    /// Text to type into the field
+    pub text: String,
+    /// Select all and delete the field's existing content before typing (Ctrl+A, Delete)
+    #[serde(default)]
+    pub clear_first: bool,
+    /// Input method: "keyboard" (default) or "clipboard", as for window_type
+    #[serde(default = "default_type_method")]
+    pub method: String,
+    /// Delay between keystrokes in milliseconds (default: 12, or MARIONETTE_TYPE_DELAY_MS)
+    #[serde(default)]
+    pub delay_ms: Option<u32>,
+    /// Focus the window before clicking (default: false)
+    #[serde(default)]
+    pub focus_first: bool,
+    /// How x/y are interpreted: "window" (default, relative to the window) or "screen" (absolute)
+    #[serde(default)]
+    pub coordinate_space: CoordinateSpace,
+}
+
+/// Parameters for window_accessibility_tree tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowAccessibilityTreeParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Deepest level below the window to walk (default: 15)
+    #[serde(default = "default_accessibility_max_depth")]
+    pub max_depth: usize,
+    /// Most elements to visit (default: 500, at most 5000)
+    #[serde(default = "default_accessibility_max_nodes")]
+    pub max_nodes: usize,
+    /// Leave out elements that aren't showing on screen, and their children (default: true)
+    #[serde(default = "default_showing_only")]
+    pub showing_only: bool,
+}
+
+fn default_showing_only() -> bool {
+    true
+}
+
+fn default_accessibility_max_depth() -> usize {
+    15
+}
+
+fn default_accessibility_max_nodes() -> usize {
+    500
+}
+
+/// Upper bound on elements visited in one accessibility tree walk
+const MAX_ACCESSIBILITY_NODES: usize = 5000;
+
+/// Parameters for window_click_element tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowClickElementParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Role to match, case-insensitive substring (e.g. "button" matches "push button" and "toggle button")
+    #[serde(default)]
+    pub role: Option<String>,
+    /// Accessible name (usually the visible label) to match, case-insensitive substring
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Which match to click when several match, in tree order (default: 0, the first)
+    #[serde(default)]
+    pub index: usize,
+    /// Mouse button: "left" (default), "right", "middle"
+    #[serde(default = "default_button")]
+    pub button: String,
+    /// Number of clicks (1 = single click, 2 = double-click)
+    #[serde(default = "default_count")]
+    pub count: u32,
+    /// Focus the window before clicking (default: false)
+    #[serde(default)]
+    pub focus_first: bool,
+}
+
+/// Parameters for window_scroll tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowScrollParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// X coordinate within the window
+    pub x: i32,
+    /// Y coordinate within the window
+    pub y: i32,
+    /// Horizontal scroll in wheel notches (negative = left, positive = right)
+    #[serde(default)]
+    pub dx: i32,
+    /// Vertical scroll in wheel notches (negative = up, positive = down)
+    #[serde(default)]
+    pub dy: i32,
+    /// Allow coordinates outside the window, e.g. for deliberate edge clicks (default: false)
+    #[serde(default)]
+    pub allow_out_of_bounds: bool,
+}
+
+/// Parameters for window_drag tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowDragParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Start X coordinate within the window
+    pub from_x: i32,
+    /// Start Y coordinate within the window
+    pub from_y: i32,
+    /// End X coordinate within the window
+    pub to_x: i32,
+    /// End Y coordinate within the window
+    pub to_y: i32,
+    /// Mouse button to hold: "left" (default), "right", "middle"
+    #[serde(default = "default_button")]
+    pub button: String,
+    /// How x/y are interpreted: "window" (default, relative to the window) or "screen" (absolute)
+    #[serde(default)]
+    pub coordinate_space: CoordinateSpace,
+    /// Allow coordinates outside the window, e.g. for deliberate edge clicks (default: false)
+    #[serde(default)]
+    pub allow_out_of_bounds: bool,
+}
+
+/// Parameters for window_hover tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowHoverParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// X coordinate within the window
+    pub x: i32,
+    /// Y coordinate within the window
+    pub y: i32,
+    /// How long to rest the pointer before returning, in milliseconds (default: 500, max: 10000)
+    #[serde(default = "default_hover_duration")]
+    pub duration_ms: u64,
+    /// How x/y are interpreted: "window" (default, relative to the window) or "screen" (absolute)
+    #[serde(default)]
+    pub coordinate_space: CoordinateSpace,
+}
+
+fn default_hover_duration() -> u64 {
+    500
+}
+
+/// Parameters for window_at_point tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowAtPointParams {
+    /// X coordinate in screen coordinates (default: the pointer position)
+    #[serde(default)]
+    pub x: Option<i32>,
+    /// Y coordinate in screen coordinates (default: the pointer position)
+    #[serde(default)]
+    pub y: Option<i32>,
+}
+
+/// Parameters for cursor_move tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CursorMoveParams {
+    /// X coordinate in screen coordinates
+    pub x: i32,
+    /// Y coordinate in screen coordinates
+    pub y: i32,
+}
+
 /// Parameters for window_type tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct WindowTypeParams {
     /// Text to type
     pub text: String,
-    /// Delay between keystrokes in milliseconds
-    #[serde(default = "default_delay")]
-    pub delay_ms: u32,
+    /// Delay between keystrokes in milliseconds (default: 12, or MARIONETTE_TYPE_DELAY_MS)
+    #[serde(default)]
+    pub delay_ms: Option<u32>,
+    /// Input method: "keyboard" (default) types each key; "clipboard" pastes via Ctrl+V,
+    /// which handles accented characters, emoji, and other non-ASCII text
+    #[serde(default = "default_type_method")]
+    pub method: String,
+    /// Window reference (e.g., "w0") to focus before typing; omit to type into the focused window
+    #[serde(default)]
+    pub r#ref: Option<String>,
+    /// Select all and delete the field's existing content before typing (Ctrl+A, Delete)
+    #[serde(default)]
+    pub clear_first: bool,
 }
 
-fn default_delay() -> u32 {
-    12
+fn default_type_method() -> String {
+    "keyboard".to_string()
+}
+
+/// A single step of a window_type_sequence call
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+pub enum TypeSequenceAction {
+    /// Type a string of text
+    Text {
+        /// Text to type
+        text: String,
+    },
+    /// Press a key or key combination
+    Key {
+        /// Key to press (e.g., "Return", "Tab"), or a combo like "ctrl+a"
+        key: String,
+        /// Modifier keys to hold: "ctrl", "alt", "shift", "super"
+        #[serde(default)]
+        modifiers: Vec<String>,
+    },
+}
+
+/// Parameters for window_type_sequence tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowTypeSequenceParams {
+    /// Ordered actions, each either {"text": "..."} or {"key": "Return", "modifiers": [...]}
+    pub actions: Vec<TypeSequenceAction>,
+    /// Delay between keystrokes in milliseconds when typing text (default: 12, or MARIONETTE_TYPE_DELAY_MS)
+    #[serde(default)]
+    pub delay_ms: Option<u32>,
+}
+
+/// Parameters for clipboard_set tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ClipboardSetParams {
+    /// Text to place on the clipboard
+    pub text: String,
+}
+
+/// Parameters for window_paste tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowPasteParams {
+    /// Text to paste into the focused window
+    pub text: String,
 }
 
 /// Parameters for window_key tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct WindowKeyParams {
-    /// Key to press (e.g., "Return", "Escape", "Tab", "a", "F1")
+    /// Key to press (e.g., "Return", "Escape", "Tab", "a", "F1"), or a combo like "ctrl+shift+t"
     pub key: String,
     /// Modifier keys to hold: "ctrl", "alt", "shift", "super"
     #[serde(default)]
     pub modifiers: Vec<String>,
+    /// Press the key this many times (default: 1, max: 100)
+    #[serde(default = "default_key_repeat")]
+    pub repeat: u32,
+    /// Delay between repeated presses in milliseconds (default: 50)
+    #[serde(default = "default_key_repeat_delay")]
+    pub repeat_delay_ms: u64,
+    /// Hold the key down this long in milliseconds before releasing it, instead of a
+    /// quick press (max: 10000). Cannot be combined with repeat
+    #[serde(default)]
+    pub hold_ms: Option<u64>,
+}
+
+fn default_key_repeat() -> u32 {
+    1
+}
+
+fn default_key_repeat_delay() -> u64 {
+    50
+}
+
+/// Characters typed per input tool call, so a cancel can stop long text between chunks
+const TYPE_CHUNK_CHARS: usize = 16;
+
+/// How often a cancellable wait (e.g. a timed key hold) checks for cancel
+const CANCEL_POLL_MS: u64 = 20;
+
+/// Most presses a single window_key call may repeat
+const MAX_KEY_REPEAT: u32 = 100;
+
+/// Upper bound on how long window_key may hold a key, so a bad value can't stall the server
+const MAX_KEY_HOLD_MS: u64 = 10_000;
+
+/// Parameters for window_key_down tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowKeyHoldParams {
+    /// Key to hold (e.g., "w", "space", "Left"), or a combo like "shift+w"
+    pub key: String,
+    /// Modifier keys held along with the key: "ctrl", "alt", "shift", "super"
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+}
+
+/// Parameters for window_key_up tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowKeyUpParams {
+    /// Key to release, as passed to window_key_down
+    pub key: String,
+}
+
+/// A key held down by window_key_down, with the modifiers pressed alongside it
+#[derive(Debug, Clone, serde::Serialize)]
+struct HeldKey {
+    key: String,
+    modifiers: Vec<String>,
 }
 
 /// Parameters for window_move tool
@@ -115,16 +750,456 @@ pub struct WindowMoveParams {
     pub y: i32,
 }
 
-/// Parameters for window_resize tool
+/// Parameters for window_move_to_monitor tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct WindowResizeParams {
+pub struct WindowMoveToMonitorParams {
     /// Window reference (e.g., "w0") from window_list
     pub r#ref: String,
-    /// New width
-    pub width: u32,
-    /// New height
-    pub height: u32,
-}
+    /// Monitor index (e.g., "0") or name (e.g., "DP-1") from monitor_list
+    pub monitor: String,
+    /// Horizontal offset from the monitor's left edge (default: 0)
+    #[serde(default)]
+    pub offset_x: i32,
+    /// Vertical offset from the monitor's top edge (default: 0)
+    #[serde(default)]
+    pub offset_y: i32,
+}
+
+/// Parameters for window_move_by tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowMoveByParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Pixels to move right (negative moves left)
+    #[serde(default)]
+    pub dx: i32,
+    /// Pixels to move down (negative moves up)
+    #[serde(default)]
+    pub dy: i32,
+}
+
+/// Parameters for window_resize_by tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowResizeByParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Pixels to add to the width (negative shrinks)
+    #[serde(default)]
+    pub dwidth: i32,
+    /// Pixels to add to the height (negative shrinks)
+    #[serde(default)]
+    pub dheight: i32,
+}
+
+/// Smallest size window_resize_by will shrink a window to
+const MIN_WINDOW_SIZE: u32 = 1;
+
+/// Parameters for window_center tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowCenterParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+}
+
+/// Parameters for window_snap tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowSnapParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Target area: left, right, top, bottom, top-left, top-right, bottom-left, bottom-right, or full
+    pub position: SnapPosition,
+}
+
+/// Parameters for window_resize tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowResizeParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// New width
+    pub width: u32,
+    /// New height
+    pub height: u32,
+}
+
+/// Parameters for window_minimize, window_maximize, and window_restore tools
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowStateParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+}
+
+/// Parameters for window_close tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowCloseParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Kill the owning client instead of asking the window to close
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Parameters for window_pin tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowPinParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// true to keep the window above others, false to return it to normal stacking
+    pub enabled: bool,
+}
+
+/// Parameters for window_raise and window_lower tools
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowRestackParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+}
+
+/// Parameters for window_fullscreen tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowFullscreenParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// true to make the window fullscreen, false to leave fullscreen
+    pub enabled: bool,
+}
+
+/// Parameters for window_opacity tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowOpacityParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Opacity from 0.0 (fully transparent) to 1.0 (opaque); out-of-range values are clamped
+    pub opacity: f64,
+}
+
+/// Parameters for desktop_switch tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DesktopSwitchParams {
+    /// Zero-based index of the desktop to switch to
+    pub desktop: u32,
+}
+
+/// Parameters for window_move_to_desktop tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowMoveToDesktopParams {
+    /// Window reference (e.g., "w0") from window_list
+    pub r#ref: String,
+    /// Zero-based index of the target desktop
+    pub desktop: u32,
+}
+
+/// One step of a window_batch call, tagged by `action`
+///
+/// Each variant takes the same fields as the matching single-action tool.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BatchAction {
+    /// Same as window_click
+    Click(WindowClickParams),
+    /// Same as window_type
+    Type(WindowTypeParams),
+    /// Same as window_key
+    Key(WindowKeyParams),
+    /// Same as window_move
+    Move(WindowMoveParams),
+    /// Same as window_resize
+    Resize(WindowResizeParams),
+    /// Same as window_focus
+    Focus(WindowFocusParams),
+    /// Same as window_screenshot
+    Screenshot(WindowScreenshotParams),
+}
+
+impl BatchAction {
+    /// The standalone tool this step runs, which must be enabled for the step to run
+    fn tool(&self) -> &'static str {
+        match self {
+            Self::Click(_) => "window_click",
+            Self::Type(_) => "window_type",
+            Self::Key(_) => "window_key",
+            Self::Move(_) => "window_move",
+            Self::Resize(_) => "window_resize",
+            Self::Focus(_) => "window_focus",
+            Self::Screenshot(_) => "window_screenshot",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Click(_) => "click",
+            Self::Type(_) => "type",
+            Self::Key(_) => "key",
+            Self::Move(_) => "move",
+            Self::Resize(_) => "resize",
+            Self::Focus(_) => "focus",
+            Self::Screenshot(_) => "screenshot",
+        }
+    }
+}
+
+/// Parameters for window_batch tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WindowBatchParams {
+    /// Actions to run in order
+    pub actions: Vec<BatchAction>,
+    /// Keep going after a failed step instead of stopping (default: false)
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+/// Window state change requested by a state tool
+#[derive(Debug, Clone, Copy)]
+enum WindowStateAction {
+    Minimize,
+    Maximize,
+    Restore,
+}
+
+/// Build capture options from the parameters shared by the screenshot tools
+fn capture_options(encoding: &str, quality: u8, max_width: Option<u32>, max_height: Option<u32>) -> Result<CaptureOptions, MarionetteError> {
+    let encoding = ImageEncoding::parse(encoding, quality)
+        .map_err(|e| MarionetteError::invalid_params(e.to_string(), Some("Use \"png\", \"jpeg\", or \"webp\"")))?;
+
+    Ok(CaptureOptions {
+        encoding,
+        max_width,
+        max_height,
+        cursor: None,
+        crop: None,
+    })
+}
+
+/// Build the tool result for a captured image, either inline or saved to a file
+///
+/// `name` is used in the temp file name; `info` is an object merged into the
+/// JSON returned for the "file" format.
+fn screenshot_result(image: &CapturedImage, encoding: ImageEncoding, format: &str, dir: &Path, name: &str, info: serde_json::Value) -> Result<CallToolResult, MarionetteError> {
+    if format == "file" {
+        // Save to the screenshot directory, then prune old captures there
+        let path = crate::screenshot::files::file_path(dir, name, encoding.extension());
+        std::fs::write(&path, &image.data)
+            .map_err(|e| MarionetteError::screenshot("Failed to save screenshot", e))?;
+        crate::screenshot::files::cleanup(dir, &path);
+        let mut result = json!({
+            "success": true,
+            "path": path.to_string_lossy(),
+            "mime_type": encoding.mime_type(),
+            "width": image.width,
+            "height": image.height,
+            "size_bytes": image.data.len()
+        });
+        if let (Some(result), Some(info)) = (result.as_object_mut(), info.as_object()) {
+            result.extend(info.clone());
+        }
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    } else {
+        // Return base64
+        let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &image.data);
+        Ok(CallToolResult::success(vec![
+            Content::image(base64_data, encoding.mime_type())
+        ]))
+    }
+}
+
+/// Report what a mutating tool would have done instead of doing it
+///
+/// `plan` is the result the tool would return after acting.
+fn dry_run_result(mut plan: serde_json::Value) -> CallToolResult {
+    plan["dry_run"] = json!(true);
+    plan["message"] = json!("Dry run: nothing was executed");
+    CallToolResult::success(vec![Content::text(
+        serde_json::to_string_pretty(&plan).unwrap(),
+    )])
+}
+
+/// The JSON payload of a result built by one of this server's tools
+fn result_json(result: &CallToolResult) -> serde_json::Value {
+    result
+        .content
+        .iter()
+        .find_map(|content| content.as_text())
+        .and_then(|text| serde_json::from_str(&text.text).ok())
+        .unwrap_or(serde_json::Value::Null)
+}
+
+/// Whether two peer handles belong to the same client session
+///
+/// `Peer` has no identity of its own, but every handle for a session shares
+/// one client info cell, set during initialization, so its address tells
+/// sessions apart.
+fn same_peer(a: &Peer<RoleServer>, b: &Peer<RoleServer>) -> bool {
+    match (a.peer_info(), b.peer_info()) {
+        (Some(a), Some(b)) => std::ptr::eq(a, b),
+        _ => false,
+    }
+}
+
+/// Tool names from a comma-separated environment variable, or None if it is unset
+fn tool_names_from_env(name: &str) -> Option<Vec<String>> {
+    let value = std::env::var(name).ok()?;
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|tool| !tool.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Remove tools the operator hasn't allowed from the router
+///
+/// Removed tools aren't advertised by list_tools, and calls to them fail as
+/// unknown tools. With `enabled`, only the listed tools are kept; `disabled`
+/// tools are then removed, so a tool in both lists is disabled.
+fn filter_tools(router: &mut ToolRouter<MarionetteServer>, enabled: Option<Vec<String>>, disabled: Vec<String>) {
+    for tool in enabled.iter().flatten().chain(&disabled) {
+        if !router.has_route(tool) {
+            tracing::warn!("Ignoring unknown tool in tool filter: {}", tool);
+        }
+    }
+
+    if let Some(enabled) = enabled {
+        router.map.retain(|name, _| enabled.iter().any(|tool| tool == name));
+    }
+    for tool in &disabled {
+        router.remove_route(tool);
+    }
+}
+
+/// Serialize an accessibility subtree with bounds relative to the window
+///
+/// Returns None for elements left out by `showing_only`.
+fn accessible_json(tree: &AccessibleTree, index: usize, window: &WindowHandle, showing_only: bool) -> Option<serde_json::Value> {
+    let node = &tree.nodes[index];
+    if showing_only && !node.showing && index != 0 {
+        return None;
+    }
+
+    let children: Vec<serde_json::Value> = node
+        .children
+        .iter()
+        .filter_map(|&child| accessible_json(tree, child, window, showing_only))
+        .collect();
+
+    let mut value = json!({
+        "role": node.role,
+        "name": node.name,
+        "bounds": node.bounds.map(|b| json!({
+            "x": b.x - window.geometry.x,
+            "y": b.y - window.geometry.y,
+            "width": b.width,
+            "height": b.height
+        })),
+        "showing": node.showing
+    });
+    if !children.is_empty() {
+        value["children"] = json!(children);
+    }
+    Some(value)
+}
+
+/// Window-relative and screen-absolute forms of a point given in `space`
+fn resolve_point(window: &WindowHandle, x: i32, y: i32, space: CoordinateSpace) -> ((i32, i32), (i32, i32)) {
+    let origin = (window.geometry.x, window.geometry.y);
+    match space {
+        CoordinateSpace::Window => ((x, y), (origin.0 + x, origin.1 + y)),
+        CoordinateSpace::Screen => ((x - origin.0, y - origin.1), (x, y)),
+    }
+}
+
+/// Reject window-relative coordinates that fall outside the window, unless allowed
+///
+/// Out-of-bounds input lands on whatever is on screen there, often another app.
+fn check_in_bounds(window: &WindowHandle, x: i32, y: i32, allow_out_of_bounds: bool) -> Result<(), MarionetteError> {
+    let inside = x >= 0
+        && y >= 0
+        && (x as i64) < window.geometry.width as i64
+        && (y as i64) < window.geometry.height as i64;
+    if inside || allow_out_of_bounds {
+        return Ok(());
+    }
+
+    Err(MarionetteError::invalid_params(
+        format!(
+            "Coordinates ({}, {}) are outside window {} (valid: x 0..{}, y 0..{})",
+            x, y, window.ref_id, window.geometry.width, window.geometry.height
+        ),
+        Some("Use coordinates within the window, refresh its geometry with window_list, or set allow_out_of_bounds"),
+    ))
+}
+
+/// Find a monitor by index (as listed by monitor_list) or name
+fn find_monitor<'a>(monitors: &'a [MonitorInfo], selector: &str) -> Option<&'a MonitorInfo> {
+    match selector.parse::<usize>() {
+        Ok(index) => monitors.get(index),
+        Err(_) => monitors.iter().find(|m| m.name == selector),
+    }
+}
+
+/// Windows matching a title and/or class substring, sorted by ref
+///
+/// When both are given, windows must match both.
+fn find_windows<'a>(registry: &'a WindowRegistry, title: Option<&str>, class: Option<&str>) -> Vec<&'a WindowHandle> {
+    // Intersect title and class matches; both lists are already sorted by ref
+    let by_title = title.map(|t| registry.find_by_title(t));
+    let by_class = class.map(|c| registry.find_by_class(c));
+    match (by_title, by_class) {
+        (Some(titles), Some(classes)) => titles
+            .into_iter()
+            .filter(|w| classes.iter().any(|c| c.ref_id == w.ref_id))
+            .collect(),
+        (Some(found), None) | (None, Some(found)) => found,
+        (None, None) => Vec::new(),
+    }
+}
+
+/// URI of a window's own resource
+fn window_resource_uri(window_ref: &str) -> String {
+    format!("{}/{}", WINDOWS_RESOURCE_URI, window_ref)
+}
+
+/// Whether a window's type passes window_list's include and exclude filters
+fn window_type_allowed(window: &WindowHandle, include_types: &[String], exclude_types: &[String]) -> bool {
+    // Backends that don't report a type never filter on it
+    window.window_type.as_ref().is_none_or(|t| {
+        (include_types.is_empty() || include_types.contains(t)) && !exclude_types.contains(t)
+    })
+}
+
+/// Serialize a window's metadata for tool results
+fn window_json(window: &WindowHandle) -> serde_json::Value {
+    json!({
+        "ref": window.ref_id,
+        "title": window.title,
+        "class": window.class,
+        "instance": window.instance,
+        "geometry": {
+            "x": window.geometry.x,
+            "y": window.geometry.y,
+            "width": window.geometry.width,
+            "height": window.geometry.height
+        },
+        "frame": window.frame.map(|frame| json!({
+            "x": frame.x,
+            "y": frame.y,
+            "width": frame.width,
+            "height": frame.height
+        })),
+        "focused": window.focused,
+        "visible": window.visible,
+        "stacking_index": window.stacking_index,
+        "pid": window.pid,
+        "desktop": window.desktop,
+        "type": window.window_type,
+        "minimized": window.minimized,
+        "maximized_horz": window.maximized_horz,
+        "maximized_vert": window.maximized_vert,
+        "fullscreen": window.fullscreen
+    })
+}
 
 /// Marionette MCP Server
 ///
@@ -135,6 +1210,36 @@ pub struct MarionetteServer {
     registry: Arc<RwLock<WindowRegistry>>,
     /// Platform-specific window backend
     backend: Arc<dyn WindowBackend>,
+    /// Tool used to inject pointer and keyboard input
+    input: Arc<dyn InputBackend>,
+    /// Keys currently held down by window_key_down, in press order
+    held_keys: Arc<Mutex<Vec<HeldKey>>>,
+    /// Mouse buttons held down by a drag in progress
+    pressed_buttons: Arc<std::sync::Mutex<Vec<String>>>,
+    /// Bumped by the cancel tool; long input operations stop once it differs from its value when they began
+    cancel_generation: Arc<AtomicU64>,
+    /// Set while the operator's emergency-stop hotkey has input disabled
+    input_stopped: Arc<AtomicBool>,
+    /// Last raw capture taken by window_screenshot_diff, keyed by ref
+    last_captures: Arc<RwLock<HashMap<String, RgbaImage>>>,
+    /// Screenshots taken with the "resource" format, served as marionette://screenshots/<id>
+    screenshots: Arc<std::sync::Mutex<ScreenshotStore>>,
+    /// Directory "file" format screenshots are saved to, from MARIONETTE_SCREENSHOT_DIR
+    screenshot_dir: PathBuf,
+    /// Clients subscribed to the window list resource (one per session over HTTP)
+    windows_subscribers: Arc<Mutex<Vec<Peer<RoleServer>>>>,
+    /// Throttles input tools so a runaway client can't flood the desktop
+    rate_limiter: Arc<std::sync::Mutex<RateLimiter>>,
+    /// Report what input and window-management tools would do instead of doing it
+    dry_run: bool,
+    /// Delay between typed characters when a call doesn't give one
+    type_delay_ms: u32,
+    /// Pointer scale override from MARIONETTE_POINTER_SCALE
+    pointer_scale: Option<f64>,
+    /// Keyboard layout detected at startup, if it could be determined
+    keyboard_layout: Option<crate::input::layout::KeyboardLayout>,
+    /// Audit log of tool calls, if MARIONETTE_ACTION_LOG is set
+    action_log: Option<Arc<ActionLog>>,
     /// MCP tool router
     tool_router: ToolRouter<MarionetteServer>,
 }
@@ -144,403 +1249,2798 @@ impl MarionetteServer {
     /// Create a new Marionette server
     pub async fn new() -> anyhow::Result<Self> {
         let backend = crate::backend::create_backend().await?;
+        let input_config = crate::input::InputConfig::from_env();
+        let type_delay_ms = input_config.timing.type_delay_ms;
+        let pointer_scale = input_config.pointer_scale;
+        let input = crate::input::create_input_backend(input_config);
+
+        // Saving refs is opt-in: X11 may recycle window IDs across sessions
+        let mut registry = match std::env::var_os("MARIONETTE_PERSIST_REFS") {
+            Some(_) => match WindowRegistry::default_state_path() {
+                Some(path) => WindowRegistry::load_or_new(path),
+                None => {
+                    tracing::warn!("MARIONETTE_PERSIST_REFS is set but neither XDG_STATE_HOME nor HOME is; refs won't persist");
+                    WindowRegistry::new()
+                }
+            },
+            None => WindowRegistry::new(),
+        };
+        registry.set_log_changes(std::env::var_os("MARIONETTE_LOG_WINDOW_CHANGES").is_some());
+
+        let watch_interval_ms = std::env::var("MARIONETTE_WATCH_INTERVAL_MS")
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .filter(|&ms| ms > 0)
+            .unwrap_or(DEFAULT_WATCH_INTERVAL_MS);
+
+        let rate_limit = std::env::var("MARIONETTE_RATE_LIMIT")
+            .ok()
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|rate| rate.is_finite() && *rate >= 0.0)
+            .unwrap_or(DEFAULT_INPUT_RATE_LIMIT);
+
+        let dry_run = std::env::var_os("MARIONETTE_DRY_RUN").is_some();
+        if dry_run {
+            tracing::warn!("MARIONETTE_DRY_RUN is set; input and window-management tools will not execute");
+        }
+
+        let keyboard_layout = crate::input::layout::detect_layout().await;
+        match &keyboard_layout {
+            Some(layout) if !layout.is_us_qwerty() && input.name() == "ydotool" => tracing::warn!(
+                "Keyboard layout is {}; ydotool types as if it were US QWERTY, so window_type may produce \
+                 wrong characters. Use method \"clipboard\" for reliable text entry",
+                layout
+            ),
+            Some(layout) => tracing::info!("Keyboard layout: {}", layout),
+            None => tracing::debug!("Could not detect keyboard layout"),
+        }
+
+        let input_stopped = Arc::new(AtomicBool::new(false));
+        if let Some(hotkey) = std::env::var("MARIONETTE_STOP_HOTKEY").ok().filter(|hotkey| !hotkey.is_empty()) {
+            // Fail startup rather than leave the operator trusting a stop that isn't armed
+            if crate::backend::is_wayland() {
+                anyhow::bail!(
+                    "MARIONETTE_STOP_HOTKEY is set, but global hotkeys are only supported on X11. \
+                     Unset it and bind a compositor shortcut that stops the server instead"
+                );
+            }
+            crate::backend::hotkey::spawn_stop_hotkey(&hotkey, input_stopped.clone())
+                .map_err(|e| anyhow::anyhow!("Emergency-stop hotkey {} could not be armed: {}", hotkey, e))?;
+        }
+
+        let action_log = ActionLog::from_env()?.map(Arc::new);
+        if let Some(log) = &action_log {
+            tracing::info!("Recording tool calls to {}", log.path().display());
+        }
+
+        let mut tool_router = Self::tool_router();
+        let tool_count = tool_router.map.len();
+        filter_tools(
+            &mut tool_router,
+            tool_names_from_env("MARIONETTE_ENABLED_TOOLS"),
+            tool_names_from_env("MARIONETTE_DISABLED_TOOLS").unwrap_or_default(),
+        );
+        if tool_router.map.len() < tool_count {
+            let mut tools: Vec<&str> = tool_router.map.keys().map(|name| name.as_ref()).collect();
+            tools.sort_unstable();
+            tracing::info!("Tool filter applied; enabled tools: {}", tools.join(", "));
+        }
 
-        Ok(Self {
-            registry: Arc::new(RwLock::new(WindowRegistry::new())),
+        let server = Self {
+            registry: Arc::new(RwLock::new(registry)),
             backend,
-            tool_router: Self::tool_router(),
-        })
+            input,
+            held_keys: Arc::new(Mutex::new(Vec::new())),
+            pressed_buttons: Arc::new(std::sync::Mutex::new(Vec::new())),
+            cancel_generation: Arc::new(AtomicU64::new(0)),
+            input_stopped,
+            last_captures: Arc::new(RwLock::new(HashMap::new())),
+            screenshots: Arc::new(std::sync::Mutex::new(ScreenshotStore::new(MAX_STORED_SCREENSHOTS))),
+            screenshot_dir: crate::screenshot::files::screenshot_dir_from_env(),
+            windows_subscribers: Arc::new(Mutex::new(Vec::new())),
+            rate_limiter: Arc::new(std::sync::Mutex::new(RateLimiter::new(rate_limit))),
+            dry_run,
+            keyboard_layout,
+            type_delay_ms,
+            pointer_scale,
+            action_log,
+            tool_router,
+        };
+
+        server.spawn_window_watcher(std::time::Duration::from_millis(watch_interval_ms));
+        Ok(server)
     }
 
-    #[tool(description = "List all windows with their references and metadata. Returns window refs (w0, w1, ...) that can be used with other tools.")]
-    async fn window_list(
-        &self,
-        params: Parameters<WindowListParams>,
-    ) -> Result<CallToolResult, McpError> {
-        // Refresh window list from backend
-        let windows = match self.backend.list_windows().await {
-            Ok(windows) => windows,
+    /// Release every key and mouse button the server is holding down
+    ///
+    /// Called on shutdown and by cancel, so an interrupted session doesn't leave
+    /// a stuck Shift or mouse button behind. Failures are logged, and releasing
+    /// continues. Returns the released (buttons, keys).
+    pub async fn release_pressed(&self) -> (Vec<String>, Vec<String>) {
+        let buttons: Vec<String> = std::mem::take(&mut *self.pressed_buttons.lock().unwrap_or_else(|e| e.into_inner()));
+        let mut released_buttons = Vec::new();
+        for button in buttons.into_iter().rev() {
+            match self.input.button_up(&button).await {
+                Ok(()) => {
+                    tracing::info!("Released {} mouse button", button);
+                    released_buttons.push(button);
+                }
+                Err(e) => tracing::warn!("Failed to release {} mouse button: {}", button, e),
+            }
+        }
+
+        let mut held_keys = self.held_keys.lock().await;
+        let mut released_keys = Vec::new();
+        while let Some(held) = held_keys.pop() {
+            match self.input.key_up(&held.key, &held.modifiers).await {
+                Ok(()) => {
+                    tracing::info!("Released held key {}", held.key);
+                    released_keys.push(held.key);
+                }
+                Err(e) => tracing::warn!("Failed to release held key {}: {}", held.key, e),
+            }
+        }
+
+        (released_buttons, released_keys)
+    }
+
+    /// Mark the start of a cancellable operation
+    fn cancel_mark(&self) -> u64 {
+        self.cancel_generation.load(Ordering::SeqCst)
+    }
+
+    /// Whether cancel has been called since `mark` was taken
+    fn cancelled_since(&self, mark: u64) -> bool {
+        self.cancel_generation.load(Ordering::SeqCst) != mark
+    }
+
+    /// Sleep for `duration`, waking early if cancel is called
+    ///
+    /// Returns false if the sleep was cut short.
+    async fn sleep_cancellable(&self, duration: std::time::Duration, mark: u64) -> bool {
+        let deadline = tokio::time::Instant::now() + duration;
+        loop {
+            if self.cancelled_since(mark) {
+                return false;
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            let step = (deadline - now).min(std::time::Duration::from_millis(CANCEL_POLL_MS));
+            tokio::time::sleep(step).await;
+        }
+    }
+
+    /// Type text in chunks, stopping early if cancel is called
+    ///
+    /// Returns how many characters were typed.
+    async fn type_cancellable(&self, text: &str, delay_ms: u32, mark: u64) -> anyhow::Result<usize> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut typed = 0;
+        for chunk in chars.chunks(TYPE_CHUNK_CHARS) {
+            if self.cancelled_since(mark) {
+                break;
+            }
+            let chunk: String = chunk.iter().collect();
+            self.input.type_text(&chunk, delay_ms).await?;
+            typed += chunk.chars().count();
+        }
+        Ok(typed)
+    }
+
+    /// Poll the backend in the background and notify subscribers when the window list changes
+    ///
+    /// Polling only happens while a client is subscribed. Changes picked up by
+    /// tool calls are reported too, since they also advance the registry version.
+    fn spawn_window_watcher(&self, interval: std::time::Duration) {
+        let registry = self.registry.clone();
+        let backend = self.backend.clone();
+        let subscribers = self.windows_subscribers.clone();
+
+        tokio::spawn(async move {
+            let mut last_version = registry.read().await.version();
+
+            loop {
+                tokio::time::sleep(interval).await;
+
+                // Sessions that ended without unsubscribing are dropped here
+                let peers = {
+                    let mut subscribers = subscribers.lock().await;
+                    subscribers.retain(|peer| !peer.is_transport_closed());
+                    subscribers.clone()
+                };
+                if peers.is_empty() {
+                    last_version = registry.read().await.version();
+                    continue;
+                }
+
+                match backend.list_windows().await {
+                    Ok(windows) => registry.write().await.update_windows(windows),
+                    Err(e) => tracing::debug!("Window watcher failed to list windows: {}", e),
+                }
+
+                let version = registry.read().await.version();
+                if version == last_version {
+                    continue;
+                }
+                last_version = version;
+
+                for peer in &peers {
+                    let notification = ResourceUpdatedNotificationParam {
+                        uri: WINDOWS_RESOURCE_URI.to_string(),
+                    };
+                    if let Err(e) = peer.notify_resource_updated(notification).await {
+                        tracing::warn!("Failed to send window list update notification: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    /// The monitor a window overlaps most, or the primary monitor if it is off-screen
+    async fn window_monitor(&self, window: &WindowHandle) -> Result<MonitorInfo, MarionetteError> {
+        let mut monitors = self.backend.list_monitors()
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to list monitors", e))?;
+        let bounds: Vec<_> = monitors.iter().map(MonitorInfo::geometry).collect();
+
+        let index = geometry::most_overlapping(&window.geometry, &bounds)
+            .or_else(|| monitors.iter().position(|m| m.primary))
+            .unwrap_or(0);
+
+        if index >= monitors.len() {
+            return Err(MarionetteError::backend("Failed to find the window's monitor", "no monitors found"));
+        }
+        Ok(monitors.swap_remove(index))
+    }
+
+    /// Convert a point in screen coordinates to the coordinates the input backend expects
+    ///
+    /// Window geometry is logical, but ydotool positions the pointer in device
+    /// pixels, so on a scaled monitor the point is multiplied by the scale of the
+    /// monitor it lies on. `MARIONETTE_POINTER_SCALE` replaces the detected scale.
+    async fn pointer_point(&self, x: i32, y: i32) -> (i32, i32) {
+        if !self.input.pointer_uses_device_pixels() {
+            return (x, y);
+        }
+
+        let scale = match self.pointer_scale {
+            Some(scale) => scale,
+            None => match self.backend.list_monitors().await {
+                Ok(monitors) => monitors
+                    .iter()
+                    .find(|m| geometry::contains(&m.geometry(), x, y))
+                    .map_or(1.0, |m| m.scale_factor),
+                Err(e) => {
+                    tracing::debug!("Failed to list monitors for pointer scaling, assuming 1x: {}", e);
+                    1.0
+                }
+            },
+        };
+        if scale == 1.0 {
+            return (x, y);
+        }
+
+        let point = geometry::to_device_pixels(x, y, scale);
+        tracing::debug!("Scaled pointer target ({}, {}) by {} to {:?}", x, y, scale, point);
+        point
+    }
+
+    /// Refuse input while the operator's emergency-stop hotkey has it disabled
+    fn check_input_enabled(&self) -> Result<(), MarionetteError> {
+        if self.input_stopped.load(Ordering::SeqCst) {
+            return Err(MarionetteError::InputStopped);
+        }
+        Ok(())
+    }
+
+    /// Take a rate limit token for an input tool, after checking input isn't stopped
+    fn throttle(&self, tool: &'static str) -> Result<(), MarionetteError> {
+        self.check_input_enabled()?;
+        let mut limiter = self.rate_limiter.lock().unwrap_or_else(|e| e.into_inner());
+        limiter
+            .check(tool)
+            .map_err(|retry_after| MarionetteError::rate_limited(tool, retry_after))
+    }
+
+    /// Focus a window and give the window manager time to apply it before input
+    async fn focus_and_settle(&self, window: &WindowHandle) -> Result<(), MarionetteError> {
+        self.backend.focus_window(&window.platform_id)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to focus window", e))?;
+        tokio::time::sleep(std::time::Duration::from_millis(FOCUS_SETTLE_DELAY_MS)).await;
+        Ok(())
+    }
+
+    /// Re-read a window's geometry after a move or resize so later input uses the new position
+    ///
+    /// The window manager applies the request asynchronously, so poll briefly
+    /// until the geometry changes. Failures are logged rather than returned,
+    /// since the move or resize itself already succeeded. Returns the geometry
+    /// read back, which may differ from the request if the window manager clamped it.
+    async fn refresh_geometry(&self, window: &WindowHandle) -> Option<Geometry> {
+        let mut geometry = None;
+        for _ in 0..GEOMETRY_REFRESH_ATTEMPTS {
+            tokio::time::sleep(std::time::Duration::from_millis(GEOMETRY_REFRESH_INTERVAL_MS)).await;
+            match self.backend.refresh_window(&window.platform_id).await {
+                Ok(current) => {
+                    let changed = current != window.geometry;
+                    geometry = Some(current);
+                    if changed {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    tracing::debug!("Failed to refresh geometry for {}: {}", window.ref_id, e);
+                    break;
+                }
+            }
+        }
+
+        if let Some(geometry) = geometry {
+            self.registry.write().await.update_geometry(&window.ref_id, geometry);
+        }
+        geometry
+    }
+
+    /// Read a window's icon as a base64 PNG for window_list
+    ///
+    /// Windows without an icon, and failures, yield None so one bad window
+    /// doesn't fail the listing.
+    async fn window_icon_base64(&self, window: &WindowHandle) -> Option<String> {
+        let icon = match self.backend.window_icon(&window.platform_id).await {
+            Ok(icon) => icon?,
             Err(e) => {
-                return Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Failed to list windows",
-                    "details": e.to_string()
-                }).to_string())]));
+                tracing::debug!("Failed to read icon for {}: {}", window.ref_id, e);
+                return None;
             }
         };
 
-        // Update registry with new windows
-        let mut registry = self.registry.write().await;
-        registry.update_windows(windows);
+        let png = crate::screenshot::encode_image(&icon, crate::screenshot::ImageEncoding::Png).ok()?;
+        Some(base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png))
+    }
 
-        // Get filtered window list
-        let window_list: Vec<serde_json::Value> = registry
-            .windows()
-            .iter()
-            .filter(|w| {
-                let title_match = params.0.title_filter.as_ref().is_none_or(|f| {
-                    w.title.to_lowercase().contains(&f.to_lowercase())
-                });
+    /// Serialize windows as window_list returns them, with aliases, optional icons, and desktop state
+    ///
+    /// Shared with the `marionette://windows` resource so both report the same shape.
+    /// Takes windows already cloned with their aliases, so callers release the
+    /// registry lock before the icon and desktop queries reach the backend.
+    async fn window_list_json(
+        &self,
+        windows: Vec<(WindowHandle, Vec<String>)>,
+        snapshot_version: u64,
+        include_icons: bool,
+    ) -> serde_json::Value {
+        let mut window_list = Vec::with_capacity(windows.len());
+        for (window, aliases) in &windows {
+            let mut entry = window_json(window);
+            entry["aliases"] = json!(aliases);
+            if include_icons {
+                entry["icon"] = json!(self.window_icon_base64(window).await);
+            }
+            window_list.push(entry);
+        }
+
+        // Backends without virtual desktops report null for both
+        let desktops = self.backend.desktop_info().await.ok();
+
+        json!({
+            "windows": window_list,
+            "count": window_list.len(),
+            "snapshot_version": snapshot_version,
+            "desktop_current": desktops.map(|d| d.current),
+            "desktop_count": desktops.map(|d| d.count)
+        })
+    }
+
+    /// Re-enumerate windows into the registry and return it for reading
+    ///
+    /// The write lock is downgraded rather than released, so callers see exactly
+    /// the snapshot they refreshed. Drop the guard before any slow backend call.
+    async fn refresh_registry(&self) -> Result<RwLockReadGuard<'_, WindowRegistry>, MarionetteError> {
+        let windows = self.backend.list_windows()
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to list windows", e))?;
+        let mut registry = self.registry.write().await;
+        registry.update_windows(windows);
+        Ok(registry.downgrade())
+    }
+
+    /// Look up a window by ref, cloning it so the registry lock isn't held
+    async fn window(&self, window_ref: &str) -> Result<WindowHandle, MarionetteError> {
+        let registry = self.registry.read().await;
+        registry
+            .get_window(window_ref)
+            .cloned()
+            .ok_or_else(|| MarionetteError::window_not_found(window_ref))
+    }
+
+    /// Shared implementation for the minimize/maximize/restore tools
+    async fn change_window_state(
+        &self,
+        window_ref: &str,
+        action: WindowStateAction,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(window_ref).await?;
+
+        let (state, verb) = match action {
+            WindowStateAction::Minimize => ("minimized", "minimize"),
+            WindowStateAction::Maximize => ("maximized", "maximize"),
+            WindowStateAction::Restore => ("restored", "restore"),
+        };
+        let result = json!({
+            "success": true,
+            "ref": window_ref,
+            "title": window.title,
+            "state": state
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        let outcome = match action {
+            WindowStateAction::Minimize => self.backend.minimize_window(&window.platform_id).await,
+            WindowStateAction::Maximize => self.backend.maximize_window(&window.platform_id).await,
+            WindowStateAction::Restore => self.backend.restore_window(&window.platform_id).await,
+        };
+        outcome.map_err(|e| MarionetteError::backend(format!("Failed to {} window", verb), e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "List all windows with their references and metadata. Returns window refs (w0, w1, ...) that can be used with other tools.")]
+    async fn window_list(
+        &self,
+        params: Parameters<WindowListParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let by_stacking = match params.0.sort.as_str() {
+            "ref" => false,
+            "stacking" => true,
+            other => {
+                return Err(MarionetteError::invalid_params(
+                    format!("Unknown sort order: {}", other),
+                    Some("Use \"ref\" or \"stacking\""),
+                ).into());
+            }
+        };
+
+        let include_types: Vec<String> = params.0.include_types.clone().unwrap_or_else(|| {
+            DEFAULT_WINDOW_TYPES.iter().map(|t| t.to_string()).collect()
+        });
+        validate_window_types(&include_types)?;
+        validate_window_types(&params.0.exclude_types)?;
+        let title_regex = compile_filter_regex(params.0.title_regex.as_deref(), "title_regex")?;
+        let class_regex = compile_filter_regex(params.0.class_regex.as_deref(), "class_regex")?;
+
+        // Refresh window list from backend
+        let registry = self.refresh_registry().await?;
+
+        let sorted = if by_stacking {
+            registry.windows_by_stacking()
+        } else {
+            registry.windows()
+        };
+
+        // Get filtered window list, cloned so the registry isn't locked during backend calls
+        let filtered: Vec<(WindowHandle, Vec<String>)> = sorted
+            .into_iter()
+            .filter(|w| {
+                let title_match = params.0.title_filter.as_ref().is_none_or(|f| {
+                    w.title.to_lowercase().contains(&f.to_lowercase())
+                });
                 let class_match = params.0.class_filter.as_ref().is_none_or(|f| {
-                    w.class.to_lowercase().contains(&f.to_lowercase())
+                    let f = f.to_lowercase();
+                    w.class.to_lowercase().contains(&f) || w.instance.to_lowercase().contains(&f)
+                });
+                let title_regex_match = title_regex.as_ref().is_none_or(|re| re.is_match(&w.title));
+                let class_regex_match = class_regex
+                    .as_ref()
+                    .is_none_or(|re| re.is_match(&w.class) || re.is_match(&w.instance));
+                let pid_match = params.0.pid_filter.is_none_or(|pid| w.pid == Some(pid));
+                let type_match = window_type_allowed(w, &include_types, &params.0.exclude_types);
+                title_match && class_match && title_regex_match && class_regex_match && pid_match && type_match
+            })
+            .map(|w| (w.clone(), registry.aliases_of(&w.ref_id)))
+            .collect();
+        let snapshot_version = registry.version();
+        drop(registry);
+
+        let result = self.window_list_json(filtered, snapshot_version, params.0.include_icons).await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Find windows by title and/or class substring and return their refs, without a separate window_list call")]
+    async fn window_find(
+        &self,
+        params: Parameters<WindowFindParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.0.title.is_none() && params.0.class.is_none() {
+            return Err(MarionetteError::invalid_params("No search criteria", Some("Provide title and/or class")).into());
+        }
+
+        // Refresh window list from backend
+        let registry = self.refresh_registry().await?;
+
+        let matches = find_windows(&registry, params.0.title.as_deref(), params.0.class.as_deref());
+
+        let mut result = json!({
+            "matches": matches.iter().map(|w| window_json(w)).collect::<Vec<_>>(),
+            "count": matches.len(),
+            "snapshot_version": registry.version()
+        });
+        match matches.as_slice() {
+            [only] => {
+                result["hint"] = json!(format!("Exactly one match; use ref \"{}\" directly with other tools", only.ref_id));
+            }
+            [] => {
+                result["hint"] = json!("No windows matched; run window_list to see all windows");
+            }
+            _ => {}
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Wait for a window matching title and/or class to appear, e.g. after launching an app. Polls until a match is found or timeout_ms elapses.")]
+    async fn window_wait(
+        &self,
+        params: Parameters<WindowWaitParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.0.title.is_none() && params.0.class.is_none() {
+            return Err(MarionetteError::invalid_params("No search criteria", Some("Provide title and/or class")).into());
+        }
+
+        let timeout_ms = params.0.timeout_ms.min(MAX_WAIT_TIMEOUT_MS);
+        let start = std::time::Instant::now();
+        let deadline = start + std::time::Duration::from_millis(timeout_ms);
+
+        loop {
+            // Each iteration is one refresh; the lock is released before
+            // sleeping so other tools aren't blocked
+            let registry = self.refresh_registry().await?;
+
+            let matches = find_windows(&registry, params.0.title.as_deref(), params.0.class.as_deref());
+            let waited_ms = start.elapsed().as_millis() as u64;
+
+            if let Some(first) = matches.first() {
+                let result = json!({
+                    "matched": true,
+                    "ref": first.ref_id,
+                    "matches": matches.iter().map(|w| window_json(w)).collect::<Vec<_>>(),
+                    "waited_ms": waited_ms,
+                    "snapshot_version": registry.version()
+                });
+                return Ok(CallToolResult::success(vec![Content::text(
+                    serde_json::to_string_pretty(&result).unwrap(),
+                )]));
+            }
+
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                let result = json!({
+                    "matched": false,
+                    "ref": null,
+                    "waited_ms": waited_ms,
+                    "snapshot_version": registry.version(),
+                    "hint": "No window matched before the timeout; increase timeout_ms or check the filters with window_list"
+                });
+                return Ok(CallToolResult::success(vec![Content::text(
+                    serde_json::to_string_pretty(&result).unwrap(),
+                )]));
+            }
+            drop(registry);
+
+            let interval = std::time::Duration::from_millis(WAIT_POLL_INTERVAL_MS);
+            tokio::time::sleep(interval.min(deadline - now)).await;
+        }
+    }
+
+    #[tool(description = "Wait until a window's state settles: its title contains text, it gains or loses focus, and/or its geometry stops changing. Reports if the window disappears while waiting.")]
+    async fn window_wait_state(
+        &self,
+        params: Parameters<WindowWaitStateParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = &params.0;
+        if p.title_contains.is_none() && p.focused.is_none() && p.geometry_stable_ms.is_none() {
+            return Err(MarionetteError::invalid_params(
+                "No condition given",
+                Some("Provide title_contains, focused, and/or geometry_stable_ms"),
+            ).into());
+        }
+
+        // Fail fast on refs that were never valid, rather than reporting a disappearance
+        let window = self.window(&p.r#ref).await?;
+
+        let timeout_ms = p.timeout_ms.min(MAX_WAIT_TIMEOUT_MS);
+        let start = std::time::Instant::now();
+        let deadline = start + std::time::Duration::from_millis(timeout_ms);
+
+        let mut last_geometry = window.geometry;
+        let mut geometry_changed_at = start;
+
+        loop {
+            let registry = self.refresh_registry().await?;
+
+            let now = std::time::Instant::now();
+            let waited_ms = start.elapsed().as_millis() as u64;
+
+            let Some(window) = registry.get_window(&p.r#ref) else {
+                let result = json!({
+                    "status": "window_disappeared",
+                    "ref": p.r#ref,
+                    "waited_ms": waited_ms,
+                    "message": "The window was closed or destroyed while waiting"
+                });
+                return Ok(CallToolResult::success(vec![Content::text(
+                    serde_json::to_string_pretty(&result).unwrap(),
+                )]));
+            };
+
+            if window.geometry != last_geometry {
+                last_geometry = window.geometry;
+                geometry_changed_at = now;
+            }
+
+            let title_met = p.title_contains.as_ref().is_none_or(|t| {
+                window.title.to_lowercase().contains(&t.to_lowercase())
+            });
+            let focus_met = p.focused.is_none_or(|f| window.focused == f);
+            let geometry_met = p.geometry_stable_ms.is_none_or(|stable_ms| {
+                now.duration_since(geometry_changed_at).as_millis() as u64 >= stable_ms
+            });
+
+            let met = title_met && focus_met && geometry_met;
+            if met || now >= deadline {
+                let mut result = json!({
+                    "status": if met { "met" } else { "timeout" },
+                    "window": window_json(window),
+                    "waited_ms": waited_ms,
+                    "snapshot_version": registry.version()
                 });
-                title_match && class_match
+                if !met {
+                    result["unmet"] = json!([
+                        (!title_met).then_some("title_contains"),
+                        (!focus_met).then_some("focused"),
+                        (!geometry_met).then_some("geometry_stable_ms"),
+                    ].into_iter().flatten().collect::<Vec<_>>());
+                }
+                return Ok(CallToolResult::success(vec![Content::text(
+                    serde_json::to_string_pretty(&result).unwrap(),
+                )]));
+            }
+            drop(registry);
+
+            let interval = std::time::Duration::from_millis(WAIT_POLL_INTERVAL_MS);
+            tokio::time::sleep(interval.min(deadline - now)).await;
+        }
+    }
+
+    #[tool(description = "Get the currently focused window. Returns focused: null when no window has focus (e.g., the desktop).")]
+    async fn window_active(&self) -> Result<CallToolResult, McpError> {
+        // Refresh so the focus state is current and the ref matches window_list
+        let registry = self.refresh_registry().await?;
+
+        let focused = registry
+            .windows()
+            .into_iter()
+            .find(|w| w.focused)
+            .map(window_json);
+
+        let result = json!({
+            "focused": focused,
+            "snapshot_version": registry.version()
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Report which window and input backends are in use and the session environment they were chosen from. Use this to diagnose focus or input problems.")]
+    async fn backend_info(&self) -> Result<CallToolResult, McpError> {
+        let env = |name: &str| std::env::var(name).ok();
+
+        let result = json!({
+            "backend": self.backend.name(),
+            "input_backend": self.input.name(),
+            "session_type": crate::backend::session_type(),
+            "keyboard_layout": self.keyboard_layout.as_ref().map(|layout| layout.to_string()),
+            "env": {
+                "DISPLAY": env("DISPLAY"),
+                "WAYLAND_DISPLAY": env("WAYLAND_DISPLAY"),
+                "XDG_SESSION_TYPE": env("XDG_SESSION_TYPE"),
+                "XDG_CURRENT_DESKTOP": env("XDG_CURRENT_DESKTOP"),
+                "SWAYSOCK": env("SWAYSOCK"),
+                "I3SOCK": env("I3SOCK"),
+                "HYPRLAND_INSTANCE_SIGNATURE": env("HYPRLAND_INSTANCE_SIGNATURE")
+            }
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Run self-checks on the window backend, input tool, and screenshot capture and report pass/fail for each, e.g. to find out why input or focus isn't working")]
+    async fn diagnostics(&self) -> Result<CallToolResult, McpError> {
+        let checks = [
+            ("window_backend", self.backend.name(), self.backend.health_check().await),
+            ("input", self.input.name(), self.input.health_check().await),
+            ("screenshot", "xcap", crate::screenshot::health_check().await),
+        ];
+
+        let all_ok = checks.iter().all(|(_, _, outcome)| outcome.is_ok());
+        let checks: Vec<serde_json::Value> = checks
+            .into_iter()
+            .map(|(check, using, outcome)| match outcome {
+                Ok(detail) => json!({ "check": check, "using": using, "ok": true, "detail": detail }),
+                Err(e) => json!({ "check": check, "using": using, "ok": false, "detail": e.to_string() }),
             })
-            .map(|w| {
+            .collect();
+
+        let result = json!({
+            "all_ok": all_ok,
+            "checks": checks
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "List monitors/outputs with their name, origin, size, scale factor, and whether they are primary. Use origins to compute absolute coordinates for window_move.")]
+    async fn monitor_list(&self) -> Result<CallToolResult, McpError> {
+        let monitors = self.backend.list_monitors()
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to list monitors", e))?;
+
+        let monitor_list: Vec<serde_json::Value> = monitors
+            .iter()
+            .enumerate()
+            .map(|(index, m)| {
                 json!({
-                    "ref": w.ref_id,
-                    "title": w.title,
-                    "class": w.class,
+                    "index": index,
+                    "name": m.name,
                     "geometry": {
-                        "x": w.geometry.x,
-                        "y": w.geometry.y,
-                        "width": w.geometry.width,
-                        "height": w.geometry.height
+                        "x": m.x,
+                        "y": m.y,
+                        "width": m.width,
+                        "height": m.height
                     },
-                    "focused": w.focused,
-                    "visible": w.visible
+                    "scale_factor": m.scale_factor,
+                    "primary": m.primary
                 })
             })
             .collect();
 
         let result = json!({
-            "windows": window_list,
-            "count": window_list.len(),
-            "snapshot_version": registry.version()
+            "monitors": monitor_list,
+            "count": monitor_list.len()
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Get detailed snapshot of a specific window's current state")]
+    async fn window_snapshot(
+        &self,
+        params: Parameters<WindowSnapshotParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.0.refresh {
+            let _ = self.refresh_registry().await?;
+        }
+
+        let window = self.window(&params.0.r#ref).await?;
+
+        let mut result = window_json(&window);
+        result["platform_id"] = json!(format!("{:?}", window.platform_id));
+        result["refreshed"] = json!(params.0.refresh);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Check whether a window ref is still valid, without fetching the window list. Set verify to re-enumerate windows first, since the cached registry can be stale.")]
+    async fn window_exists(
+        &self,
+        params: Parameters<WindowExistsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.0.verify {
+            let _ = self.refresh_registry().await?;
+        }
+
+        let exists = self.registry.read().await.get_window(&params.0.r#ref).is_some();
+
+        let result = json!({
+            "ref": params.0.r#ref,
+            "exists": exists,
+            "verified": params.0.verify
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Give a window a human-readable alias (e.g. \"editor\") that any tool accepts in place of its ref, or omit alias to remove the window's aliases. Aliases are dropped when the window closes.")]
+    async fn window_alias(
+        &self,
+        params: Parameters<WindowAliasParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+        let mut registry = self.registry.write().await;
+
+        let result = match &params.0.alias {
+            Some(alias) => {
+                registry
+                    .set_alias(&window.ref_id, alias)
+                    .map_err(|e| MarionetteError::invalid_params(e, Some("Use a name that isn't of the form w<number>")))?;
+                json!({
+                    "success": true,
+                    "ref": window.ref_id,
+                    "aliases": registry.aliases_of(&window.ref_id)
+                })
+            }
+            None => json!({
+                "success": true,
+                "ref": window.ref_id,
+                "removed": registry.clear_aliases(&window.ref_id),
+                "aliases": []
+            }),
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    /// Focus a window (shared by window_focus and window_batch)
+    async fn focus(&self, params: WindowFocusParams) -> Result<CallToolResult, MarionetteError> {
+        let window = self.window(&params.r#ref).await?;
+
+        if self.dry_run {
+            return Ok(dry_run_result(json!({
+                "success": true,
+                "ref": params.r#ref,
+                "title": window.title
+            })));
+        }
+
+        let outcome = self.backend.focus_window(&window.platform_id)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to focus window", e))?;
+
+        let result = json!({
+            "success": true,
+            "ref": params.r#ref,
+            "title": window.title,
+            "attempts": outcome.attempts,
+            "verified": outcome.verified,
+            "message": format!("Focused window: {}", window.title)
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Focus/activate a specific window, bringing it to the foreground")]
+    async fn window_focus(
+        &self,
+        params: Parameters<WindowFocusParams>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.focus(params.0).await?)
+    }
+
+    /// Capture a window (shared by window_screenshot and window_batch)
+    async fn screenshot_window(&self, params: WindowScreenshotParams) -> Result<CallToolResult, MarionetteError> {
+        let window = self.window(&params.r#ref).await?;
+
+        let mut options = capture_options(&params.encoding, params.quality, params.max_width, params.max_height)?;
+
+        // Any region field selects a sub-rectangle; the rest default to the window's extent
+        if params.x.is_some() || params.y.is_some() || params.width.is_some() || params.height.is_some() {
+            let x = params.x.unwrap_or(0);
+            let y = params.y.unwrap_or(0);
+            options.crop = Some(Geometry {
+                x,
+                y,
+                width: params.width.unwrap_or_else(|| (window.geometry.width as i64 - x as i64).max(0) as u32),
+                height: params.height.unwrap_or_else(|| (window.geometry.height as i64 - y as i64).max(0) as u32),
+            });
+        }
+
+        // Convert the pointer to window-relative coordinates, skipping it when outside
+        let cursor_info = if params.include_cursor {
+            match self.backend.cursor_position().await {
+                Ok((x, y)) => {
+                    let rel_x = x - window.geometry.x;
+                    let rel_y = y - window.geometry.y;
+                    let inside = rel_x >= 0
+                        && rel_y >= 0
+                        && rel_x < window.geometry.width as i32
+                        && rel_y < window.geometry.height as i32;
+                    if inside {
+                        options.cursor = Some((rel_x, rel_y));
+                        Some(json!({ "drawn": true, "window_coords": { "x": rel_x, "y": rel_y } }))
+                    } else {
+                        Some(json!({ "drawn": false, "reason": "Cursor is outside the window bounds", "screen_coords": { "x": x, "y": y } }))
+                    }
+                }
+                Err(e) => Some(json!({ "drawn": false, "reason": e.to_string() })),
+            }
+        } else {
+            None
+        };
+
+        let image = crate::screenshot::capture_window(&window.platform_id, &window.geometry, options)
+            .await
+            .map_err(|e| MarionetteError::screenshot("Failed to capture screenshot", e))?;
+
+        // Pixels per window coordinate differ from 1 on HiDPI displays and after
+        // downscaling, so report the logical size the image covers
+        let (logical_width, logical_height) = match &options.crop {
+            Some(crop) => (
+                crop.width.min((window.geometry.width as i64 - crop.x as i64).max(0) as u32),
+                crop.height.min((window.geometry.height as i64 - crop.y as i64).max(0) as u32),
+            ),
+            None => (window.geometry.width, window.geometry.height),
+        };
+        let scale = match self.window_monitor(&window).await {
+            Ok(monitor) => Some(monitor.scale_factor),
+            Err(e) => {
+                tracing::debug!("Failed to find the monitor of {}: {}", window.ref_id, e);
+                None
+            }
+        };
+
+        let mut info = json!({
+            "ref": params.r#ref,
+            "width": image.width,
+            "height": image.height,
+            "logical_width": logical_width,
+            "logical_height": logical_height,
+            "scale": scale
+        });
+        if let Some(crop) = &options.crop {
+            info["region"] = json!({ "x": crop.x, "y": crop.y, "width": crop.width, "height": crop.height });
+        }
+        if let Some(cursor_info) = &cursor_info {
+            info["cursor"] = cursor_info.clone();
+        }
+
+        if params.format == "resource" {
+            let mime_type = options.encoding.mime_type();
+            let size = image.data.len();
+            let id = self.screenshots.lock().unwrap_or_else(|e| e.into_inner()).insert(StoredScreenshot {
+                data: image.data,
+                mime_type,
+                width: image.width,
+                height: image.height,
+                source: params.r#ref.clone(),
+            });
+
+            let uri = format!("{}{}", SCREENSHOT_RESOURCE_PREFIX, id);
+            let mut link = RawResource::new(uri.clone(), format!("screenshot-{}", id));
+            link.description = Some(format!("Screenshot of {}", params.r#ref));
+            link.mime_type = Some(mime_type.to_string());
+            link.size = Some(size as u32);
+
+            info["uri"] = json!(uri);
+            info["mime_type"] = json!(mime_type);
+            info["size_bytes"] = json!(size);
+            return Ok(CallToolResult::success(vec![
+                Content::resource_link(link),
+                Content::text(info.to_string()),
+            ]));
+        }
+
+        let mut result = screenshot_result(
+            &image,
+            options.encoding,
+            &params.format,
+            &self.screenshot_dir,
+            &params.r#ref,
+            info.clone(),
+        )?;
+        // Inline images carry no JSON, so report dimensions and the cursor alongside them
+        if params.format != "file" {
+            result.content.push(Content::text(info.to_string()));
+        }
+        Ok(result)
+    }
+
+    #[tool(description = "Capture a screenshot of a specific window")]
+    async fn window_screenshot(
+        &self,
+        params: Parameters<WindowScreenshotParams>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.screenshot_window(params.0).await?)
+    }
+
+    #[tool(description = "Capture every visible window as a thumbnail and return them in one PNG grid, each labeled with its ref. Useful for getting oriented before picking a window to work with.")]
+    async fn windows_overview(
+        &self,
+        params: Parameters<WindowsOverviewParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let max_windows = params.0.max_windows.clamp(1, crate::screenshot::MAX_OVERVIEW_WINDOWS);
+        let thumbnail_size = params.0.thumbnail_size.clamp(
+            MIN_OVERVIEW_THUMBNAIL_SIZE,
+            crate::screenshot::MAX_OVERVIEW_THUMBNAIL_SIZE,
+        );
+
+        // Minimized and hidden windows would only capture as blank thumbnails
+        let (visible, hidden_count) = {
+            let registry = self.refresh_registry().await?;
+            let all = registry.windows();
+            let visible: Vec<WindowHandle> = all.iter().filter(|w| w.visible).map(|w| (*w).clone()).collect();
+            let hidden_count = all.len() - visible.len();
+            (visible, hidden_count)
+        };
+        let omitted = visible.len().saturating_sub(max_windows);
+
+        let mut thumbnails = Vec::new();
+        let mut shown = Vec::new();
+        let mut failed = Vec::new();
+        for window in visible.into_iter().take(max_windows) {
+            match crate::screenshot::capture_window_image(&window.platform_id, &window.geometry).await {
+                Ok(image) => {
+                    thumbnails.push((window.ref_id.clone(), image));
+                    shown.push(window);
+                }
+                Err(e) => failed.push(json!({ "ref": window.ref_id, "error": e.to_string() })),
+            }
+        }
+
+        if thumbnails.is_empty() {
+            return Err(MarionetteError::screenshot(
+                "Failed to capture any window",
+                if failed.is_empty() { "no visible windows" } else { "every capture failed" },
+            ).into());
+        }
+
+        let (image, cells) = tokio::task::spawn_blocking(move || {
+            crate::screenshot::composite_overview(thumbnails, thumbnail_size)
+        })
+        .await
+        .map_err(|e| MarionetteError::screenshot("Failed to build overview", e))?
+        .map_err(|e| MarionetteError::screenshot("Failed to build overview", e))?;
+
+        let legend = json!({
+            "windows": shown.iter().zip(&cells).map(|(w, cell)| json!({
+                "ref": w.ref_id,
+                "title": w.title,
+                "class": w.class,
+                "row": cell.row,
+                "column": cell.column
+            })).collect::<Vec<_>>(),
+            "failed": failed,
+            "omitted": omitted,
+            "hidden": hidden_count,
+            "width": image.width,
+            "height": image.height
+        });
+
+        let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &image.data);
+        Ok(CallToolResult::success(vec![
+            Content::image(base64_data, ImageEncoding::Png.mime_type()),
+            Content::text(serde_json::to_string_pretty(&legend).unwrap()),
+        ]))
+    }
+
+    #[tool(description = "Capture a window and compare it with the previous window_screenshot_diff capture of the same ref. Returns the percentage of changed pixels and the window-relative bounding box of the change, e.g. to check whether a click opened a menu. The first call for a ref only records a baseline.")]
+    async fn window_screenshot_diff(
+        &self,
+        params: Parameters<WindowScreenshotDiffParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+
+        let image = crate::screenshot::capture_window_image(&window.platform_id, &window.geometry)
+            .await
+            .map_err(|e| MarionetteError::screenshot("Failed to capture screenshot", e))?;
+        let (width, height) = image.dimensions();
+
+        let previous = {
+            let mut captures = self.last_captures.write().await;
+            // Drop captures of windows that have since closed
+            let registry = self.registry.read().await;
+            captures.retain(|window_ref, _| registry.get_window(window_ref).is_some());
+            captures.insert(params.0.r#ref.clone(), image.clone())
+        };
+
+        let mut result = json!({
+            "ref": params.0.r#ref,
+            "width": width,
+            "height": height
+        });
+        match previous {
+            None => {
+                result["baseline"] = json!(true);
+                result["message"] = json!("No previous capture for this ref; recorded a baseline to compare the next call against");
+            }
+            Some(previous) => {
+                let diff = tokio::task::spawn_blocking(move || {
+                    crate::screenshot::diff_images(&previous, &image, params.0.tolerance)
+                })
+                .await
+                .map_err(|e| MarionetteError::screenshot("Failed to compare screenshots", e))?;
+
+                result["baseline"] = json!(false);
+                match diff {
+                    Some(diff) => {
+                        result["changed"] = json!(diff.changed_pixels > 0);
+                        result["changed_percent"] = json!(diff.changed_percent());
+                        result["changed_pixels"] = json!(diff.changed_pixels);
+                        result["bounds"] = json!(diff.bounds.map(|b| json!({
+                            "x": b.x,
+                            "y": b.y,
+                            "width": b.width,
+                            "height": b.height
+                        })));
+                    }
+                    // A resized window can't be compared pixel by pixel
+                    None => {
+                        result["changed"] = json!(true);
+                        result["changed_percent"] = json!(100.0);
+                        result["size_changed"] = json!(true);
+                    }
+                }
+            }
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Read the text in a window with OCR (tesseract). Returns each word with a window-relative bounding box that can be passed straight to window_click.")]
+    async fn window_ocr(
+        &self,
+        params: Parameters<WindowOcrParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+
+        let words = crate::screenshot::ocr::ocr_window(&window.platform_id, &window.geometry, &params.0.lang)
+            .await
+            .map_err(|e| MarionetteError::screenshot("Failed to read text from window", e))?;
+
+        let text = words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" ");
+        let result = json!({
+            "ref": params.0.r#ref,
+            "lang": params.0.lang,
+            "text": text,
+            "words": words,
+            "count": words.len()
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Find a template image (e.g. a cropped icon or button) inside a window. Returns the window-relative center of the best match, ready for window_click, or found: false if nothing matches above the threshold.")]
+    async fn window_find_image(
+        &self,
+        params: Parameters<WindowFindImageParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if !(0.0..=1.0).contains(&params.0.threshold) {
+            return Err(MarionetteError::invalid_params(
+                format!("threshold must be between 0.0 and 1.0, got {}", params.0.threshold),
+                None,
+            ).into());
+        }
+
+        let template = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, params.0.template.trim())
+            .map_err(|e| MarionetteError::invalid_params(
+                format!("template is not valid base64: {}", e),
+                Some("Pass the PNG bytes base64-encoded, without a data: URL prefix"),
+            ))?;
+
+        let window = self.window(&params.0.r#ref).await?;
+
+        let found = crate::screenshot::matching::find_in_window(&window.platform_id, &window.geometry, &template)
+            .await
+            .map_err(|e| MarionetteError::screenshot("Failed to search window for template", e))?
+            .ok_or_else(|| MarionetteError::invalid_params(
+                "Template is larger than the window",
+                Some("Crop the template from a window_screenshot of the same window"),
+            ))?;
+
+        // Below the threshold the best location is noise, so don't report one
+        let result = if found.score >= params.0.threshold {
+            json!({
+                "ref": params.0.r#ref,
+                "found": true,
+                "x": found.x + found.width / 2,
+                "y": found.y + found.height / 2,
+                "confidence": found.score,
+                "match": {
+                    "x": found.x,
+                    "y": found.y,
+                    "width": found.width,
+                    "height": found.height
+                }
+            })
+        } else {
+            json!({
+                "ref": params.0.r#ref,
+                "found": false,
+                "confidence": found.score.max(0.0),
+                "threshold": params.0.threshold
+            })
+        };
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Capture a screenshot of a whole monitor (the primary monitor by default)")]
+    async fn screen_screenshot(
+        &self,
+        params: Parameters<ScreenScreenshotParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let options = capture_options(&params.0.encoding, params.0.quality, params.0.max_width, params.0.max_height)?;
+
+        let image = crate::screenshot::capture_monitor(params.0.monitor.clone(), options)
+            .await
+            .map_err(|e| MarionetteError::screenshot("Failed to capture screenshot; run monitor_list to get valid monitor indices and names", e))?;
+
+        Ok(screenshot_result(
+            &image,
+            options.encoding,
+            &params.0.format,
+            &self.screenshot_dir,
+            "screen",
+            json!({ "monitor": params.0.monitor }),
+        )?)
+    }
+
+    #[tool(description = "Capture a screenshot of a screen region in absolute screen coordinates. A region spanning several monitors is stitched together; set monitor to capture from one monitor only.")]
+    async fn screen_region(
+        &self,
+        params: Parameters<ScreenRegionParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = &params.0;
+
+        let options = capture_options(&p.encoding, p.quality, p.max_width, p.max_height)?;
+
+        let image = crate::screenshot::capture_region(p.x, p.y, p.width, p.height, p.monitor.clone(), options)
+            .await
+            .map_err(|e| MarionetteError::screenshot("Failed to capture screenshot", e))?;
+
+        Ok(screenshot_result(
+            &image,
+            options.encoding,
+            &p.format,
+            &self.screenshot_dir,
+            "region",
+            json!({ "region": { "x": p.x, "y": p.y, "width": p.width, "height": p.height } }),
+        )?)
+    }
+
+    /// Click within a window (shared by window_click and window_batch)
+    async fn click(&self, params: WindowClickParams) -> Result<CallToolResult, MarionetteError> {
+        self.throttle("window_click")?;
+        let window = self.window(&params.r#ref).await?;
+        let ((window_x, window_y), (screen_x, screen_y)) =
+            resolve_point(&window, params.x, params.y, params.coordinate_space);
+        check_in_bounds(&window, window_x, window_y, params.allow_out_of_bounds)?;
+
+        let count = params.count.max(1);
+        let result = json!({
+            "success": true,
+            "ref": params.r#ref,
+            "window_coords": { "x": window_x, "y": window_y },
+            "screen_coords": { "x": screen_x, "y": screen_y },
+            "button": params.button,
+            "count": count,
+            "description": params.description,
+            "focused_first": params.focus_first
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        if params.focus_first {
+            self.focus_and_settle(&window).await?;
+        }
+
+        let (pointer_x, pointer_y) = self.pointer_point(screen_x, screen_y).await;
+        self.input.click(pointer_x, pointer_y, &params.button, count)
+            .await
+            .map_err(|e| MarionetteError::input("Failed to click", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    /// Walk a window's accessibility tree over AT-SPI
+    async fn accessibility_tree(&self, window: &WindowHandle, limits: TreeLimits) -> Result<AccessibleTree, MarionetteError> {
+        let client = AtspiClient::connect()
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to connect to the accessibility bus", e))?;
+        client
+            .window_tree(window.pid, &window.title, limits)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to read the accessibility tree", e))
+    }
+
+    #[tool(description = "Get a window's accessibility tree (AT-SPI): each element's role, name, and window-relative bounds, for picking click targets semantically instead of by pixel. Requires the application to expose accessibility (GTK, Qt, Firefox, and most toolkits do).")]
+    async fn window_accessibility_tree(
+        &self,
+        params: Parameters<WindowAccessibilityTreeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+        let limits = TreeLimits {
+            max_depth: params.0.max_depth,
+            max_nodes: params.0.max_nodes.clamp(1, MAX_ACCESSIBILITY_NODES),
+        };
+
+        let tree = self.accessibility_tree(&window, limits).await?;
+
+        let result = json!({
+            "ref": params.0.r#ref,
+            "tree": accessible_json(&tree, 0, &window, params.0.showing_only),
+            "node_count": tree.nodes.len(),
+            "truncated": tree.truncated
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Click an element located through the accessibility tree by role and/or name (e.g. role \"button\", name \"Save\") at its center, instead of by pixel coordinates. Use window_accessibility_tree to see available elements.")]
+    async fn window_click_element(
+        &self,
+        params: Parameters<WindowClickElementParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        if p.role.is_none() && p.name.is_none() {
+            return Err(MarionetteError::invalid_params("No element criteria", Some("Provide role and/or name")).into());
+        }
+
+        let window = self.window(&p.r#ref).await?;
+        let limits = TreeLimits {
+            max_depth: default_accessibility_max_depth(),
+            max_nodes: MAX_ACCESSIBILITY_NODES,
+        };
+        let tree = self.accessibility_tree(&window, limits).await?;
+
+        let matches = tree.find(p.role.as_deref(), p.name.as_deref());
+        let Some(element) = matches.get(p.index) else {
+            return Err(MarionetteError::invalid_params(
+                format!("No showing element matches (found {} matches, index {})", matches.len(), p.index),
+                Some("Use window_accessibility_tree to see available elements"),
+            ).into());
+        };
+        let bounds = element.bounds.expect("find only returns elements with bounds");
+
+        // Extents are in screen coordinates, so click in screen space
+        let click = WindowClickParams {
+            r#ref: p.r#ref,
+            x: bounds.x + bounds.width as i32 / 2,
+            y: bounds.y + bounds.height as i32 / 2,
+            button: p.button,
+            count: p.count,
+            description: Some(format!("{} \"{}\" ({} of {} matches)", element.role, element.name, p.index + 1, matches.len())),
+            focus_first: p.focus_first,
+            coordinate_space: CoordinateSpace::Screen,
+            allow_out_of_bounds: false,
+        };
+        Ok(self.click(click).await?)
+    }
+
+    #[tool(description = "Click at coordinates within a window. Coordinates are window-relative unless coordinate_space is \"screen\", in which case they are absolute screen coordinates. Set count to 2 for a double-click.")]
+    async fn window_click(
+        &self,
+        params: Parameters<WindowClickParams>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.click(params.0).await?)
+    }
+
+    #[tool(description = "Click a text field within a window and type into it in one call, optionally clearing its content first. Coordinates are window-relative unless coordinate_space is \"screen\".")]
+    async fn window_click_type(
+        &self,
+        params: Parameters<WindowClickTypeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let click = self.click(WindowClickParams {
+            r#ref: p.r#ref.clone(),
+            x: p.x,
+            y: p.y,
+            button: default_button(),
+            count: 1,
+            description: None,
+            focus_first: p.focus_first,
+            coordinate_space: p.coordinate_space,
+            allow_out_of_bounds: false,
+        }).await?;
+        let click = result_json(&click);
+
+        // Give the field time to take keyboard focus from the click
+        if !self.dry_run {
+            tokio::time::sleep(std::time::Duration::from_millis(FOCUS_SETTLE_DELAY_MS)).await;
+        }
+
+        let typed = self.type_text(WindowTypeParams {
+            text: p.text,
+            delay_ms: p.delay_ms,
+            method: p.method,
+            r#ref: None,
+            clear_first: p.clear_first,
+        }).await?;
+        let typed = result_json(&typed);
+
+        let mut result = json!({
+            "success": true,
+            "ref": p.r#ref,
+            "window_coords": click["window_coords"],
+            "screen_coords": click["screen_coords"],
+            "text_length": typed["text_length"],
+            "method": typed["method"],
+            "cleared": typed["cleared"]
+        });
+        for field in ["warning", "layout_warning", "success", "cancelled", "typed_chars"] {
+            if let Some(value) = typed.get(field) {
+                result[field] = value.clone();
+            }
+        }
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Move the pointer over coordinates within a window and wait, without clicking. Use to reveal tooltips and hover menus before a screenshot. Coordinates are window-relative unless coordinate_space is \"screen\".")]
+    async fn window_hover(
+        &self,
+        params: Parameters<WindowHoverParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.throttle("window_hover")?;
+        let window = self.window(&params.0.r#ref).await?;
+        let ((window_x, window_y), (screen_x, screen_y)) =
+            resolve_point(&window, params.0.x, params.0.y, params.0.coordinate_space);
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "window_coords": { "x": window_x, "y": window_y },
+            "screen_coords": { "x": screen_x, "y": screen_y },
+            "duration_ms": params.0.duration_ms
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        let (pointer_x, pointer_y) = self.pointer_point(screen_x, screen_y).await;
+        self.input.hover(pointer_x, pointer_y, params.0.duration_ms)
+            .await
+            .map_err(|e| MarionetteError::input("Failed to hover", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Scroll the mouse wheel at coordinates within a window. Positive dy scrolls down, negative dy scrolls up.")]
+    async fn window_scroll(
+        &self,
+        params: Parameters<WindowScrollParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.throttle("window_scroll")?;
+        let window = self.window(&params.0.r#ref).await?;
+        check_in_bounds(&window, params.0.x, params.0.y, params.0.allow_out_of_bounds)?;
+
+        // Convert window-relative to screen-absolute coordinates
+        let screen_x = window.geometry.x + params.0.x;
+        let screen_y = window.geometry.y + params.0.y;
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "window_coords": { "x": params.0.x, "y": params.0.y },
+            "screen_coords": { "x": screen_x, "y": screen_y },
+            "dx": params.0.dx,
+            "dy": params.0.dy
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        let (pointer_x, pointer_y) = self.pointer_point(screen_x, screen_y).await;
+        self.input.scroll(pointer_x, pointer_y, params.0.dx, params.0.dy)
+            .await
+            .map_err(|e| MarionetteError::input("Failed to scroll", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Drag with a mouse button held from one point to another within a window. Coordinates are window-relative unless coordinate_space is \"screen\".")]
+    async fn window_drag(
+        &self,
+        params: Parameters<WindowDragParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.throttle("window_drag")?;
+        let window = self.window(&params.0.r#ref).await?;
+        let (window_from, (from_x, from_y)) =
+            resolve_point(&window, params.0.from_x, params.0.from_y, params.0.coordinate_space);
+        let (window_to, (to_x, to_y)) =
+            resolve_point(&window, params.0.to_x, params.0.to_y, params.0.coordinate_space);
+        check_in_bounds(&window, window_from.0, window_from.1, params.0.allow_out_of_bounds)?;
+        check_in_bounds(&window, window_to.0, window_to.1, params.0.allow_out_of_bounds)?;
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "window_coords": {
+                "from": { "x": window_from.0, "y": window_from.1 },
+                "to": { "x": window_to.0, "y": window_to.1 }
+            },
+            "screen_coords": {
+                "from": { "x": from_x, "y": from_y },
+                "to": { "x": to_x, "y": to_y }
+            },
+            "button": params.0.button
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        let (from_x, from_y) = self.pointer_point(from_x, from_y).await;
+        let (to_x, to_y) = self.pointer_point(to_x, to_y).await;
+
+        // Tracked for the duration so shutdown can release the button if the drag is cut short
+        self.pressed_buttons.lock().unwrap_or_else(|e| e.into_inner()).push(params.0.button.clone());
+        let dragged = self.input.drag(from_x, from_y, to_x, to_y, &params.0.button).await;
+        {
+            let mut pressed = self.pressed_buttons.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(index) = pressed.iter().position(|button| *button == params.0.button) {
+                pressed.remove(index);
+            }
+        }
+        dragged.map_err(|e| MarionetteError::input("Failed to drag", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Get the pointer position in screen coordinates and the ref of the window under it")]
+    async fn cursor_position(&self) -> Result<CallToolResult, McpError> {
+        let (x, y) = self.backend.cursor_position()
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to query cursor position", e))?;
+
+        // Refresh window list so geometry containment is checked against current positions,
+        // falling back to the last snapshot if enumeration fails
+        let registry = match self.refresh_registry().await {
+            Ok(registry) => registry,
+            Err(_) => self.registry.read().await,
+        };
+
+        // The topmost window containing the point is the one under the cursor
+        let window = registry.window_at(x, y);
+
+        let result = json!({
+            "x": x,
+            "y": y,
+            "window": window.map(|w| json!({
+                "ref": w.ref_id,
+                "title": w.title,
+                "window_coords": { "x": x - w.geometry.x, "y": y - w.geometry.y }
+            }))
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Find the topmost window at a screen point, e.g. to map a point on a screen_screenshot back to a window ref. Defaults to the current pointer position.")]
+    async fn window_at_point(
+        &self,
+        params: Parameters<WindowAtPointParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let (x, y) = match (params.0.x, params.0.y) {
+            (Some(x), Some(y)) => (x, y),
+            (None, None) => self.backend.cursor_position()
+                .await
+                .map_err(|e| MarionetteError::backend("Failed to query cursor position", e))?,
+            _ => {
+                return Err(MarionetteError::invalid_params(
+                    "Only one of x and y given",
+                    Some("Provide both x and y, or neither to use the pointer position"),
+                ).into());
+            }
+        };
+
+        let registry = self.refresh_registry().await?;
+
+        let window = registry.window_at(x, y);
+
+        let result = json!({
+            "x": x,
+            "y": y,
+            "window": window.map(|w| json!({
+                "ref": w.ref_id,
+                "title": w.title,
+                "class": w.class,
+                "window_coords": { "x": x - w.geometry.x, "y": y - w.geometry.y },
+                "in_client_area": geometry::contains(&w.geometry, x, y)
+            }))
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Move the pointer to absolute screen coordinates without clicking")]
+    async fn cursor_move(
+        &self,
+        params: Parameters<CursorMoveParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.throttle("cursor_move")?;
+        let result = json!({
+            "success": true,
+            "screen_coords": { "x": params.0.x, "y": params.0.y }
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        let (pointer_x, pointer_y) = self.pointer_point(params.0.x, params.0.y).await;
+        self.input.move_to(pointer_x, pointer_y)
+            .await
+            .map_err(|e| MarionetteError::input("Failed to move cursor", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    /// Type text into the focused window (shared by window_type and window_batch)
+    async fn type_text(&self, params: WindowTypeParams) -> Result<CallToolResult, MarionetteError> {
+        self.throttle("window_type")?;
+        let mark = self.cancel_mark();
+        if !matches!(params.method.as_str(), "keyboard" | "clipboard") {
+            return Err(MarionetteError::invalid_params(
+                format!("Unknown input method: {}", params.method),
+                Some("Use \"keyboard\" or \"clipboard\""),
+            ));
+        }
+
+        let window = match &params.r#ref {
+            Some(window_ref) => Some(self.window(window_ref).await?),
+            None => None,
+        };
+
+        let non_ascii = !params.text.is_ascii();
+        let delay_ms = params.delay_ms.unwrap_or(self.type_delay_ms);
+        let mut result = json!({
+            "success": true,
+            "text_length": params.text.len(),
+            "delay_ms": delay_ms,
+            "method": params.method,
+            "ref": params.r#ref,
+            "cleared": params.clear_first
+        });
+        if non_ascii && params.method == "keyboard" {
+            result["warning"] = json!("Text contains non-ASCII characters that may not type correctly; use method \"clipboard\" if the result looks wrong");
+        }
+        if params.method == "keyboard" {
+            result["layout"] = json!(self.keyboard_layout.as_ref().map(|layout| layout.to_string()));
+            // Only ydotool maps characters to keycodes assuming US QWERTY
+            if self.input.name() == "ydotool" && self.keyboard_layout.as_ref().is_some_and(|layout| !layout.is_us_qwerty()) {
+                result["layout_warning"] = json!("The keyboard layout isn't US QWERTY, so typed characters may not match the text; use method \"clipboard\" if the result looks wrong");
+            }
+        }
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        if let Some(window) = &window {
+            self.focus_and_settle(window).await?;
+        }
+
+        if params.clear_first {
+            self.input.key_press("a", &["ctrl".to_string()])
+                .await
+                .map_err(|e| MarionetteError::input("Failed to select the field's content", e))?;
+            self.input.key_press("Delete", &[])
+                .await
+                .map_err(|e| MarionetteError::input("Failed to clear the field", e))?;
+        }
+
+        let total_chars = params.text.chars().count();
+        let typed = if params.method == "clipboard" {
+            crate::input::clipboard::paste(self.input.as_ref(), &params.text).await.map(|()| total_chars)
+        } else {
+            if non_ascii {
+                tracing::warn!(
+                    "window_type text contains non-ASCII characters which ydotool may mangle; \
+                     consider method \"clipboard\""
+                );
+            }
+            self.type_cancellable(&params.text, delay_ms, mark).await
+        };
+
+        let typed_chars = typed.map_err(|e| MarionetteError::input("Failed to type text", e))?;
+        if typed_chars < total_chars {
+            result["success"] = json!(false);
+            result["cancelled"] = json!(true);
+            result["typed_chars"] = json!(typed_chars);
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Type text into a window. Pass ref to focus that window first; otherwise types into whichever window is focused.")]
+    async fn window_type(
+        &self,
+        params: Parameters<WindowTypeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.type_text(params.0).await?)
+    }
+
+    #[tool(description = "Type text and press keys in order within one call, e.g. username, Tab, password, Return. Stops at the first failing step.")]
+    async fn window_type_sequence(
+        &self,
+        params: Parameters<WindowTypeSequenceParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_input_enabled()?;
+
+        // Parse every key combo first, so a malformed one fails before anything is typed
+        let combos = params.0.actions.iter().map(|action| match action {
+            TypeSequenceAction::Text { .. } => Ok(None),
+            TypeSequenceAction::Key { key, modifiers } => crate::input::parse_key_combo(key, modifiers).map(Some),
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        if self.dry_run {
+            let steps: Vec<serde_json::Value> = params.0.actions.iter().zip(&combos).map(|(action, combo)| {
+                match (action, combo) {
+                    (_, Some((key, modifiers))) => json!({
+                        "key": key,
+                        "modifiers": modifiers,
+                        "key_codes": self.input.key_codes(key, modifiers)
+                    }),
+                    (TypeSequenceAction::Text { text }, None) => json!({ "text_length": text.chars().count() }),
+                    (TypeSequenceAction::Key { .. }, None) => unreachable!("key steps are always parsed"),
+                }
+            }).collect();
+            return Ok(dry_run_result(json!({
+                "success": true,
+                "completed_steps": 0,
+                "steps": steps
+            })));
+        }
+
+        let mark = self.cancel_mark();
+        let delay_ms = params.0.delay_ms.unwrap_or(self.type_delay_ms);
+        for (step, (action, combo)) in params.0.actions.iter().zip(&combos).enumerate() {
+            // Each step spends a token from the standalone tool it stands in for
+            self.throttle(match action {
+                TypeSequenceAction::Text { .. } => "window_type",
+                TypeSequenceAction::Key { .. } => "window_key",
+            })?;
+            let outcome = match (action, combo) {
+                (_, Some((key, modifiers))) => self.input.key_press(key, modifiers).await,
+                (TypeSequenceAction::Text { text }, None) => {
+                    self.type_cancellable(text, delay_ms, mark).await.map(|_| ())
+                }
+                (TypeSequenceAction::Key { .. }, None) => unreachable!("key steps are always parsed"),
+            };
+
+            // A step cut short by cancel counts as not completed
+            if self.cancelled_since(mark) {
+                let result = json!({
+                    "success": false,
+                    "cancelled": true,
+                    "completed_steps": step,
+                    "total": params.0.actions.len()
+                });
+                return Ok(CallToolResult::success(vec![Content::text(
+                    serde_json::to_string_pretty(&result).unwrap(),
+                )]));
+            }
+
+            if let Err(e) = outcome {
+                let action = match action {
+                    TypeSequenceAction::Text { text } => format!("typing {} characters", text.chars().count()),
+                    TypeSequenceAction::Key { key, .. } => format!("pressing {}", key),
+                };
+                return Err(MarionetteError::input(format!("Sequence step {} failed while {}", step, action), e).into());
+            }
+        }
+
+        let result = json!({
+            "success": true,
+            "completed_steps": params.0.actions.len()
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Read the current clipboard contents as text")]
+    async fn clipboard_get(&self) -> Result<CallToolResult, McpError> {
+        let text = crate::input::clipboard::get_clipboard()
+            .await
+            .map_err(|e| MarionetteError::clipboard("Failed to read clipboard", e))?;
+
+        let result = json!({
+            "success": true,
+            "text": text,
+            "length": text.chars().count()
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Replace the clipboard contents with text")]
+    async fn clipboard_set(
+        &self,
+        params: Parameters<ClipboardSetParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let result = json!({
+            "success": true,
+            "length": params.0.text.chars().count()
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        crate::input::clipboard::set_clipboard(&params.0.text)
+            .await
+            .map_err(|e| MarionetteError::clipboard("Failed to set clipboard", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Paste text into the focused window via the clipboard and Ctrl+V. Faster than window_type for long text.")]
+    async fn window_paste(
+        &self,
+        params: Parameters<WindowPasteParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.throttle("window_paste")?;
+        let result = json!({
+            "success": true,
+            "length": params.0.text.chars().count()
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        crate::input::clipboard::paste(self.input.as_ref(), &params.0.text)
+            .await
+            .map_err(|e| MarionetteError::clipboard("Failed to paste text", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    /// Press a key combination (shared by window_key and window_batch)
+    async fn press_key(&self, params: WindowKeyParams) -> Result<CallToolResult, MarionetteError> {
+        self.throttle("window_key")?;
+        if params.repeat == 0 || params.repeat > MAX_KEY_REPEAT {
+            return Err(MarionetteError::invalid_params(
+                format!("repeat must be between 1 and {}, got {}", MAX_KEY_REPEAT, params.repeat),
+                None,
+            ));
+        }
+        if params.hold_ms.is_some() && params.repeat > 1 {
+            return Err(MarionetteError::invalid_params(
+                "hold_ms and repeat cannot be combined",
+                Some("Use hold_ms for a key held for a duration, or repeat for separate presses"),
+            ));
+        }
+        let hold_ms = params.hold_ms.map(|ms| ms.min(MAX_KEY_HOLD_MS));
+
+        let (key, modifiers) = crate::input::parse_key_combo(&params.key, &params.modifiers)?;
+        let mut result = json!({
+            "success": true,
+            "key": key,
+            "modifiers": modifiers,
+            "repeat": params.repeat,
+            "hold_ms": hold_ms
+        });
+        if self.dry_run {
+            result["key_codes"] = json!(self.input.key_codes(&key, &modifiers));
+            return Ok(dry_run_result(result));
+        }
+
+        if let Some(hold_ms) = hold_ms {
+            // Releasing at the end would also end the window_key_down hold
+            if self.held_keys.lock().await.iter().any(|held| held.key.eq_ignore_ascii_case(&key)) {
+                return Err(MarionetteError::invalid_params(
+                    format!("{} is already held with window_key_down", key),
+                    Some("Release it with window_key_up first, or leave it held"),
+                ));
+            }
+
+            self.input.key_down(&key, &modifiers)
+                .await
+                .map_err(|e| MarionetteError::input("Failed to press key down", e))?;
+
+            // Held like window_key_down for the duration, so shutdown or cancel can release it
+            let mark = self.cancel_mark();
+            self.held_keys.lock().await.push(HeldKey { key: key.clone(), modifiers: modifiers.clone() });
+            let started = std::time::Instant::now();
+            let completed = self.sleep_cancellable(std::time::Duration::from_millis(hold_ms), mark).await;
+            if !completed {
+                result["success"] = json!(false);
+                result["cancelled"] = json!(true);
+                result["held_ms"] = json!(started.elapsed().as_millis() as u64);
+            }
+
+            // release_pressed may have released it already; don't send a second key up
+            let still_held = {
+                let mut held_keys = self.held_keys.lock().await;
+                let index = held_keys.iter().position(|held| held.key.eq_ignore_ascii_case(&key));
+                index.map(|index| held_keys.remove(index))
+            };
+            if let Some(held) = still_held
+                && let Err(e) = self.input.key_up(&key, &modifiers).await
+            {
+                self.held_keys.lock().await.push(held);
+                return Err(MarionetteError::input("Failed to release key", e));
+            }
+        } else {
+            let mark = self.cancel_mark();
+            for press in 0..params.repeat {
+                if press > 0 {
+                    tokio::time::sleep(std::time::Duration::from_millis(params.repeat_delay_ms)).await;
+                }
+                if self.cancelled_since(mark) {
+                    result["success"] = json!(false);
+                    result["cancelled"] = json!(true);
+                    result["presses"] = json!(press);
+                    break;
+                }
+                self.input.key_press(&key, &modifiers)
+                    .await
+                    .map_err(|e| MarionetteError::input(format!("Failed to press key (press {} of {})", press + 1, params.repeat), e))?;
+            }
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Press a key or key combination")]
+    async fn window_key(
+        &self,
+        params: Parameters<WindowKeyParams>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.press_key(params.0).await?)
+    }
+
+    #[tool(description = "Press and hold a key (with optional modifiers) until window_key_up or key_release_all. Use for games and other held-key input.")]
+    async fn window_key_down(
+        &self,
+        params: Parameters<WindowKeyHoldParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.throttle("window_key_down")?;
+        let (key, modifiers) = crate::input::parse_key_combo(&params.0.key, &params.0.modifiers)?;
+
+        if self.dry_run {
+            return Ok(dry_run_result(json!({
+                "success": true,
+                "key": key,
+                "modifiers": modifiers,
+                "key_codes": self.input.key_codes(&key, &modifiers),
+                "held_keys": *self.held_keys.lock().await
+            })));
+        }
+
+        // Not under the held_keys lock, so cancel and shutdown don't wait on the input tool
+        self.input.key_down(&key, &modifiers)
+            .await
+            .map_err(|e| MarionetteError::input("Failed to press key down", e))?;
+
+        let mut held_keys = self.held_keys.lock().await;
+        if !held_keys.iter().any(|held| held.key.eq_ignore_ascii_case(&key)) {
+            held_keys.push(HeldKey {
+                key: key.clone(),
+                modifiers: modifiers.clone(),
+            });
+        }
+
+        let result = json!({
+            "success": true,
+            "key": key,
+            "modifiers": modifiers,
+            "held_keys": *held_keys
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Release a key previously held with window_key_down, along with the modifiers it was pressed with. Releasing a key that isn't held does nothing.")]
+    async fn window_key_up(
+        &self,
+        params: Parameters<WindowKeyUpParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut held_keys = self.held_keys.lock().await;
+
+        // Never-pressed keys are a no-op rather than an error
+        let Some(index) = held_keys.iter().position(|held| held.key.eq_ignore_ascii_case(&params.0.key)) else {
+            let result = json!({
+                "success": true,
+                "key": params.0.key,
+                "was_held": false,
+                "held_keys": *held_keys
+            });
+            return Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&result).unwrap(),
+            )]));
+        };
+
+        let held = held_keys[index].clone();
+        if self.dry_run {
+            return Ok(dry_run_result(json!({
+                "success": true,
+                "key": held.key,
+                "modifiers": held.modifiers,
+                "key_codes": self.input.key_codes(&held.key, &held.modifiers),
+                "was_held": true,
+                "held_keys": *held_keys
+            })));
+        }
+
+        self.input.key_up(&held.key, &held.modifiers)
+            .await
+            .map_err(|e| MarionetteError::input("Failed to release key", e))?;
+
+        held_keys.remove(index);
+
+        let result = json!({
+            "success": true,
+            "key": held.key,
+            "modifiers": held.modifiers,
+            "was_held": true,
+            "held_keys": *held_keys
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Release every key currently held by window_key_down, e.g. to recover from stuck keys")]
+    async fn key_release_all(&self) -> Result<CallToolResult, McpError> {
+        let mut held_keys = self.held_keys.lock().await;
+
+        if self.dry_run {
+            let released: Vec<&HeldKey> = held_keys.iter().rev().collect();
+            return Ok(dry_run_result(json!({
+                "success": true,
+                "released": released
+            })));
+        }
+
+        // Release in reverse press order, continuing past failures so as many keys as possible come up
+        let mut released = Vec::new();
+        let mut errors = Vec::new();
+        while let Some(held) = held_keys.pop() {
+            match self.input.key_up(&held.key, &held.modifiers).await {
+                Ok(()) => released.push(held),
+                Err(e) => errors.push(format!("{}: {}", held.key, e)),
+            }
+        }
+
+        if !errors.is_empty() {
+            let released: Vec<&str> = released.iter().map(|held| held.key.as_str()).collect();
+            return Err(MarionetteError::input(
+                format!("Failed to release some keys (released: [{}])", released.join(", ")),
+                anyhow::anyhow!(errors.join("; ")),
+            ).into());
+        }
+
+        let result = json!({
+            "success": true,
+            "released": released
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Emergency stop: cancel in-flight typing, key sequences, key repeats, and batches, and release every held key and mouse button. Cancelled calls return early with cancelled set and how far they got.")]
+    async fn cancel(&self) -> Result<CallToolResult, McpError> {
+        self.cancel_generation.fetch_add(1, Ordering::SeqCst);
+        tracing::info!("Cancelling in-flight input operations");
+
+        let (released_buttons, released_keys) = self.release_pressed().await;
+
+        let result = json!({
+            "success": true,
+            "released_keys": released_keys,
+            "released_buttons": released_buttons
+        });
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    /// Move a window to absolute coordinates (shared by window_move and window_batch)
+    async fn move_window(&self, params: WindowMoveParams) -> Result<CallToolResult, MarionetteError> {
+        let window = self.window(&params.r#ref).await?;
+
+        let mut result = json!({
+            "success": true,
+            "ref": params.r#ref,
+            "new_position": { "x": params.x, "y": params.y }
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.move_window(&window.platform_id, params.x, params.y)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to move window", e))?;
+        if let Some(geometry) = self.refresh_geometry(&window).await {
+            result["geometry"] = json!({ "x": geometry.x, "y": geometry.y, "width": geometry.width, "height": geometry.height });
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Move window to a new position")]
+    async fn window_move(
+        &self,
+        params: Parameters<WindowMoveParams>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.move_window(params.0).await?)
+    }
+
+    #[tool(description = "Switch to another virtual desktop by zero-based index. Use window_list to see desktop_current, desktop_count, and each window's desktop.")]
+    async fn desktop_switch(
+        &self,
+        params: Parameters<DesktopSwitchParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let desktops = self.backend.desktop_info()
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to read desktops", e))?;
+
+        if params.0.desktop >= desktops.count {
+            return Err(MarionetteError::invalid_params(
+                format!("Desktop {} out of range (count: {})", params.0.desktop, desktops.count),
+                Some("Use a desktop index below desktop_count from window_list"),
+            ).into());
+        }
+
+        let result = json!({
+            "success": true,
+            "desktop": params.0.desktop,
+            "previous_desktop": desktops.current
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.switch_desktop(params.0.desktop)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to switch desktop", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Move a window to another virtual desktop by zero-based index")]
+    async fn window_move_to_desktop(
+        &self,
+        params: Parameters<WindowMoveToDesktopParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+
+        let desktops = self.backend.desktop_info()
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to read desktops", e))?;
+
+        if params.0.desktop >= desktops.count {
+            return Err(MarionetteError::invalid_params(
+                format!("Desktop {} out of range (count: {})", params.0.desktop, desktops.count),
+                Some("Use a desktop index below desktop_count from window_list"),
+            ).into());
+        }
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "desktop": params.0.desktop
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.move_window_to_desktop(&window.platform_id, params.0.desktop)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to move window to desktop", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Move a window onto a monitor, at the monitor's top-left corner plus an optional offset. The window is kept fully on that monitor where it fits.")]
+    async fn window_move_to_monitor(
+        &self,
+        params: Parameters<WindowMoveToMonitorParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+
+        let monitors = self.backend.list_monitors()
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to list monitors", e))?;
+        let monitor = find_monitor(&monitors, &params.0.monitor).ok_or_else(|| {
+            MarionetteError::invalid_params(
+                format!("Monitor not found: {}", params.0.monitor),
+                Some("Use an index or name from monitor_list"),
+            )
+        })?;
+
+        let bounds = monitor.geometry();
+        let (x, y) = geometry::clamp_position(
+            bounds.x.saturating_add(params.0.offset_x),
+            bounds.y.saturating_add(params.0.offset_y),
+            window.geometry.width,
+            window.geometry.height,
+            &bounds,
+        );
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "monitor": monitor.name,
+            "new_position": { "x": x, "y": y }
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.move_window(&window.platform_id, x, y)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to move window", e))?;
+        self.refresh_geometry(&window).await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Center a window on the monitor it currently overlaps most")]
+    async fn window_center(
+        &self,
+        params: Parameters<WindowCenterParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+        let monitor = self.window_monitor(&window).await?;
+
+        let (x, y) = geometry::centered_position(window.geometry.width, window.geometry.height, &monitor.geometry());
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "monitor": monitor.name,
+            "new_position": { "x": x, "y": y }
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.move_window(&window.platform_id, x, y)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to move window", e))?;
+        self.refresh_geometry(&window).await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Snap a window to half or a quarter of the monitor it is on (left, right, top, bottom, top-left, top-right, bottom-left, bottom-right), or fill it (full)")]
+    async fn window_snap(
+        &self,
+        params: Parameters<WindowSnapParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+        let monitor = self.window_monitor(&window).await?;
+
+        let target = geometry::snap_geometry(params.0.position, &monitor.geometry());
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "monitor": monitor.name,
+            "geometry": {
+                "x": target.x,
+                "y": target.y,
+                "width": target.width,
+                "height": target.height
+            }
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.move_window(&window.platform_id, target.x, target.y)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to move window", e))?;
+        self.backend.resize_window(&window.platform_id, target.width, target.height)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to resize window", e))?;
+        self.refresh_geometry(&window).await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    /// Resize a window (shared by window_resize and window_batch)
+    async fn resize_window(&self, params: WindowResizeParams) -> Result<CallToolResult, MarionetteError> {
+        let window = self.window(&params.r#ref).await?;
+
+        let mut result = json!({
+            "success": true,
+            "ref": params.r#ref,
+            "new_size": { "width": params.width, "height": params.height }
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.resize_window(&window.platform_id, params.width, params.height)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to resize window", e))?;
+        // Non-resizable windows and size hints can make the window manager clamp the request
+        if let Some(geometry) = self.refresh_geometry(&window).await {
+            result["geometry"] = json!({ "x": geometry.x, "y": geometry.y, "width": geometry.width, "height": geometry.height });
+            result["clamped"] = json!(geometry.width != params.width || geometry.height != params.height);
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Resize window")]
+    async fn window_resize(
+        &self,
+        params: Parameters<WindowResizeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.resize_window(params.0).await?)
+    }
+
+    #[tool(description = "Move a window by a relative offset from its current position, e.g. dx: 50 to move it 50px right")]
+    async fn window_move_by(
+        &self,
+        params: Parameters<WindowMoveByParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+
+        // Deltas apply to the geometry cached by the last window_list refresh
+        let x = window.geometry.x.saturating_add(params.0.dx);
+        let y = window.geometry.y.saturating_add(params.0.dy);
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "previous_position": { "x": window.geometry.x, "y": window.geometry.y },
+            "new_position": { "x": x, "y": y }
         });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.move_window(&window.platform_id, x, y)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to move window", e))?;
+        self.refresh_geometry(&window).await;
 
         Ok(CallToolResult::success(vec![Content::text(
             serde_json::to_string_pretty(&result).unwrap(),
         )]))
     }
 
-    #[tool(description = "Get detailed snapshot of a specific window's current state")]
-    async fn window_snapshot(
+    #[tool(description = "Resize a window by a relative amount from its current size, e.g. dwidth: -100 to make it 100px narrower. Sizes never go below 1x1.")]
+    async fn window_resize_by(
         &self,
-        params: Parameters<WindowSnapshotParams>,
+        params: Parameters<WindowResizeByParams>,
     ) -> Result<CallToolResult, McpError> {
-        let registry = self.registry.read().await;
+        let window = self.window(&params.0.r#ref).await?;
 
-        match registry.get_window(&params.0.r#ref) {
-            Some(window) => {
-                let result = json!({
-                    "ref": window.ref_id,
-                    "title": window.title,
-                    "class": window.class,
-                    "geometry": {
-                        "x": window.geometry.x,
-                        "y": window.geometry.y,
-                        "width": window.geometry.width,
-                        "height": window.geometry.height
-                    },
-                    "focused": window.focused,
-                    "visible": window.visible,
-                    "platform_id": format!("{:?}", window.platform_id)
-                });
+        // Work in i64 so a large negative delta can't wrap the unsigned size
+        let resize = |size: u32, delta: i32| {
+            (size as i64 + delta as i64).clamp(MIN_WINDOW_SIZE as i64, u32::MAX as i64) as u32
+        };
+        let width = resize(window.geometry.width, params.0.dwidth);
+        let height = resize(window.geometry.height, params.0.dheight);
 
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap(),
-                )]))
-            }
-            None => {
-                Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Window not found",
-                    "ref": params.0.r#ref,
-                    "suggestion": "Run window_list to get current window references"
-                }).to_string())]))
-            }
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "previous_size": { "width": window.geometry.width, "height": window.geometry.height },
+            "new_size": { "width": width, "height": height }
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
         }
+
+        self.backend.resize_window(&window.platform_id, width, height)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to resize window", e))?;
+        self.refresh_geometry(&window).await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Focus/activate a specific window, bringing it to the foreground")]
-    async fn window_focus(
+    #[tool(description = "Minimize (iconify) a window")]
+    async fn window_minimize(
         &self,
-        params: Parameters<WindowFocusParams>,
+        params: Parameters<WindowStateParams>,
     ) -> Result<CallToolResult, McpError> {
-        let registry = self.registry.read().await;
-
-        let window = match registry.get_window(&params.0.r#ref) {
-            Some(w) => w.clone(),
-            None => {
-                return Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Window not found",
-                    "ref": params.0.r#ref,
-                    "suggestion": "Run window_list to get current window references"
-                }).to_string())]));
-            }
-        };
-        drop(registry);
+        self.change_window_state(&params.0.r#ref, WindowStateAction::Minimize).await
+    }
 
-        match self.backend.focus_window(&window.platform_id).await {
-            Ok(()) => {
-                let result = json!({
-                    "success": true,
-                    "ref": params.0.r#ref,
-                    "title": window.title,
-                    "message": format!("Focused window: {}", window.title)
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap(),
-                )]))
-            }
-            Err(e) => {
-                Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Failed to focus window",
-                    "ref": params.0.r#ref,
-                    "details": e.to_string()
-                }).to_string())]))
-            }
-        }
+    #[tool(description = "Maximize a window. Calling this on an already maximized window leaves it maximized.")]
+    async fn window_maximize(
+        &self,
+        params: Parameters<WindowStateParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.change_window_state(&params.0.r#ref, WindowStateAction::Maximize).await
     }
 
-    #[tool(description = "Capture a screenshot of a specific window")]
-    async fn window_screenshot(
+    #[tool(description = "Restore a minimized or maximized window to its normal state")]
+    async fn window_restore(
         &self,
-        params: Parameters<WindowScreenshotParams>,
+        params: Parameters<WindowStateParams>,
     ) -> Result<CallToolResult, McpError> {
-        let registry = self.registry.read().await;
+        self.change_window_state(&params.0.r#ref, WindowStateAction::Restore).await
+    }
 
-        let window = match registry.get_window(&params.0.r#ref) {
-            Some(w) => w.clone(),
-            None => {
-                return Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Window not found",
-                    "ref": params.0.r#ref,
-                    "suggestion": "Run window_list to get current window references"
-                }).to_string())]));
-            }
-        };
-        drop(registry);
+    #[tool(description = "Raise a window to the top of the stacking order without focusing it, so keyboard input keeps going to the focused window")]
+    async fn window_raise(
+        &self,
+        params: Parameters<WindowRestackParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
 
-        match crate::screenshot::capture_window(&window.platform_id).await {
-            Ok(image_data) => {
-                if params.0.format == "file" {
-                    // Save to temp file
-                    let path = std::env::temp_dir().join(format!("marionette_{}_{}.png", params.0.r#ref, std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
-                    if let Err(e) = std::fs::write(&path, &image_data) {
-                        return Ok(CallToolResult::error(vec![Content::text(json!({
-                            "error": "Failed to save screenshot",
-                            "details": e.to_string()
-                        }).to_string())]));
-                    }
-                    let result = json!({
-                        "success": true,
-                        "ref": params.0.r#ref,
-                        "path": path.to_string_lossy(),
-                        "size_bytes": image_data.len()
-                    });
-                    Ok(CallToolResult::success(vec![Content::text(
-                        serde_json::to_string_pretty(&result).unwrap(),
-                    )]))
-                } else {
-                    // Return base64
-                    let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &image_data);
-                    Ok(CallToolResult::success(vec![
-                        Content::image(base64_data, "image/png")
-                    ]))
-                }
-            }
-            Err(e) => {
-                Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Failed to capture screenshot",
-                    "ref": params.0.r#ref,
-                    "details": e.to_string()
-                }).to_string())]))
-            }
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "title": window.title
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
         }
+
+        self.backend.raise_window(&window.platform_id)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to raise window", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Click at coordinates within a window")]
-    async fn window_click(
+    #[tool(description = "Lower a window to the bottom of the stacking order without changing focus")]
+    async fn window_lower(
         &self,
-        params: Parameters<WindowClickParams>,
+        params: Parameters<WindowRestackParams>,
     ) -> Result<CallToolResult, McpError> {
-        let registry = self.registry.read().await;
+        let window = self.window(&params.0.r#ref).await?;
 
-        let window = match registry.get_window(&params.0.r#ref) {
-            Some(w) => w.clone(),
-            None => {
-                return Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Window not found",
-                    "ref": params.0.r#ref,
-                    "suggestion": "Run window_list to get current window references"
-                }).to_string())]));
-            }
-        };
-        drop(registry);
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "title": window.title
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
 
-        // Convert window-relative to screen-absolute coordinates
-        let screen_x = window.geometry.x + params.0.x;
-        let screen_y = window.geometry.y + params.0.y;
+        self.backend.lower_window(&window.platform_id)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to lower window", e))?;
 
-        match crate::input::click(screen_x, screen_y, &params.0.button).await {
-            Ok(()) => {
-                let result = json!({
-                    "success": true,
-                    "ref": params.0.r#ref,
-                    "window_coords": { "x": params.0.x, "y": params.0.y },
-                    "screen_coords": { "x": screen_x, "y": screen_y },
-                    "button": params.0.button,
-                    "description": params.0.description
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap(),
-                )]))
-            }
-            Err(e) => {
-                Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Failed to click",
-                    "details": e.to_string()
-                }).to_string())]))
-            }
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
+    }
+
+    #[tool(description = "Make a window fullscreen or leave fullscreen. Unlike maximize, fullscreen removes decorations and covers panels. Sets the state explicitly, so repeating a call has no further effect.")]
+    async fn window_fullscreen(
+        &self,
+        params: Parameters<WindowFullscreenParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let window = self.window(&params.0.r#ref).await?;
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "fullscreen": params.0.enabled
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
         }
+
+        self.backend.set_fullscreen(&window.platform_id, params.0.enabled)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to change fullscreen state", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Type text into the focused window")]
-    async fn window_type(
+    #[tool(description = "Keep a window above all other windows (always on top), or turn that off. Sets the state explicitly, so repeating a call has no further effect.")]
+    async fn window_pin(
         &self,
-        params: Parameters<WindowTypeParams>,
+        params: Parameters<WindowPinParams>,
     ) -> Result<CallToolResult, McpError> {
-        match crate::input::type_text(&params.0.text, params.0.delay_ms).await {
-            Ok(()) => {
-                let result = json!({
-                    "success": true,
-                    "text_length": params.0.text.len(),
-                    "delay_ms": params.0.delay_ms
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap(),
-                )]))
-            }
-            Err(e) => {
-                Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Failed to type text",
-                    "details": e.to_string()
-                }).to_string())]))
-            }
+        let window = self.window(&params.0.r#ref).await?;
+
+        let result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "always_on_top": params.0.enabled
+        });
+        if self.dry_run {
+            return Ok(dry_run_result(result));
         }
+
+        self.backend.set_always_on_top(&window.platform_id, params.0.enabled)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to change always-on-top state", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Press a key or key combination")]
-    async fn window_key(
+    #[tool(description = "Set a window's opacity from 0.0 (transparent) to 1.0 (opaque). X11 windows only; needs a running compositor to have a visible effect.")]
+    async fn window_opacity(
         &self,
-        params: Parameters<WindowKeyParams>,
+        params: Parameters<WindowOpacityParams>,
     ) -> Result<CallToolResult, McpError> {
-        match crate::input::key_press(&params.0.key, &params.0.modifiers).await {
-            Ok(()) => {
-                let result = json!({
-                    "success": true,
-                    "key": params.0.key,
-                    "modifiers": params.0.modifiers
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap(),
-                )]))
-            }
-            Err(e) => {
-                Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Failed to press key",
-                    "details": e.to_string()
-                }).to_string())]))
-            }
+        if params.0.opacity.is_nan() {
+            return Err(MarionetteError::invalid_params("Opacity must be a number", Some("Use a value from 0.0 to 1.0")).into());
+        }
+
+        let window = self.window(&params.0.r#ref).await?;
+        let opacity = params.0.opacity.clamp(0.0, 1.0);
+
+        let mut result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "opacity": opacity,
+            "note": "Opacity only takes effect while a compositor is running"
+        });
+        if opacity != params.0.opacity {
+            result["requested_opacity"] = json!(params.0.opacity);
         }
+        if self.dry_run {
+            return Ok(dry_run_result(result));
+        }
+
+        self.backend.set_opacity(&window.platform_id, opacity)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to set window opacity", e))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
     }
 
-    #[tool(description = "Move window to a new position")]
-    async fn window_move(
+    #[tool(description = "Run several actions (click, type, key, move, resize, focus, screenshot) in order in one call. Stops at the first failure unless continue_on_error is set. Returns one result per step; screenshot images and resource links follow the JSON.")]
+    async fn window_batch(
         &self,
-        params: Parameters<WindowMoveParams>,
+        params: Parameters<WindowBatchParams>,
     ) -> Result<CallToolResult, McpError> {
-        let registry = self.registry.read().await;
+        let total = params.0.actions.len();
+        let mut steps = Vec::with_capacity(total);
+        let mut attachments = Vec::new();
+        let mut failed = 0;
+        let mark = self.cancel_mark();
+        let mut cancelled = false;
 
-        let window = match registry.get_window(&params.0.r#ref) {
-            Some(w) => w.clone(),
-            None => {
-                return Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Window not found",
-                    "ref": params.0.r#ref,
-                    "suggestion": "Run window_list to get current window references"
-                }).to_string())]));
+        for (step, action) in params.0.actions.into_iter().enumerate() {
+            if self.cancelled_since(mark) {
+                cancelled = true;
+                break;
             }
-        };
-        drop(registry);
+            let name = action.name();
+            // Batching must not become a way around the tool filter
+            let outcome = if !self.tool_router.has_route(action.tool()) {
+                Err(MarionetteError::tool_disabled(action.tool()))
+            } else {
+                match action {
+                    BatchAction::Click(p) => self.click(p).await,
+                    BatchAction::Type(p) => self.type_text(p).await,
+                    BatchAction::Key(p) => self.press_key(p).await,
+                    BatchAction::Move(p) => self.move_window(p).await,
+                    BatchAction::Resize(p) => self.resize_window(p).await,
+                    BatchAction::Focus(p) => self.focus(p).await,
+                    BatchAction::Screenshot(p) => self.screenshot_window(p).await,
+                }
+            };
 
-        match self.backend.move_window(&window.platform_id, params.0.x, params.0.y).await {
-            Ok(()) => {
-                let result = json!({
-                    "success": true,
-                    "ref": params.0.r#ref,
-                    "new_position": { "x": params.0.x, "y": params.0.y }
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap(),
-                )]))
-            }
-            Err(e) => {
-                Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Failed to move window",
-                    "details": e.to_string()
-                }).to_string())]))
+            match outcome {
+                Ok(result) => {
+                    // Keep JSON results inline and move images and resource links after the summary
+                    let mut value = serde_json::Value::Null;
+                    let mut image_count = 0;
+                    let mut link_count = 0;
+                    for content in result.content {
+                        if let Some(text) = content.as_text() {
+                            value = serde_json::from_str(&text.text)
+                                .unwrap_or_else(|_| json!(text.text));
+                            continue;
+                        }
+                        if content.as_image().is_some() {
+                            image_count += 1;
+                        } else {
+                            link_count += 1;
+                        }
+                        attachments.push(content);
+                    }
+
+                    let mut entry = json!({ "step": step, "action": name, "success": true, "result": value });
+                    if image_count > 0 {
+                        entry["images"] = json!(image_count);
+                    }
+                    if link_count > 0 {
+                        entry["resource_links"] = json!(link_count);
+                    }
+                    steps.push(entry);
+                }
+                Err(e) => {
+                    failed += 1;
+                    steps.push(json!({
+                        "step": step,
+                        "action": name,
+                        "success": false,
+                        "code": e.code(),
+                        "error": e.to_string()
+                    }));
+                    if !params.0.continue_on_error {
+                        break;
+                    }
+                }
             }
         }
+
+        let summary = json!({
+            "success": failed == 0 && !cancelled,
+            "steps_run": steps.len(),
+            "total": total,
+            "failed": failed,
+            "cancelled": cancelled,
+            "steps": steps
+        });
+
+        let mut content = vec![Content::text(serde_json::to_string_pretty(&summary).unwrap())];
+        content.extend(attachments);
+        Ok(CallToolResult::success(content))
     }
 
-    #[tool(description = "Resize window")]
-    async fn window_resize(
+    #[tool(description = "Close a window. By default asks the window to close gracefully; set force to kill its client.")]
+    async fn window_close(
         &self,
-        params: Parameters<WindowResizeParams>,
+        params: Parameters<WindowCloseParams>,
     ) -> Result<CallToolResult, McpError> {
-        let registry = self.registry.read().await;
+        let window = self.window(&params.0.r#ref).await?;
 
-        let window = match registry.get_window(&params.0.r#ref) {
-            Some(w) => w.clone(),
-            None => {
-                return Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Window not found",
-                    "ref": params.0.r#ref,
-                    "suggestion": "Run window_list to get current window references"
-                }).to_string())]));
-            }
-        };
-        drop(registry);
+        if self.dry_run {
+            return Ok(dry_run_result(json!({
+                "success": true,
+                "ref": params.0.r#ref,
+                "title": window.title,
+                "force": params.0.force
+            })));
+        }
 
-        match self.backend.resize_window(&window.platform_id, params.0.width, params.0.height).await {
-            Ok(()) => {
-                let result = json!({
-                    "success": true,
-                    "ref": params.0.r#ref,
-                    "new_size": { "width": params.0.width, "height": params.0.height }
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap(),
-                )]))
-            }
-            Err(e) => {
-                Ok(CallToolResult::error(vec![Content::text(json!({
-                    "error": "Failed to resize window",
-                    "details": e.to_string()
-                }).to_string())]))
-            }
+        let outcome = self.backend.close_window(&window.platform_id, params.0.force)
+            .await
+            .map_err(|e| MarionetteError::backend("Failed to close window", e))?;
+
+        let method = match outcome.method {
+            CloseMethod::Graceful => "WM_DELETE_WINDOW",
+            CloseMethod::Force => "kill_client",
+        };
+        let mut result = json!({
+            "success": true,
+            "ref": params.0.r#ref,
+            "title": window.title,
+            "method": method,
+            "force": params.0.force
+        });
+        if outcome.method == CloseMethod::Force && !outcome.graceful_supported {
+            result["note"] = json!("Window does not support WM_DELETE_WINDOW, fell back to kill_client");
         }
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&result).unwrap(),
+        )]))
     }
 }
 
-#[tool_handler]
 impl ServerHandler for MarionetteServer {
     fn get_info(&self) -> ServerInfo {
+        let mut instructions = "Marionette provides window manipulation tools for Linux desktops. \
+             Use window_list to discover windows, then use the returned refs (w0, w1, ...) \
+             with other tools for screenshots, input, and window management.".to_string();
+        if self.dry_run {
+            instructions.push_str(
+                " Dry-run mode is on: input and window-management tools report what they would do \
+                 (with dry_run: true) without doing it."
+            );
+        }
+
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
             capabilities: ServerCapabilities::builder()
+                .enable_resources()
+                .enable_resources_subscribe()
                 .enable_tools()
                 .build(),
             server_info: Implementation::from_build_env(),
-            instructions: Some(
-                "Marionette provides window manipulation tools for Linux desktops. \
-                 Use window_list to discover windows, then use the returned refs (w0, w1, ...) \
-                 with other tools for screenshots, input, and window management.".to_string()
-            ),
+            instructions: Some(instructions),
         }
     }
 
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(log) = &self.action_log else {
+            let tcc = ToolCallContext::new(self, request, context);
+            return self.tool_router.call(tcc).await;
+        };
+
+        let tool = request.name.clone();
+        let params = request.arguments.clone();
+        let start = std::time::Instant::now();
+
+        let tcc = ToolCallContext::new(self, request, context);
+        let outcome = self.tool_router.call(tcc).await;
+
+        log.record(&tool, params.as_ref(), &outcome, start.elapsed());
+        outcome
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
     async fn initialize(
         &self,
         _request: InitializeRequestParam,
@@ -548,4 +4048,173 @@ impl ServerHandler for MarionetteServer {
     ) -> Result<InitializeResult, McpError> {
         Ok(self.get_info())
     }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let mut resource = RawResource::new(WINDOWS_RESOURCE_URI, "windows");
+        resource.description = Some(
+            "All windows with their refs and metadata, as returned by window_list. \
+             Subscribe to be notified when windows appear, close, or change.".to_string()
+        );
+        resource.mime_type = Some("application/json".to_string());
+
+        // Per-window resources reflect the last enumeration, like cached refs do
+        let registry = self.registry.read().await;
+        let mut resources = vec![resource.no_annotation()];
+        for window in registry.windows() {
+            let mut resource = RawResource::new(window_resource_uri(&window.ref_id), window.ref_id.clone());
+            resource.description = Some(format!("{} ({})", window.title, window.class));
+            resource.mime_type = Some("application/json".to_string());
+            resources.push(resource.no_annotation());
+        }
+        drop(registry);
+
+        let screenshots = self.screenshots.lock().unwrap_or_else(|e| e.into_inner());
+        for (id, screenshot) in screenshots.iter() {
+            let mut resource = RawResource::new(format!("{}{}", SCREENSHOT_RESOURCE_PREFIX, id), format!("screenshot-{}", id));
+            resource.description = Some(format!(
+                "Screenshot of {} ({}x{})",
+                screenshot.source, screenshot.width, screenshot.height
+            ));
+            resource.mime_type = Some(screenshot.mime_type.to_string());
+            resource.size = Some(screenshot.data.len() as u32);
+            resources.push(resource.no_annotation());
+        }
+
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        let template = RawResourceTemplate {
+            uri_template: WINDOW_RESOURCE_TEMPLATE.to_string(),
+            name: "window".to_string(),
+            title: None,
+            description: Some(
+                "One window's metadata, as returned by window_snapshot. Accepts a ref or an alias.".to_string()
+            ),
+            mime_type: Some("application/json".to_string()),
+        };
+        let screenshot_template = RawResourceTemplate {
+            uri_template: SCREENSHOT_RESOURCE_TEMPLATE.to_string(),
+            name: "screenshot".to_string(),
+            title: None,
+            description: Some(
+                "An image captured by window_screenshot with format \"resource\". Only recent captures are kept.".to_string()
+            ),
+            mime_type: None,
+        };
+
+        Ok(ListResourceTemplatesResult::with_all_items(vec![
+            template.no_annotation(),
+            screenshot_template.no_annotation(),
+        ]))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        if let Some(id) = request.uri.strip_prefix(SCREENSHOT_RESOURCE_PREFIX) {
+            let screenshot = self.screenshots.lock().unwrap_or_else(|e| e.into_inner()).get(id);
+            let Some(screenshot) = screenshot else {
+                return Err(McpError::resource_not_found(
+                    format!("Screenshot not found: {}", id),
+                    Some(json!({ "suggestion": "Only recent screenshots are kept; take a new one with window_screenshot" })),
+                ));
+            };
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::BlobResourceContents {
+                    uri: request.uri,
+                    mime_type: Some(screenshot.mime_type.to_string()),
+                    blob: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &screenshot.data),
+                    meta: None,
+                }],
+            });
+        }
+
+        let window_ref = request.uri.strip_prefix(WINDOWS_RESOURCE_URI).and_then(|rest| rest.strip_prefix('/'));
+        if request.uri != WINDOWS_RESOURCE_URI && window_ref.is_none_or(str::is_empty) {
+            return Err(McpError::resource_not_found(
+                format!("Unknown resource: {}", request.uri),
+                None,
+            ));
+        }
+
+        let registry = self.refresh_registry().await?;
+
+        let result = match window_ref {
+            Some(window_ref) => {
+                let Some(window) = registry.get_window(window_ref) else {
+                    return Err(McpError::resource_not_found(
+                        format!("Window not found: {}", window_ref),
+                        None,
+                    ));
+                };
+                let mut result = window_json(window);
+                result["aliases"] = json!(registry.aliases_of(&window.ref_id));
+                result["platform_id"] = json!(format!("{:?}", window.platform_id));
+                result
+            }
+            None => {
+                // Same defaults as window_list without parameters
+                let include_types: Vec<String> = DEFAULT_WINDOW_TYPES.iter().map(|t| t.to_string()).collect();
+                let windows: Vec<(WindowHandle, Vec<String>)> = registry
+                    .windows()
+                    .into_iter()
+                    .filter(|w| window_type_allowed(w, &include_types, &[]))
+                    .map(|w| (w.clone(), registry.aliases_of(&w.ref_id)))
+                    .collect();
+                let snapshot_version = registry.version();
+                drop(registry);
+                self.window_list_json(windows, snapshot_version, false).await
+            }
+        };
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                mime_type: Some("application/json".to_string()),
+                text: serde_json::to_string_pretty(&result).unwrap(),
+                meta: None,
+            }],
+        })
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        if request.uri != WINDOWS_RESOURCE_URI {
+            return Err(McpError::resource_not_found(
+                format!("Unknown resource: {}", request.uri),
+                None,
+            ));
+        }
+
+        let mut subscribers = self.windows_subscribers.lock().await;
+        if !subscribers.iter().any(|peer| same_peer(peer, &context.peer)) {
+            subscribers.push(context.peer);
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        if request.uri == WINDOWS_RESOURCE_URI {
+            self.windows_subscribers.lock().await.retain(|peer| !same_peer(peer, &context.peer));
+        }
+        Ok(())
+    }
 }